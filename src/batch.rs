@@ -0,0 +1,314 @@
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use futures::stream::{self, BoxStream};
+use futures::StreamExt;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use crate::client::Client;
+use crate::error::{Error, self};
+use crate::nomad::dispatch::{self, DispatchPayload};
+use crate::nomad::job::Job;
+use crate::nomad::meta;
+use crate::nomad::spec::Spec;
+use crate::schema::JobSchema;
+
+// Constant
+/// Column of a row containing the path of the file used as the payload of the dispatch
+pub const PAYLOAD_COLUMN: &str = "_payload";
+const RESULTS_SUFFIX: &str = "results.jsonl";
+
+/// Row of a batch file. Each row is dispatched as a separate job
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Row {
+    /// Position of the row in the batch file starting from 1
+    pub index: usize,
+    pub metas: HashMap<String, String>,
+    pub payload: Option<PathBuf>
+}
+
+/// Outcome of the dispatch of a row, written in the results file. The value of the secret metas are redacted
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct RowResult {
+    pub row: usize,
+    pub metas: HashMap<String, String>,
+    /// Hash of the metas before redaction, so that a change of a secret meta is detected
+    #[serde(default)]
+    pub hash: String,
+    #[serde(rename = "DispatchedJobID", default, skip_serializing_if = "Option::is_none")]
+    pub dispatched_job_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>
+}
+
+/// Dispatch a parameterized job once for every row of a batch file
+pub struct Batch<'a> {
+    client: &'a Client,
+    job: &'a Job,
+    spec: &'a Spec,
+    schema: Option<&'a JobSchema>,
+    idempotent: bool,
+    secrets: HashSet<String>
+}
+
+/// Read the rows of a csv or a jsonl file. Empty values are ignored so that the default of the meta is used.
+/// The path of the payload is resolved from the directory of the batch file
+///
+/// # Arguments
+///
+/// * `path` - &Path
+pub fn read_rows(path: &Path) -> Result<Vec<Row>, Error> {
+    let to_err = |msg: String| Error::Batch(format!("{}: {msg}", path.display()));
+    let records = match path.extension().and_then(|e| e.to_str()) {
+        Some("csv") => read_csv(path).map_err(to_err)?,
+        Some("jsonl" | "ndjson") => read_jsonl(path).map_err(to_err)?,
+        _ => return Err(to_err(error::BATCH_FORMAT_ERR.to_string()))
+    };
+
+    let dir = path.parent().unwrap_or_else(|| Path::new(""));
+    let rows = records.into_iter()
+        .enumerate()
+        .map(|(idx, mut metas)| {
+            metas.retain(|_, value| !value.is_empty());
+            let payload = metas.remove(PAYLOAD_COLUMN).map(|p| dir.join(p));
+
+            Row { index: idx + 1, metas, payload }
+        })
+        .collect();
+
+    Ok(rows)
+}
+
+/// Read a csv file where the header contains the name of the metas
+///
+/// # Arguments
+///
+/// * `path` - &Path
+fn read_csv(path: &Path) -> Result<Vec<HashMap<String, String>>, String> {
+    let mut reader = csv::Reader::from_path(path).map_err(|err| err.to_string())?;
+    let headers = reader.headers().map_err(|err| err.to_string())?.clone();
+
+    reader.records()
+        .map(|record| {
+            let record = record.map_err(|err| err.to_string())?;
+            Ok(headers.iter()
+                .zip(record.iter())
+                .map(|(key, value)| (key.to_string(), value.to_string()))
+                .collect())
+        })
+        .collect()
+}
+
+/// Read a jsonl file where each line is an object of metas. Values which are not strings are
+/// converted to their json representation
+///
+/// # Arguments
+///
+/// * `path` - &Path
+fn read_jsonl(path: &Path) -> Result<Vec<HashMap<String, String>>, String> {
+    let content = fs::read_to_string(path).map_err(|err| err.to_string())?;
+
+    content.lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| {
+            let object: Map<String, Value> = serde_json::from_str(line)
+                .map_err(|err| format!("{} {err}", error::BATCH_ROW_ERR))?;
+
+            Ok(object.into_iter()
+                .filter_map(|(key, value)| match value {
+                    Value::Null => None,
+                    Value::String(value) => Some((key, value)),
+                    value => Some((key, value.to_string()))
+                })
+                .collect())
+        })
+        .collect()
+}
+
+/// Return the default path of the results file of a batch file, e.g. inputs.results.jsonl for inputs.csv
+///
+/// # Arguments
+///
+/// * `path` - &Path
+pub fn results_path(path: &Path) -> PathBuf {
+    path.with_extension(RESULTS_SUFFIX)
+}
+
+/// Read the results of a previous run. The last result of a row takes precedence
+///
+/// # Arguments
+///
+/// * `path` - &Path
+pub fn read_results(path: &Path) -> Result<HashMap<usize, RowResult>, Error> {
+    if !path.exists() {
+        return Ok(HashMap::new());
+    }
+
+    let to_err = |msg: String| Error::Batch(format!("{}: {msg}", path.display()));
+    let content = fs::read_to_string(path).map_err(|err| to_err(err.to_string()))?;
+
+    content.lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| {
+            serde_json::from_str::<RowResult>(line)
+                .map(|res| (res.row, res))
+                .map_err(|err| to_err(err.to_string()))
+        })
+        .collect()
+}
+
+/// Return the rows which have not been dispatched yet. A row is dispatched again when it failed
+/// or when its metas have changed since the previous run, secret metas included
+///
+/// # Arguments
+///
+/// * `rows` - Vec<Row>
+/// * `results` - &HashMap<usize, RowResult>
+pub fn pending_rows(rows: Vec<Row>, results: &HashMap<usize, RowResult>) -> Vec<Row> {
+    rows.into_iter()
+        .filter(|row| match results.get(&row.index) {
+            Some(res) => res.dispatched_job_id.is_none() || res.hash != dispatch::metas_hash(&row.metas),
+            None => true
+        })
+        .collect()
+}
+
+/// Open the results file in append mode so that a run can be resumed
+///
+/// # Arguments
+///
+/// * `path` - &Path
+pub fn open_results(path: &Path) -> Result<File, Error> {
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .map_err(|err| Error::Batch(format!("{}: {err}", path.display())))
+}
+
+/// Append a result to the results file
+///
+/// # Arguments
+///
+/// * `file` - &mut File
+/// * `res` - &RowResult
+pub fn write_result(file: &mut File, res: &RowResult) -> Result<(), Error> {
+    let line = serde_json::to_string(res).map_err(|err| Error::Serialize(err.to_string()))?;
+
+    writeln!(file, "{line}").map_err(|err| Error::Batch(err.to_string()))
+}
+
+impl<'a> Batch<'a> {
+    /// Create a new batch for a job. The spec should already contain the schema of the job
+    ///
+    /// # Arguments
+    ///
+    /// * `client` - &Client
+    /// * `job` - &Job
+    /// * `spec` - &Spec
+    /// * `schema` - Option<&JobSchema>
+    pub fn new(client: &'a Client, job: &'a Job, spec: &'a Spec, schema: Option<&'a JobSchema>) -> Self {
+        Batch { client, job, spec, schema, idempotent: false, secrets: HashSet::new() }
+    }
+
+    /// Derive an idempotency token from the metas and the payload of each row so that a row
+    /// is never dispatched twice by nomad
+    ///
+    /// # Arguments
+    ///
+    /// * `self` - Self
+    /// * `idempotent` - bool
+    pub fn with_idempotency_from_metas(self, idempotent: bool) -> Self {
+        Batch { idempotent, ..self }
+    }
+
+    /// Set the metas whose value is redacted from the results
+    ///
+    /// # Arguments
+    ///
+    /// * `self` - Self
+    /// * `secrets` - HashSet<String>
+    pub fn with_secrets(self, secrets: HashSet<String>) -> Self {
+        Batch { secrets, ..self }
+    }
+
+    /// Return the job dispatched by the batch
+    ///
+    /// # Arguments
+    ///
+    /// * `&self` - Batch
+    pub fn job(&self) -> &Job {
+        self.job
+    }
+
+    /// Return the metas whose value is redacted from the results
+    ///
+    /// # Arguments
+    ///
+    /// * `&self` - Batch
+    pub fn secrets(&self) -> &HashSet<String> {
+        &self.secrets
+    }
+
+    /// Dispatch the rows with at most `concurrency` dispatches in flight. Results are returned
+    /// in the order in which the dispatches complete
+    ///
+    /// # Arguments
+    ///
+    /// * `&self` - Batch
+    /// * `rows` - Vec<Row>
+    /// * `concurrency` - usize
+    pub fn run(&'a self, rows: Vec<Row>, concurrency: usize) -> BoxStream<'a, RowResult> {
+        stream::iter(rows)
+            .map(move |row| self.dispatch(row))
+            .buffer_unordered(concurrency.max(1))
+            .boxed()
+    }
+
+    /// Validate the metas of a row and dispatch it. Errors are reported in the result of the row
+    ///
+    /// # Arguments
+    ///
+    /// * `&self` - Batch
+    /// * `row` - Row
+    pub async fn dispatch(&self, row: Row) -> RowResult {
+        let res = self.dispatch_row(&row).await;
+
+        RowResult {
+            row: row.index,
+            metas: meta::redact_metas(&row.metas, &self.secrets),
+            hash: dispatch::metas_hash(&row.metas),
+            dispatched_job_id: res.as_ref().ok().cloned(),
+            error: res.err().map(|err| err.to_string())
+        }
+    }
+
+    /// Dispatch a row and return the id of the dispatched job. A row already dispatched with the same
+    /// idempotency token returns the id of the existing job
+    ///
+    /// # Arguments
+    ///
+    /// * `&self` - Batch
+    /// * `row` - &Row
+    async fn dispatch_row(&self, row: &Row) -> Result<String, Error> {
+        let metas = self.spec.resolve_metas(row.metas.clone())?;
+        self.client.register_secrets(&self.secrets);
+
+        // the payload file may not be text, e.g. an archive
+        let payload = match &row.payload {
+            Some(path) => Some(fs::read(path)
+                .map_err(|err| Error::Batch(format!("{}: {err}", path.display())))?),
+            None => self.schema.and_then(|s| s.render_payload(&metas)).map(String::into_bytes)
+        };
+
+        let token = self.idempotent.then(|| dispatch::idempotency_token(&self.job.id, &metas, payload.as_deref()));
+        let payload = DispatchPayload::from_bytes(metas, payload)?.with_idempotency_token(token);
+
+        match self.client.send_dispatch(self.job, &payload).await {
+            Ok(res) => Ok(res.dispatch_id),
+            Err(Error::AlreadyDispatched { job }) => Ok(job),
+            Err(err) => Err(err)
+        }
+    }
+}
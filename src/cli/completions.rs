@@ -0,0 +1,163 @@
+use std::env;
+use std::io;
+use std::path::PathBuf;
+use clap::{Args, ValueEnum};
+use clap_complete::engine::CompletionCandidate;
+use clap_complete::env::Shells;
+use async_trait::async_trait;
+use chrono::Duration;
+use noumead::Client;
+use noumead::completion::{self, CompletionCache, CompletionKind};
+use noumead::config::{ConnectionArgs, TokenSources};
+use noumead::error::Error;
+use noumead::rest::RestOptions;
+use super::Run;
+
+// constant
+/// Environment variable set by the registration script when the shell ask for the completions
+pub const COMPLETE_ENV: &str = "NOUMEAD_COMPLETE";
+const BIN_NAME: &str = "noumead";
+const CACHE_TTL_SECONDS: i64 = 60;
+const CONNECT_TIMEOUT_SECONDS: u64 = 2;
+const REQUEST_TIMEOUT_SECONDS: u64 = 5;
+const COMMAND_LINE_SEPARATOR: &str = "--";
+const NOMAD_URL_FLAGS: &[&str] = &["--nomad-url", "-n"];
+const TOKEN_FLAGS: &[&str] = &["--token", "-t"];
+const TOKEN_FILE_FLAGS: &[&str] = &["--token-file"];
+const CONTEXT_FLAGS: &[&str] = &["--context"];
+const JOB_FLAGS: &[&str] = &["--job", "-j"];
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+enum Shell {
+    Bash,
+    Zsh,
+    Fish,
+    Powershell
+}
+
+#[derive(Args, Debug)]
+pub struct CompletionsArgs {
+    /// Shell for which the completion script is generated
+    #[arg(value_enum)]
+    shell: Shell
+}
+
+#[async_trait]
+impl Run for CompletionsArgs {
+    async fn run(&self, _: &super::Cli) -> Result<(), Error> {
+        let name = self.shell.to_possible_value()
+            .map(|value| value.get_name().to_string())
+            .unwrap_or_default();
+
+        let shells = Shells::builtins();
+        let Some(shell) = shells.completer(&name) else {
+            return Err(Error::ScenarioErr(format!("unsupported shell {name}")));
+        };
+
+        // the script call noumead back with the completion variable set, which let clap
+        // complete the arguments along with the values fetched from Nomad
+        shell.write_registration(COMPLETE_ENV, BIN_NAME, BIN_NAME, BIN_NAME, &mut io::stdout())
+            .map_err(|err| Error::ScenarioErr(err.to_string()))
+    }
+}
+
+/// Complete the id of the parameterized jobs
+pub fn jobs() -> Vec<CompletionCandidate> {
+    candidates(CompletionKind::Jobs, None)
+}
+
+/// Complete the name of the namespaces
+pub fn namespaces() -> Vec<CompletionCandidate> {
+    candidates(CompletionKind::Namespaces, None)
+}
+
+/// Complete the name of the tasks of the parameterized jobs, or of the job given with `--job`
+pub fn tasks() -> Vec<CompletionCandidate> {
+    candidates(CompletionKind::Tasks, typed(JOB_FLAGS).as_deref())
+}
+
+/// Complete the name of the contexts of the config
+pub fn contexts() -> Vec<CompletionCandidate> {
+    let Ok(config) = super::load_config() else {
+        return Vec::new();
+    };
+
+    config.contexts
+        .into_iter()
+        .map(|(name, context)| CompletionCandidate::new(name).help(context.address.map(Into::into)))
+        .collect()
+}
+
+/// Return the value given to a flag on the command line being completed. The shell passes the command line
+/// after a `--` argument
+///
+/// # Arguments
+///
+/// * `flags` - &[&str]
+fn typed(flags: &[&str]) -> Option<String> {
+    let words: Vec<String> = env::args()
+        .skip_while(|arg| arg != COMMAND_LINE_SEPARATOR)
+        .skip(1)
+        .collect();
+
+    completion::typed_value(&words, flags)
+}
+
+/// Return the address and the token of the Nomad server along with the options of the connection. They are
+/// resolved as the command being completed would, from its flags, then from the environment, then from its
+/// context or the default context. The completion runs on every TAB, so the token command of the context,
+/// which may prompt the user, is never run and only the token cached by a login is read
+fn connection() -> Option<(String, Option<String>, RestOptions)> {
+    let args = ConnectionArgs {
+        context: typed(CONTEXT_FLAGS),
+        address: typed(NOMAD_URL_FLAGS),
+        token: typed(TOKEN_FLAGS),
+        token_file: typed(TOKEN_FILE_FLAGS).map(PathBuf::from),
+        headers: Vec::new()
+    };
+
+    let config = super::load_config().unwrap_or_default();
+    let connection = config.connection(args, |var| env::var(var).ok(), TokenSources::Cached).ok()?;
+
+    // the shell waits for the candidates, an unreachable Nomad must not block it
+    let options = RestOptions {
+        headers: connection.headers,
+        connect_timeout: Some(std::time::Duration::from_secs(CONNECT_TIMEOUT_SECONDS)),
+        request_timeout: Some(std::time::Duration::from_secs(REQUEST_TIMEOUT_SECONDS)),
+        ..Default::default()
+    };
+
+    Some((connection.address?, connection.token, options))
+}
+
+/// Fetch the candidates through the cache. The completion is run before the runtime of the cli is started,
+/// so a dedicated runtime is used. Errors are ignored as nothing can be shown to the user while completing
+///
+/// # Arguments
+///
+/// * `kind` - CompletionKind
+/// * `job` - Option<&str>
+fn candidates(kind: CompletionKind, job: Option<&str>) -> Vec<CompletionCandidate> {
+    let Some(dir) = completion::default_dir() else {
+        return Vec::new();
+    };
+
+    let Some((address, token, options)) = connection() else {
+        return Vec::new();
+    };
+
+    let Ok(client) = Client::new(Some(address.clone()), token, options) else {
+        return Vec::new();
+    };
+
+    let Ok(runtime) = tokio::runtime::Builder::new_current_thread().enable_all().build() else {
+        return Vec::new();
+    };
+
+    let cache = CompletionCache::new(dir, Duration::seconds(CACHE_TTL_SECONDS));
+    runtime.block_on(cache.candidates(&client, &address, kind, job))
+        .unwrap_or_default()
+        .into_iter()
+        .map(|candidate| CompletionCandidate::new(candidate.value).help(candidate.help.map(Into::into)))
+        .collect()
+}
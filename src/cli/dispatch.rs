@@ -1,66 +1,376 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
 use clap::Args;
+use clap_complete::engine::ArgValueCandidates;
 use async_trait::async_trait;
+use futures::StreamExt;
+use indicatif::{ProgressBar, ProgressStyle};
+use noumead::batch::{self, Batch};
+use noumead::error::{Error, self};
+use noumead::Client;
+use noumead::nomad::acl::Grant;
+use noumead::nomad::api;
+use noumead::nomad::dispatch::{self, DispatchPayload, DispatchRes};
+use noumead::nomad::job::{self, Job};
+use noumead::nomad::meta;
+use noumead::nomad::spec;
 use crate::inquiry;
-use crate::log::Logger;
-use crate::error::{Error, self};
-use crate::nomad::{self, job::Job};
-use super::Run;
+use crate::output::{BatchOutput, DispatchOutput, DryRunOutput, Formatter};
+use super::{completions, Run};
+use super::source::{self, MetaSource};
+
+// constant
+const PROGRESS_TEMPLATE: &str = "{bar:40.cyan/blue} {pos}/{len} {msg}";
+const DRY_RUN_PAYLOAD_LIMIT: usize = 2048;
+const NOMAD_ADDR_VAR: &str = "$NOMAD_ADDR";
 
 #[derive(Args, Debug)]
 pub struct DispatchArgs {
-    #[arg(short, long)]
+    #[arg(short, long, conflicts_with = "from_file")]
     follow: bool,
+
+    /// Resolve the metas and the payload, then print the request instead of sending it
+    #[arg(long, conflicts_with_all = ["follow", "from_file"])]
+    dry_run: bool,
+
+    /// Id of the job to dispatch. The job is selected from a list when omitted
+    #[arg(short, long, add = ArgValueCandidates::new(completions::jobs))]
+    job: Option<String>,
+
+    /// Namespace of the job, required when jobs of several namespaces share the id. The jobs listed
+    /// for the selection are limited to this namespace
+    #[arg(long, add = ArgValueCandidates::new(completions::namespaces))]
+    namespace: Option<String>,
+
+    /// Task whose logs are followed. The task is selected from a list when omitted
+    #[arg(long, requires = "follow", add = ArgValueCandidates::new(completions::tasks))]
+    task: Option<String>,
+
+    /// Value of a meta formatted as key=value, or key=env:VAR to read it from an environment variable.
+    /// When provided, the metas are not prompted
+    #[arg(short, long = "meta", value_parser = source::parse_meta, conflicts_with = "from_file")]
+    metas: Vec<(String, MetaSource)>,
+
+    /// Value of a meta read from a file, formatted as key=@path. The value is treated as a secret
+    #[arg(long = "meta-file", value_parser = source::parse_meta_file, conflicts_with = "from_file")]
+    meta_files: Vec<(String, MetaSource)>,
+
+    /// Meta whose value is masked when prompted and redacted from the output
+    #[arg(long = "secret-meta")]
+    secret_metas: Vec<String>,
+
+    /// Token used by Nomad to dispatch the job only once, e.g. when a CI job is retried
+    #[arg(long, conflicts_with_all = ["idempotency_from_metas", "from_file"])]
+    idempotency_token: Option<String>,
+
+    /// Derive the idempotency token from a hash of the job, the metas and the payload
+    #[arg(long)]
+    idempotency_from_metas: bool,
+
+    /// Csv or jsonl file where each row contains the metas of a dispatch. The _payload column
+    /// contains the path of the file used as the payload
+    #[arg(long)]
+    from_file: Option<PathBuf>,
+
+    /// Maximum number of dispatches sent at the same time
+    #[arg(long, default_value_t = 4, value_parser = clap::value_parser!(u16).range(1..), requires = "from_file")]
+    concurrency: u16,
+
+    /// File where the result of each row is written. Rows already dispatched are skipped when the batch
+    /// is run again. Default to <file>.results.jsonl
+    #[arg(long, requires = "from_file")]
+    results: Option<PathBuf>
 }
 
 #[async_trait]
 impl Run for DispatchArgs {
-    async fn run(&self, cli: &super::Cli) -> Result<(), crate::error::Error> {
+    async fn run(&self, cli: &super::Cli) -> Result<(), Error> {
+        let client = cli.client()?;
+        let formatter = cli.formatter();
         // filter the job to only get the parameterized job
-        let jobs: Vec<Job> = nomad::job::get_nomad_job_list(&cli.rest_handler)
+        let jobs: Vec<Job> = client.parameterized_jobs()
             .await?
             .into_iter()
-            .filter(|j| j.parameterized)
+            .filter(|j| self.namespace.as_ref().is_none_or(|ns| &j.namespace == ns))
             .collect();
 
-        let (_, idx) = inquiry::select(&jobs, "Select the job that you want to dispatch")?;
+        let job = match &self.job {
+            Some(id) => job::find_job(&jobs, self.namespace.as_deref(), id)?,
+            None if jobs.is_empty() => return Err(Error::ScenarioErr(error::SELECTED_JOB_NOT_FOUND_ERR.to_string())),
+            None => inquiry::select_job(&jobs, &cli.usage(), "Select the job that you want to dispatch")?
+        };
+
+        // warn before prompting the metas when the token can't dispatch the job. Errors are ignored
+        // as the ACLs might be disabled
+        if let Ok(Grant::Denied | Grant::NotGranted) = client.dispatch_grant(&job.namespace).await {
+            formatter.warn(&format!("{} {}", error::DISPATCH_NOT_GRANTED_WARN, job.namespace));
+        }
+
+        // the schema of the job complete or override the metas conventions of the job
+        let schema = cli.schema()?;
+        let job_schema = schema.as_ref().and_then(|s| s.job(&job.id));
 
-        let Some(job) = jobs.get(idx) else {
-            return Err(Error::ScenarioErr(error::SELECTED_JOB_NOT_FOUND_ERR.to_string()));
+        let spec = client.spec(job).await?;
+        let spec = match job_schema {
+            Some(job_schema) => job_schema.apply(spec),
+            None => spec
         };
 
-        let (required, optionals) = job.get_job_meta(&cli.rest_handler).await?;
+        // secrets are marked by the schema, the convention of the job, the command line or read from a file or a variable
+        let sources: Vec<&(String, MetaSource)> = self.metas.iter().chain(&self.meta_files).collect();
+        let mut secrets: HashSet<String> = spec::secret_keys(&spec.metas)
+            .into_iter()
+            .chain(self.secret_metas.iter().cloned())
+            .chain(sources.iter().filter(|(_, s)| s.is_secret()).map(|(key, _)| key.clone()))
+            .collect();
+
+        if let Some(path) = &self.from_file {
+            let runner = Batch::new(client, job, &spec, job_schema)
+                .with_idempotency_from_metas(self.idempotency_from_metas)
+                .with_secrets(secrets);
+
+            let res = self.batch(&formatter, runner, path).await;
+            cli.record_usage(job);
+
+            return res;
+        }
+
+        // the metas provided on the command line are validated the same way as the prompted ones.
+        // A prompted value can reference a variable as well, var: alone opens a picker. The values
+        // read from the variables are validated against the metas once resolved
+        let sources: Vec<(String, MetaSource)> = match sources.is_empty() {
+            true => {
+                let mut fields = spec.meta_fields()?;
+                for field in fields.iter_mut() {
+                    field.secret |= secrets.contains(&field.name);
+                }
+
+                // the source of the typed metas is only asked when there are variables to read from
+                let variables = client.variables(&job.namespace, "").await.is_ok_and(|v| !v.is_empty());
 
-        let mut required_value = match required {
-            Some(metas) => inquiry::prompt_vector(metas, "Input the required value for", true)?,
-            None => HashMap::new()
+                let mut sources = Vec::new();
+                for (key, value) in inquiry::prompt_metas(&fields, variables)? {
+                    let source = match (value == source::VAR_PREFIX, source::parse_var(&value)) {
+                        (true, _) => pick_variable(client, &job.namespace).await?,
+                        (_, Some(var)) => var,
+                        _ => MetaSource::Value(value)
+                    };
+
+                    sources.push((key, source));
+                }
+
+                sources
+            },
+            false => sources.into_iter().cloned().collect()
         };
 
-        let optional_value = match optionals {
-            Some(metas) => inquiry::prompt_vector(metas, "Input value for optional", false)?,
-            None => HashMap::new()
+        let mut metas = HashMap::new();
+        for (key, source) in &sources {
+            if source.is_secret() {
+                secrets.insert(key.clone());
+            }
+
+            metas.insert(key.clone(), source.resolve(key, client, &job.namespace).await?);
+        }
+        let metas = spec.resolve_metas(metas)?;
+
+        // secret values are redacted from the debug logs
+        client.register_secrets(&secrets);
+
+        let render = |metas: &HashMap<String, String>| job_schema.and_then(|s| s.render_payload(metas));
+        let payload = render(&metas);
+        let token = match (&self.idempotency_token, self.idempotency_from_metas) {
+            (Some(token), _) => Some(token.clone()),
+            (None, true) => Some(dispatch::idempotency_token(&job.id, &metas, payload.as_deref().map(str::as_bytes))),
+            (None, false) => None
         };
+        let payload = DispatchPayload::new(metas.clone(), payload)?.with_idempotency_token(token);
 
-        // merge the required_value with the optional_value
-        required_value.extend(optional_value.into_iter());
+        if self.dry_run {
+            return formatter.emit(&dry_run(cli.nomad_url(), job, &payload.redacted(&secrets, render)?)?);
+        }
 
-        // dispatch the job
-        let dispatch_res = job.dispatch_job(&cli.rest_handler, required_value).await?;
-        Logger::info(&format!("Job with id: {} has been dispatched", dispatch_res.dispatch_id));
+        // dispatch the job. A job already dispatched with the same token is reused
+        let (dispatch_res, existing) = match client.send_dispatch(job, &payload).await {
+            Ok(res) => (res, false),
+            Err(Error::AlreadyDispatched { job }) => (DispatchRes { eval_id: String::new(), dispatch_id: job }, true),
+            Err(err) => return Err(err)
+        };
+        cli.record_usage(job);
+
+        formatter.emit(&DispatchOutput {
+            job: &job.id,
+            namespace: &job.namespace,
+            metas: &meta::redact_metas(&metas, &secrets),
+            existing,
+            res: &dispatch_res
+        })?;
 
         // follow the log of the job dispatch
         if self.follow {
-            let alloc = nomad::alloc::Allocation::fetch_single_alloc(&dispatch_res.dispatch_id, &cli.rest_handler).await?;
+            let alloc = client.allocation(&job.namespace, &dispatch_res.dispatch_id).await?;
             let tasks_name = alloc.get_tasks_name();
 
             // ask for the list of task to choose
-            let (selected_task, _) = inquiry::select(&tasks_name, "Select the task to log")?;
-            // get the logs for the targeted allocations
-            alloc.get_allocation_logs(&selected_task, &cli.rest_handler).await?;
+            let selected_task = match &self.task {
+                Some(task) => tasks_name.iter().find(|name| name.as_str() == task.as_str()).ok_or(Error::MissingTask)?,
+                None => inquiry::select(&tasks_name, "Select the task to log")?
+            };
+            // print the logs of the targeted allocations
+            let mut logs = client.follow_logs(&alloc, selected_task);
+            while let Some(event) = logs.next().await {
+                formatter.emit(&event?)?;
+            }
+
+            // wait for the dispatched job to be completed
+            let status = client.wait_for_dead_job(&job.namespace, &dispatch_res.dispatch_id).await?;
+            formatter.emit(&status)?;
         }
 
-        Logger::info("Job has been dispatched");
+        formatter.info("Job has been dispatched");
 
         Ok(())
     }
 }
+
+impl DispatchArgs {
+    /// Dispatch the job for every row of the batch file which has not been dispatched yet
+    ///
+    /// # Arguments
+    ///
+    /// * `&self` - DispatchArgs
+    /// * `formatter` - &Formatter
+    /// * `runner` - Batch
+    /// * `path` - &Path
+    async fn batch(&self, formatter: &Formatter, runner: Batch<'_>, path: &Path) -> Result<(), Error> {
+        let rows = batch::read_rows(path)?;
+        let results_path = self.results.clone().unwrap_or_else(|| batch::results_path(path));
+        let previous = batch::read_results(&results_path)?;
+
+        let total = rows.len();
+        let pending = batch::pending_rows(rows, &previous);
+        let skipped = total - pending.len();
+
+        let progress = ProgressBar::new(pending.len() as u64);
+        if let Ok(style) = ProgressStyle::with_template(PROGRESS_TEMPLATE) {
+            progress.set_style(style);
+        }
+
+        // results are appended as soon as a row is dispatched so that the batch can be resumed after a failure
+        let mut file = batch::open_results(&results_path)?;
+        let mut results = runner.run(pending, self.concurrency as usize);
+
+        let (mut dispatched, mut failed) = (0, 0);
+        while let Some(res) = results.next().await {
+            batch::write_result(&mut file, &res)?;
+            match res.error {
+                Some(_) => failed += 1,
+                None => dispatched += 1
+            }
+
+            progress.set_message(format!("dispatched: {dispatched} failed: {failed}"));
+            progress.inc(1);
+        }
+        progress.finish_and_clear();
+
+        formatter.emit(&BatchOutput {
+            job: &runner.job().id,
+            results: &results_path,
+            dispatched,
+            failed,
+            skipped
+        })?;
+
+        match failed {
+            0 => Ok(()),
+            _ => Err(Error::ScenarioErr(error::BATCH_FAILED_ERR.to_string()))
+        }
+    }
+}
+
+/// Describe the dispatch request along with the equivalent nomad and curl commands
+///
+/// # Arguments
+///
+/// * `nomad_url` - Option<&str>
+/// * `job` - &Job
+/// * `payload` - &DispatchPayload
+fn dry_run(nomad_url: Option<&str>, job: &Job, payload: &DispatchPayload) -> Result<DryRunOutput, Error> {
+    let endpoint = api::dispatch_endpoint(&job.namespace, &job.id);
+    let url = format!("{}/{endpoint}", nomad_url.unwrap_or(NOMAD_ADDR_VAR));
+    let body = serde_json::to_string(payload).map_err(|err| Error::Serialize(err.to_string()))?;
+    let decoded = payload.decoded_payload().unwrap_or_default();
+
+    // metas are sorted so that the commands are stable
+    let mut metas: Vec<(&String, &String)> = payload.metas.iter().collect();
+    metas.sort();
+
+    let mut nomad = vec![format!("nomad job dispatch -namespace {}", shell_quote(&job.namespace))];
+    nomad.extend(metas.iter().map(|(k, v)| format!("-meta {}", shell_quote(&format!("{k}={v}")))));
+    if let Some(token) = &payload.idempotency_token {
+        nomad.push(format!("-idempotency-token {}", shell_quote(token)));
+    }
+    nomad.push(format!("{} -", shell_quote(&job.id)));
+
+    let curl = format!(
+        "curl -X POST -H \"X-Nomad-Token: $NOMAD_TOKEN\" --data {} \"{url}\"",
+        shell_quote(&body)
+    );
+
+    Ok(DryRunOutput {
+        method: "POST",
+        url,
+        job: job.id.clone(),
+        namespace: job.namespace.clone(),
+        metas: payload.metas.clone(),
+        idempotency_token: payload.idempotency_token.clone(),
+        payload_size: decoded.len(),
+        payload: truncate(&decoded, DRY_RUN_PAYLOAD_LIMIT),
+        nomad: format!("printf %s {} | {}", shell_quote(&decoded), nomad.join(" ")),
+        curl
+    })
+}
+
+/// Quote a value so that it can be pasted in a shell
+///
+/// # Arguments
+///
+/// * `value` - &str
+fn shell_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "'\\''"))
+}
+
+/// Truncate a value to a maximum number of bytes without splitting a character
+///
+/// # Arguments
+///
+/// * `value` - &str
+/// * `limit` - usize
+fn truncate(value: &str, limit: usize) -> String {
+    if value.len() <= limit {
+        return value.to_string();
+    }
+
+    let end = (0..=limit).rev().find(|idx| value.is_char_boundary(*idx)).unwrap_or(0);
+    format!("{}... ({} bytes)", &value[..end], value.len())
+}
+
+/// Select a variable of the namespace and one of its items
+///
+/// # Arguments
+///
+/// * `client` - &Client
+/// * `namespace` - &str
+async fn pick_variable(client: &Client, namespace: &str) -> Result<MetaSource, Error> {
+    let path = super::var::select_path(client, namespace, "").await?;
+    let variable = client.variable(namespace, &path).await?;
+
+    // only the name of the items are shown, never their value
+    let items: Vec<&String> = variable.items.keys().collect();
+    let item = match items.len() {
+        1 => None,
+        _ => Some(inquiry::select(&items, "Select the item of the variable")?.to_string())
+    };
+
+    Ok(MetaSource::Var { path, item })
+}
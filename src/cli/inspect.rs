@@ -0,0 +1,27 @@
+use clap::Args;
+use clap_complete::engine::ArgValueCandidates;
+use async_trait::async_trait;
+use noumead::error::Error;
+use noumead::nomad::namespace::DEFAULT_NAMESPACE;
+use super::{completions, Run};
+
+#[derive(Args, Debug)]
+pub struct InspectArgs {
+    /// Id of the job to inspect
+    #[arg(add = ArgValueCandidates::new(completions::jobs))]
+    job: String,
+
+    /// Namespace of the job
+    #[arg(long, default_value = DEFAULT_NAMESPACE, add = ArgValueCandidates::new(completions::namespaces))]
+    namespace: String
+}
+
+#[async_trait]
+impl Run for InspectArgs {
+    async fn run(&self, cli: &super::Cli) -> Result<(), Error> {
+        let client = cli.client()?;
+        let spec = client.inspect(&self.namespace, &self.job).await?;
+
+        cli.formatter().emit(&spec)
+    }
+}
@@ -0,0 +1,88 @@
+use std::path::PathBuf;
+use clap::Args;
+use async_trait::async_trait;
+use noumead::config;
+use noumead::error::{self, Error};
+use noumead::login::{CallbackListener, DEFAULT_CALLBACK_PORT};
+use noumead::nomad::acl::LoginToken;
+use crate::inquiry;
+use super::Run;
+
+#[derive(Args, Debug)]
+pub struct LoginArgs {
+    /// Auth method used to obtain a short-lived token. By default the `auth_method` of the context.
+    /// Without auth method, the token is prompted
+    #[arg(long)]
+    method: Option<String>,
+
+    /// Exchange the JWT of the file with a JWT auth method instead of logging in with OIDC
+    #[arg(long)]
+    jwt_file: Option<PathBuf>,
+
+    /// Print the url of the OIDC provider without opening the browser
+    #[arg(long)]
+    no_browser: bool,
+
+    /// Port of the local listener receiving the redirection of the OIDC provider
+    #[arg(long, default_value_t = DEFAULT_CALLBACK_PORT)]
+    callback_port: u16
+}
+
+#[async_trait]
+impl Run for LoginArgs {
+    async fn run(&self, cli: &super::Cli) -> Result<(), Error> {
+        let Some(context) = cli.context() else {
+            return Err(Error::Config(error::MISSING_CONTEXT_ERR.to_string()));
+        };
+
+        let config = super::load_config()?;
+        let method = self.method.clone()
+            .or_else(|| config.contexts.get(context).and_then(|c| c.auth_method.clone()));
+
+        let Some(method) = method else {
+            let token = inquiry::prompt_token(context)?;
+            config::store_token(context, &token)?;
+            cli.formatter().info(&format!("The token of the context {context} has been stored in the keyring"));
+
+            return Ok(());
+        };
+
+        let token = self.login(cli, &method).await?;
+        config::store_login_token(context, &token)?;
+
+        let expiration = token.expiration_time
+            .map(|time| format!(" until {}", time.to_rfc3339()))
+            .unwrap_or_default();
+        cli.formatter().info(&format!("Logged in with {method}, the token of the context {context} is cached{expiration}"));
+
+        Ok(())
+    }
+}
+
+impl LoginArgs {
+    /// Obtain a token from the auth method, with the JWT of the file or through the OIDC provider
+    ///
+    /// # Arguments
+    ///
+    /// * `&self` - LoginArgs
+    /// * `cli` - &super::Cli
+    /// * `method` - &str
+    async fn login(&self, cli: &super::Cli, method: &str) -> Result<LoginToken, Error> {
+        let client = cli.anonymous_client()?;
+        if let Some(path) = &self.jwt_file {
+            let jwt = config::token_from_file(path)?;
+            return client.jwt_login(method, &jwt).await;
+        }
+
+        let listener = CallbackListener::bind(self.callback_port).await?;
+        let formatter = cli.formatter();
+
+        client.oidc_login(method, listener, |url| {
+            formatter.info(&format!("Complete the login in the browser at {url}"));
+            if !self.no_browser {
+                // the url has been printed, which is enough when no browser can be opened
+                let _ = open::that(url);
+            }
+        }).await
+    }
+}
@@ -1,39 +1,112 @@
 use std::env;
-use clap::{Parser, Subcommand};
+use std::path::PathBuf;
+use std::time::Duration;
+use clap::{CommandFactory, Parser, Subcommand};
+use clap_complete::CompleteEnv;
 use async_trait::async_trait;
-use crate::error::Error;
-use crate::rest::RestHandler;
+use clap_complete::engine::ArgValueCandidates;
+use noumead::{Client, Error};
+use noumead::config::{self, Config, ConnectionArgs, TokenSources};
+use noumead::rest::{DebugHook, RestOptions};
+use noumead::nomad::job::Job;
+use noumead::nomad::meta::SecretKeys;
+use noumead::schema::Schema;
+use noumead::usage::{self, Usage};
+use crate::log::Logger;
+use crate::output::{Formatter, OutputFormat};
 
+mod completions;
 mod dispatch;
+mod inspect;
+mod login;
+mod schema;
+pub mod source;
+mod status;
 mod stop;
+mod tui;
+mod var;
+mod whoami;
 
 // constant
-const NOMAD_ADDR_ENV: &str = "NOMAD_ADDR";
-const NOMAD_TOKEN_ENV: &str = "NOMAD_TOKEN";
+const HEADER_SEPARATOR: char = ':';
 
 #[derive(Parser, Debug)]
 #[command(author, version, about)]
 struct Args {
+    /// Address of Nomad, an http(s) url or the unix socket of the agent such as unix:///var/run/nomad.sock
     #[arg(short, long)]
     nomad_url: Option<String>,
 
     #[arg(short, long)]
     token: Option<String>,
 
+    /// File containing the token used to query Nomad
+    #[arg(long, conflicts_with = "token")]
+    token_file: Option<PathBuf>,
+
+    /// Context of the config describing the Nomad cluster to use. By default the context set in the config
+    #[arg(long, global = true, add = ArgValueCandidates::new(completions::contexts))]
+    context: Option<String>,
+
+    /// Proxy used to reach Nomad, http(s) or socks5. By default the proxy is read from HTTP_PROXY, HTTPS_PROXY
+    /// and ALL_PROXY. The hosts of NO_PROXY are reached directly
+    #[arg(long)]
+    proxy: Option<String>,
+
+    /// Header added to every request, formatted as Name:Value. Repeat the flag to add several headers.
+    /// The headers of the context are added as well unless overridden
+    #[arg(short = 'H', long = "header", value_parser = parse_header)]
+    headers: Vec<(String, String)>,
+
+    /// Maximum number of seconds to establish a connection with Nomad
+    #[arg(long, value_name = "SECONDS", default_value_t = 10)]
+    connect_timeout: u64,
+
+    /// Maximum number of seconds of a request to Nomad
+    #[arg(long, value_name = "SECONDS", default_value_t = 60)]
+    request_timeout: u64,
+
+    /// Maximum number of seconds of each poll reading the logs of a task while they are followed
+    #[arg(long, value_name = "SECONDS", default_value_t = 300)]
+    log_poll_timeout: u64,
+
+    /// Dump the full request and response exchanged with Nomad
+    #[arg(long)]
+    debug: bool,
+
+    /// Format used to print the result of the commands
+    #[arg(short, long, value_enum, global = true, default_value_t = OutputFormat::Text)]
+    output: OutputFormat,
+
+    /// Schema describing the metas of the jobs. By default noumead.schema.toml or noumead.schema.yaml
+    /// is searched in the current repository, then in the config directory
+    #[arg(long, global = true)]
+    schema: Option<PathBuf>,
+
     #[command(subcommand)]
     command: Commands
 }
 
 #[derive(Subcommand, Debug)]
 enum Commands {
+    /// Print the completion script of a shell
+    Completions(completions::CompletionsArgs),
     Dispatch(dispatch::DispatchArgs),
-    Stop(stop::StopArgs)
+    Inspect(inspect::InspectArgs),
+    /// Log in to a context and store its token in the keyring of the OS
+    Login(login::LoginArgs),
+    Schema(schema::SchemaArgs),
+    Status(status::StatusArgs),
+    Stop(stop::StopArgs),
+    Tui(tui::TuiArgs),
+    Var(var::VarArgs),
+    /// Show the ACL token used to query Nomad
+    Whoami(whoami::WhoamiArgs)
 }
 
-#[derive(Debug)]
 pub struct Cli {
     args: Args,
-    rest_handler: RestHandler
+    client: Option<Client>
 }
 
 #[async_trait]
@@ -47,63 +120,224 @@ trait Run {
     async fn run(&self, cli: &Cli) -> Result<(), Error>;
 }
 
+/// Complete the arguments when noumead is called by the completion script of a shell, then exit.
+/// Nothing happens otherwise
+pub fn complete() {
+    CompleteEnv::with_factory(Args::command)
+        .var(completions::COMPLETE_ENV)
+        .complete();
+}
+
 impl Cli {
     /// Create a new cli by parsing the arguments
-    pub fn new() -> Cli {
+    pub fn new() -> Result<Cli, Error> {
         let mut args = Args::parse();
-        args.fill_optional_values();
+        args.fill_optional_values()?;
 
-        Cli {
+        Ok(Cli {
             args,
-            rest_handler: RestHandler::default()
-        }
+            client: None
+        })
     }
 
-    /// Initialize the rest handler which is gonna be used to send request to Nomad
+    /// Initialize the client which is gonna be used to send request to Nomad. The completion script
+    /// is printed and the token is stored without reaching Nomad
     ///
     /// # Arguments
     ///
     /// * `&mut self` - Cli
-    pub fn init_rest_handler(&mut self) -> Result<&Self, Error> {
-        let rest_handler = RestHandler::new(
+    pub fn init_client(&mut self) -> Result<&Self, Error> {
+        if matches!(self.args.command, Commands::Completions(_) | Commands::Login(_)) {
+            return Ok(self);
+        }
+
+        let client = Client::new(
             self.args.nomad_url.to_owned(),
-            self.args.token.to_owned()
+            self.args.token.to_owned(),
+            self.rest_options()
         )?;
 
-        self.rest_handler = rest_handler;
+        self.client = Some(client);
 
         Ok(self)
     }
 
+    /// Return the client used to send request to Nomad
+    ///
+    /// # Arguments
+    ///
+    /// * `&self` - Cli
+    fn client(&self) -> Result<&Client, Error> {
+        self.client
+            .as_ref()
+            .ok_or_else(|| Error::NomadReqErr(noumead::error::REQ_BUILD_ERR.to_string()))
+    }
+
+    /// Create a client without token, used to log in with an auth method
+    ///
+    /// # Arguments
+    ///
+    /// * `&self` - Cli
+    fn anonymous_client(&self) -> Result<Client, Error> {
+        Client::new(self.args.nomad_url.to_owned(), None, self.rest_options())
+    }
+
+    /// Return the options of the connection to Nomad
+    ///
+    /// # Arguments
+    ///
+    /// * `&self` - Cli
+    fn rest_options(&self) -> RestOptions {
+        RestOptions {
+            proxy: self.args.proxy.to_owned(),
+            headers: self.args.headers.to_owned(),
+            connect_timeout: Some(Duration::from_secs(self.args.connect_timeout)),
+            request_timeout: Some(Duration::from_secs(self.args.request_timeout)),
+            log_poll_timeout: Some(Duration::from_secs(self.args.log_poll_timeout)),
+            debug: self.args.debug.then(|| DebugHook::new(|record| Logger::debug(&record.title, record.details))),
+            secrets: SecretKeys::default()
+        }
+    }
+
+    /// Return the address of the Nomad server, if any
+    ///
+    /// # Arguments
+    ///
+    /// * `&self` - Cli
+    fn nomad_url(&self) -> Option<&str> {
+        self.args.nomad_url.as_deref()
+    }
+
+    /// Return the name of the context in use, if any
+    ///
+    /// # Arguments
+    ///
+    /// * `&self` - Cli
+    fn context(&self) -> Option<&str> {
+        self.args.context.as_deref()
+    }
+
+    /// Return the formatter used to print the output of the commands
+    ///
+    /// # Arguments
+    ///
+    /// * `&self` - Cli
+    pub fn formatter(&self) -> Formatter {
+        Formatter::new(self.args.output)
+    }
+
+    /// Load the schema describing the metas of the jobs, if any
+    ///
+    /// # Arguments
+    ///
+    /// * `&self` - Cli
+    fn schema(&self) -> Result<Option<Schema>, Error> {
+        match &self.args.schema {
+            Some(path) => Schema::load(path).map(Some),
+            None => Schema::find().map(|found| found.map(|(_, schema)| schema))
+        }
+    }
+
+    /// Load the local usage stats of the jobs. The stats are only used to sort the jobs, so they are
+    /// ignored when they can't be read
+    ///
+    /// # Arguments
+    ///
+    /// * `&self` - Cli
+    fn usage(&self) -> Usage {
+        usage::default_path()
+            .and_then(|path| Usage::load(&path).ok())
+            .unwrap_or_default()
+    }
+
+    /// Record the dispatch of a job in the local usage stats. Failing to save the stats does not fail the dispatch
+    ///
+    /// # Arguments
+    ///
+    /// * `&self` - Cli
+    /// * `job` - &Job
+    fn record_usage(&self, job: &Job) {
+        let Some(path) = usage::default_path() else {
+            return;
+        };
+
+        let mut usage = self.usage();
+        usage.record(job, chrono::Utc::now());
+        let _ = usage.save(&path);
+    }
+
     /// Run the CLI with the provided arguments
     ///
     /// # Arguments
     /// * `&self` - Cli
     pub async fn run(&self) -> Result<(), Error> {
         match &self.args.command {
+            Commands::Completions(args) => args.run(self).await,
             Commands::Dispatch(args) => args.run(self).await,
-            Commands::Stop(args) => args.run(self).await
+            Commands::Inspect(args) => args.run(self).await,
+            Commands::Login(args) => args.run(self).await,
+            Commands::Schema(args) => args.run(self).await,
+            Commands::Status(args) => args.run(self).await,
+            Commands::Stop(args) => args.run(self).await,
+            Commands::Tui(args) => args.run(self).await,
+            Commands::Var(args) => args.run(self).await,
+            Commands::Whoami(args) => args.run(self).await
         }
     }
 }
 
 impl Args {
-    /// Try to fill the optional value by looking at the environment variable
+    /// Try to fill the optional values by looking at the environment variables, then at the context of the config.
+    /// The token is read in order from the `--token` flag, the `--token-file` flag, the environment, the
+    /// `token_command` of the context and finally the keyring of the OS. A context given with `--context` takes
+    /// precedence over the environment
     ///
     /// # Arguments
     ///
     /// * `&mut self` - Args
-    fn fill_optional_values(&mut self) {
-        if self.nomad_url.is_none() {
-            if let Ok(url) = env::var(NOMAD_ADDR_ENV) {
-                self.nomad_url = Some(url);
-            }
-        }
+    fn fill_optional_values(&mut self) -> Result<(), Error> {
+        // the login stores a new token and the completion script does not reach Nomad, so the token is not
+        // read, which would run the token command of the context for nothing
+        let sources = match self.command {
+            Commands::Completions(_) | Commands::Login(_) => TokenSources::None,
+            _ => TokenSources::All
+        };
 
-        if self.token.is_none() {
-            if let Ok(token) = env::var(NOMAD_TOKEN_ENV) {
-                self.token = Some(token);
-            }
-        }
+        let args = ConnectionArgs {
+            context: self.context.take(),
+            address: self.nomad_url.take(),
+            token: self.token.take(),
+            token_file: self.token_file.clone(),
+            headers: std::mem::take(&mut self.headers)
+        };
+
+        let connection = load_config()?.connection(args, |var| env::var(var).ok(), sources)?;
+        self.context = connection.context;
+        self.nomad_url = connection.address;
+        self.token = connection.token;
+        self.headers = connection.headers;
+
+        Ok(())
+    }
+}
+
+/// Parse a header formatted as Name:Value
+///
+/// # Arguments
+///
+/// * `arg` - &str
+fn parse_header(arg: &str) -> Result<(String, String), String> {
+    match arg.split_once(HEADER_SEPARATOR) {
+        Some((name, value)) if !name.trim().is_empty() => Ok((name.trim().to_string(), value.trim().to_string())),
+        _ => Err(format!("{} {arg}", noumead::error::HEADER_FORMAT_ERR))
     }
 }
+
+/// Load the config of the user. No config is the same as an empty config
+pub(crate) fn load_config() -> Result<Config, Error> {
+    match config::default_path() {
+        Some(path) => Config::load(&path),
+        None => Ok(Config::default())
+    }
+}
+
@@ -0,0 +1,69 @@
+use clap::{Args, Subcommand};
+use async_trait::async_trait;
+use noumead::error::{Error, self};
+use noumead::schema::Drift;
+use super::Run;
+
+#[derive(Args, Debug)]
+pub struct SchemaArgs {
+    #[command(subcommand)]
+    command: SchemaCommands
+}
+
+#[derive(Subcommand, Debug)]
+enum SchemaCommands {
+    /// Detect the drift between the schema and the live spec of the jobs
+    Check
+}
+
+#[async_trait]
+impl Run for SchemaArgs {
+    async fn run(&self, cli: &super::Cli) -> Result<(), Error> {
+        match self.command {
+            SchemaCommands::Check => check(cli).await
+        }
+    }
+}
+
+/// Compare every job of the schema with its live spec
+///
+/// # Arguments
+///
+/// * `cli` - &Cli
+async fn check(cli: &super::Cli) -> Result<(), Error> {
+    let client = cli.client()?;
+    let Some(schema) = cli.schema()? else {
+        return Err(Error::Schema(error::SCHEMA_NOT_FOUND_ERR.to_string()));
+    };
+
+    // the schema is keyed by the id of the jobs, which are checked in every namespace they are registered in
+    let jobs = client.api().jobs().await?;
+
+    let mut drifts = Vec::new();
+    for (name, job_schema) in &schema.jobs {
+        let namespaces: Vec<&str> = jobs.iter()
+            .filter(|j| &j.id == name)
+            .map(|j| j.namespace.as_str())
+            .collect();
+
+        if namespaces.is_empty() {
+            drifts.push(Drift::JobNotFound { job: name.clone() });
+            continue;
+        }
+
+        for namespace in namespaces {
+            let spec = client.inspect(namespace, name).await?;
+
+            // invalid patterns or types of the schema are reported as errors
+            job_schema.apply(spec.clone()).meta_fields()?;
+            drifts.extend(job_schema.check(name, &spec));
+        }
+    }
+
+    cli.formatter().emit(&drifts)?;
+
+    match drifts.is_empty() {
+        true => Ok(()),
+        false => Err(Error::Schema(error::SCHEMA_DRIFT_ERR.to_string()))
+    }
+}
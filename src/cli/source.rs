@@ -0,0 +1,120 @@
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+use noumead::Client;
+use noumead::error::{Error, self};
+
+// constant
+const ENV_PREFIX: &str = "env:";
+const FILE_PREFIX: char = '@';
+pub const VAR_PREFIX: &str = "var:";
+const FIELD_SEPARATOR: char = '#';
+
+/// Source of the value of a meta provided on the command line
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MetaSource {
+    /// Value typed on the command line
+    Value(String),
+    /// Value read from an environment variable with key=env:VAR
+    Env(String),
+    /// Value read from a file with --meta-file key=@path
+    File(PathBuf),
+    /// Value read from an item of a Nomad variable with key=var:path#item
+    Var {
+        path: String,
+        item: Option<String>
+    }
+}
+
+/// Parse a meta provided as key=value, key=env:VAR or key=var:path#item
+///
+/// # Arguments
+///
+/// * `arg` - &str
+pub fn parse_meta(arg: &str) -> Result<(String, MetaSource), String> {
+    let (key, value) = split_meta(arg)?;
+    let source = match (value.strip_prefix(ENV_PREFIX), parse_var(value)) {
+        (Some(var), _) => MetaSource::Env(var.to_string()),
+        (_, Some(var)) => var,
+        _ => MetaSource::Value(value.to_string())
+    };
+
+    Ok((key.to_string(), source))
+}
+
+/// Parse a reference to a Nomad variable formatted as var:path#item. The item can be omitted
+/// when the variable has a single item
+///
+/// # Arguments
+///
+/// * `value` - &str
+pub fn parse_var(value: &str) -> Option<MetaSource> {
+    let reference = value.strip_prefix(VAR_PREFIX).filter(|r| !r.is_empty())?;
+    let (path, item) = match reference.split_once(FIELD_SEPARATOR) {
+        Some((path, item)) => (path, Some(item.to_string())),
+        None => (reference, None)
+    };
+
+    Some(MetaSource::Var { path: path.to_string(), item })
+}
+
+/// Parse a meta read from a file, provided as key=@path
+///
+/// # Arguments
+///
+/// * `arg` - &str
+pub fn parse_meta_file(arg: &str) -> Result<(String, MetaSource), String> {
+    let (key, value) = split_meta(arg)?;
+    let path = value.strip_prefix(FILE_PREFIX).unwrap_or(value);
+
+    Ok((key.to_string(), MetaSource::File(PathBuf::from(path))))
+}
+
+/// Split a meta formatted as key=value
+///
+/// # Arguments
+///
+/// * `arg` - &str
+fn split_meta(arg: &str) -> Result<(&str, &str), String> {
+    match arg.split_once('=') {
+        Some((key, value)) if !key.is_empty() => Ok((key, value)),
+        _ => Err(format!("{} {arg}", error::META_FORMAT_ERR))
+    }
+}
+
+impl MetaSource {
+    /// Read the value of the meta. The trailing new line of a file is removed and variables are read
+    /// from the namespace of the dispatched job
+    ///
+    /// # Arguments
+    ///
+    /// * `&self` - MetaSource
+    /// * `key` - &str
+    /// * `client` - &Client
+    /// * `namespace` - &str
+    pub async fn resolve(&self, key: &str, client: &Client, namespace: &str) -> Result<String, Error> {
+        let invalid = |reason: String| Error::InvalidMeta { key: key.to_string(), reason };
+
+        match self {
+            MetaSource::Value(value) => Ok(value.clone()),
+            MetaSource::Env(var) => env::var(var)
+                .map_err(|err| invalid(format!("{} {var}: {err}", error::ENV_META_REASON))),
+            MetaSource::File(path) => fs::read_to_string(path)
+                .map(|value| value.trim_end_matches(['\n', '\r']).to_string())
+                .map_err(|err| invalid(format!("{} {}: {err}", error::FILE_META_REASON, path.display()))),
+            MetaSource::Var { path, item } => client.variable(namespace, path)
+                .await?
+                .item(item.as_deref())
+                .cloned()
+        }
+    }
+
+    /// Return whether the value comes from a source which is usually used to hide a secret
+    ///
+    /// # Arguments
+    ///
+    /// * `&self` - MetaSource
+    pub fn is_secret(&self) -> bool {
+        !matches!(self, MetaSource::Value(_))
+    }
+}
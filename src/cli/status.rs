@@ -0,0 +1,83 @@
+use clap::{Args, ValueEnum};
+use clap_complete::engine::ArgValueCandidates;
+use async_trait::async_trait;
+use tokio::time::Duration;
+use noumead::error::Error;
+use noumead::nomad::summary::DispatchState;
+use super::{completions, Run};
+
+// constant
+const WATCH_WAIT: u64 = 30000;
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+enum StateFilter {
+    Queued,
+    Running,
+    Complete,
+    Failed
+}
+
+#[derive(Args, Debug)]
+pub struct StatusArgs {
+    /// Parameterized job whose dispatched jobs are shown. Every parameterized jobs are shown when omitted
+    #[arg(add = ArgValueCandidates::new(completions::jobs))]
+    parent: Option<String>,
+
+    /// Only show the dispatched jobs with the provided states
+    #[arg(short, long, value_enum)]
+    state: Vec<StateFilter>,
+
+    /// Refresh the status whenever a dispatched job change
+    #[arg(short, long)]
+    watch: bool
+}
+
+impl From<StateFilter> for DispatchState {
+    fn from(filter: StateFilter) -> Self {
+        match filter {
+            StateFilter::Queued => DispatchState::Queued,
+            StateFilter::Running => DispatchState::Running,
+            StateFilter::Complete => DispatchState::Complete,
+            StateFilter::Failed => DispatchState::Failed
+        }
+    }
+}
+
+#[async_trait]
+impl Run for StatusArgs {
+    async fn run(&self, cli: &super::Cli) -> Result<(), Error> {
+        let client = cli.client()?;
+        let formatter = cli.formatter();
+        let states: Vec<DispatchState> = self.state.iter()
+            .map(|s| DispatchState::from(*s))
+            .collect();
+
+        // the schema marks secret metas in addition to the convention of the jobs
+        let schema = cli.schema()?;
+
+        let mut index = 0;
+        loop {
+            let (mut status, last_index) = client
+                .dispatch_status(self.parent.as_deref(), schema.as_ref(), index, Duration::from_millis(WATCH_WAIT))
+                .await?;
+
+            if !states.is_empty() {
+                for parent in status.iter_mut() {
+                    parent.dispatched.retain(|d| states.contains(&d.state));
+                }
+            }
+
+            if self.watch {
+                formatter.clear();
+            }
+
+            formatter.emit(&status)?;
+
+            if !self.watch {
+                return Ok(());
+            }
+
+            index = last_index.max(1);
+        }
+    }
+}
@@ -1,48 +1,45 @@
 use clap::Args;
 use async_trait::async_trait;
-use futures::future::join_all;
-use crate::nomad::job::{self, Job};
-use crate::{inquiry, log::Logger};
-use crate::error::{Error, self};
+use noumead::nomad::job::Job;
+use noumead::error::{Error, self};
+use crate::inquiry;
+use crate::output::StopOutput;
 use super::Run;
 
-// constant
-const RUNNING_STATUS: &str = "running";
-
 #[derive(Args, Debug)]
 pub struct StopArgs;
 
 #[async_trait]
 impl Run for StopArgs {
-    async fn run(&self, cli: &super::Cli) ->  Result<(), crate::error::Error> {
-        let jobs: Vec<Job> = job::get_nomad_job_list(&cli.rest_handler).await?
-            .into_iter()
-            .filter(|j| !j.parameterized && j.status == RUNNING_STATUS)
-            .collect();
+    async fn run(&self, cli: &super::Cli) ->  Result<(), Error> {
+        let client = cli.client()?;
+        let jobs = client.running_jobs().await?;
 
         if jobs.is_empty() {
             return Err(Error::ScenarioErr(error::NO_RUNNING_JOB_ERR.to_string()));
         }
 
-        let (selected_jobs_name, _) = inquiry::multi_select(&jobs, "Select the jobs that you want to stop")?;
+        let selected_jobs: Vec<Job> = inquiry::multi_select_jobs(&jobs, "Select the jobs that you want to stop")?
+            .into_iter()
+            .cloned()
+            .collect();
 
-        let selected_jobs: Vec<Job> = jobs.into_iter()
-            .filter(|j| selected_jobs_name.contains(&j.name))
+        let res = client.stop(&selected_jobs).await;
+        let outputs: Vec<StopOutput> = selected_jobs.iter()
+            .zip(res)
+            .map(|(job, r)| StopOutput {
+                job: &job.id,
+                namespace: &job.namespace,
+                stopped: r.is_ok(),
+                error: r.err().map(|err| err.to_string())
+            })
             .collect();
 
-        let mut tasks = Vec::new();
-        for job in selected_jobs {
-            tasks.push(cli.rest_handler.delete(format!("v1/job/{}", job.id)));
-        }
+        cli.formatter().emit(&outputs)?;
 
-        let res = join_all(tasks).await;
-        for r in res {
-            match r {
-                Ok(_) => Logger::info("A job has been deleted"),
-                Err(err) => Logger::error( "Unable to delete a job due to", err)
-            }
+        match outputs.iter().all(|o| o.stopped) {
+            true => Ok(()),
+            false => Err(Error::ScenarioErr(error::STOP_FAILED_ERR.to_string()))
         }
-
-        Ok(())
     }
 }
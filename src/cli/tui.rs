@@ -0,0 +1,17 @@
+use clap::Args;
+use async_trait::async_trait;
+use noumead::error::Error;
+use crate::ui;
+use super::Run;
+
+#[derive(Args, Debug)]
+pub struct TuiArgs;
+
+#[async_trait]
+impl Run for TuiArgs {
+    async fn run(&self, cli: &super::Cli) -> Result<(), Error> {
+        let client = cli.client()?;
+
+        ui::run(client.clone(), cli.schema()?).await
+    }
+}
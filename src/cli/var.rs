@@ -0,0 +1,274 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use clap::{Args, Subcommand};
+use clap_complete::engine::ArgValueCandidates;
+use async_trait::async_trait;
+use noumead::Client;
+use noumead::error::{Error, self};
+use noumead::nomad::namespace::DEFAULT_NAMESPACE;
+use noumead::nomad::variable::{self, Variable};
+use crate::inquiry;
+use crate::output::{VariableDeleteOutput, VariableOutput};
+use super::{completions, Run};
+
+// constant
+const STDIN_PATH: &str = "-";
+
+#[derive(Args, Debug)]
+pub struct VarArgs {
+    /// Namespace of the variables
+    #[arg(long, global = true, default_value = DEFAULT_NAMESPACE, add = ArgValueCandidates::new(completions::namespaces))]
+    namespace: String,
+
+    #[command(subcommand)]
+    command: VarCommands
+}
+
+#[derive(Subcommand, Debug)]
+enum VarCommands {
+    /// List the variables whose path starts with the prefix
+    List {
+        prefix: Option<String>
+    },
+    /// Show the items of a variable. The variable is selected from a list when the path is omitted
+    Get {
+        path: Option<String>,
+
+        /// Show the values of the items instead of masking them
+        #[arg(long)]
+        reveal: bool
+    },
+    /// Create or replace a variable. The items are read from the arguments, from a json file
+    /// or edited with $EDITOR when none is provided
+    Put {
+        path: Option<String>,
+
+        /// Item of the variable formatted as key=value
+        #[arg(value_parser = parse_item)]
+        items: Vec<(String, String)>,
+
+        /// Json file containing the items of the variable, - to read it from the standard input
+        #[arg(long, conflicts_with = "items")]
+        file: Option<PathBuf>,
+
+        /// Modify index that the variable must have to be written, 0 when it must not exist.
+        /// Default to the index of the variable when it has been read
+        #[arg(long)]
+        cas: Option<u64>,
+
+        /// Show the values of the items instead of masking them
+        #[arg(long)]
+        reveal: bool
+    },
+    /// Delete a variable. The variable is selected from a list when the path is omitted
+    Delete {
+        path: Option<String>,
+
+        /// Modify index that the variable must have to be deleted. Default to the index of the variable
+        /// when it has been read
+        #[arg(long)]
+        cas: Option<u64>
+    }
+}
+
+#[async_trait]
+impl Run for VarArgs {
+    async fn run(&self, cli: &super::Cli) -> Result<(), Error> {
+        let ns = self.namespace.as_str();
+
+        match &self.command {
+            VarCommands::List { prefix } => list(cli, ns, prefix.as_deref()).await,
+            VarCommands::Get { path, reveal } => get(cli, ns, path.as_deref(), *reveal).await,
+            VarCommands::Put { path, items, file, cas, reveal } => {
+                let input = match (items.is_empty(), file) {
+                    (false, _) => Input::Items(items.iter().cloned().collect()),
+                    (true, Some(file)) => Input::File(file.as_path()),
+                    (true, None) => Input::Editor
+                };
+
+                put(cli, ns, path.as_deref(), input, *cas, *reveal).await
+            },
+            VarCommands::Delete { path, cas } => delete(cli, ns, path.as_deref(), *cas).await
+        }
+    }
+}
+
+/// Source of the items of a variable which is written
+enum Input<'a> {
+    Items(BTreeMap<String, String>),
+    File(&'a Path),
+    Editor
+}
+
+/// Parse an item provided as key=value
+///
+/// # Arguments
+///
+/// * `arg` - &str
+fn parse_item(arg: &str) -> Result<(String, String), String> {
+    match arg.split_once('=') {
+        Some((key, value)) if !key.is_empty() => Ok((key.to_string(), value.to_string())),
+        _ => Err(format!("{} {arg}", error::VAR_ITEM_FORMAT_ERR))
+    }
+}
+
+/// Select a variable of the namespace whose path starts with the prefix
+///
+/// # Arguments
+///
+/// * `client` - &Client
+/// * `namespace` - &str
+/// * `prefix` - &str
+pub async fn select_path(client: &Client, namespace: &str, prefix: &str) -> Result<String, Error> {
+    let paths: Vec<String> = client.variables(namespace, prefix)
+        .await?
+        .into_iter()
+        .map(|v| v.path)
+        .collect();
+
+    if paths.is_empty() {
+        return Err(Error::Variable(format!("{namespace}: {}", error::VAR_NOT_FOUND_ERR)));
+    }
+
+    inquiry::select(&paths, "Select the variable").cloned()
+}
+
+/// Return the path of the variable or let the user select it
+///
+/// # Arguments
+///
+/// * `client` - &Client
+/// * `namespace` - &str
+/// * `path` - Option<&str>
+async fn path_or_select(client: &Client, namespace: &str, path: Option<&str>) -> Result<String, Error> {
+    match path {
+        Some(path) => Ok(path.to_string()),
+        None => select_path(client, namespace, "").await
+    }
+}
+
+/// Get a variable, None is returned when it does not exist
+///
+/// # Arguments
+///
+/// * `client` - &Client
+/// * `namespace` - &str
+/// * `path` - &str
+async fn find_variable(client: &Client, namespace: &str, path: &str) -> Result<Option<Variable>, Error> {
+    match client.variable(namespace, path).await {
+        Ok(variable) => Ok(Some(variable)),
+        Err(Error::Api { status: 404, .. }) => Ok(None),
+        Err(err) => Err(err)
+    }
+}
+
+/// List the variables of the namespace
+///
+/// # Arguments
+///
+/// * `cli` - &Cli
+/// * `namespace` - &str
+/// * `prefix` - Option<&str>
+async fn list(cli: &super::Cli, namespace: &str, prefix: Option<&str>) -> Result<(), Error> {
+    let variables = cli.client()?
+        .variables(namespace, prefix.unwrap_or_default())
+        .await?;
+
+    cli.formatter().emit(&variables)
+}
+
+/// Show a variable along with its items
+///
+/// # Arguments
+///
+/// * `cli` - &Cli
+/// * `namespace` - &str
+/// * `path` - Option<&str>
+/// * `reveal` - bool
+async fn get(cli: &super::Cli, namespace: &str, path: Option<&str>, reveal: bool) -> Result<(), Error> {
+    let client = cli.client()?;
+    let path = path_or_select(client, namespace, path).await?;
+    let variable = client.variable(namespace, &path).await?;
+
+    cli.formatter().emit(&VariableOutput::new(&variable, reveal))
+}
+
+/// Write a variable. Unless an index is provided, the variable is written with the index read
+/// beforehand so that a concurrent edit is not overwritten
+///
+/// # Arguments
+///
+/// * `cli` - &Cli
+/// * `namespace` - &str
+/// * `path` - Option<&str>
+/// * `input` - Input
+/// * `cas` - Option<u64>
+/// * `reveal` - bool
+async fn put(
+    cli: &super::Cli,
+    namespace: &str,
+    path: Option<&str>,
+    input: Input<'_>,
+    cas: Option<u64>,
+    reveal: bool
+) -> Result<(), Error> {
+    let client = cli.client()?;
+    let path = path_or_select(client, namespace, path).await?;
+    let current = find_variable(client, namespace, &path).await?;
+
+    let items = match input {
+        Input::Items(items) => items,
+        Input::File(file) => variable::parse_items(&read_file(file)?)?,
+        Input::Editor => {
+            let items = current.as_ref().map(|v| v.items.clone()).unwrap_or_default();
+            inquiry::edit_items(&path, &items)?
+        }
+    };
+
+    let cas = cas.unwrap_or_else(|| current.map(|v| v.modify_index).unwrap_or_default());
+    let variable = client.put_variable(&Variable::new(namespace, &path, items), Some(cas)).await?;
+
+    cli.formatter().emit(&VariableOutput::new(&variable, reveal))
+}
+
+/// Delete a variable. Unless an index is provided, the variable is deleted with the index read
+/// beforehand so that a concurrent edit is not lost
+///
+/// # Arguments
+///
+/// * `cli` - &Cli
+/// * `namespace` - &str
+/// * `path` - Option<&str>
+/// * `cas` - Option<u64>
+async fn delete(cli: &super::Cli, namespace: &str, path: Option<&str>, cas: Option<u64>) -> Result<(), Error> {
+    let client = cli.client()?;
+    let path = path_or_select(client, namespace, path).await?;
+    let cas = match cas {
+        Some(cas) => cas,
+        None => client.variable(namespace, &path).await?.modify_index
+    };
+
+    client.delete_variable(namespace, &path, Some(cas)).await?;
+
+    cli.formatter().emit(&VariableDeleteOutput { namespace, path: &path, deleted: true })
+}
+
+/// Read the content of a file, or of the standard input
+///
+/// # Arguments
+///
+/// * `path` - &Path
+fn read_file(path: &Path) -> Result<String, Error> {
+    let to_err = |err: io::Error| Error::Variable(format!("{}: {err}", path.display()));
+
+    match path.as_os_str() == STDIN_PATH {
+        true => {
+            let mut content = String::new();
+            io::stdin().read_to_string(&mut content).map_err(to_err)?;
+            Ok(content)
+        },
+        false => fs::read_to_string(path).map_err(to_err)
+    }
+}
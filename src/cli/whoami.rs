@@ -0,0 +1,16 @@
+use clap::Args;
+use async_trait::async_trait;
+use noumead::error::Error;
+use super::Run;
+
+#[derive(Args, Debug)]
+pub struct WhoamiArgs;
+
+#[async_trait]
+impl Run for WhoamiArgs {
+    async fn run(&self, cli: &super::Cli) -> Result<(), Error> {
+        let token = cli.client()?.token_self().await?;
+
+        cli.formatter().emit(&token)
+    }
+}
@@ -0,0 +1,324 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use futures::stream::BoxStream;
+use tokio::time::Duration;
+use crate::error::Error;
+use crate::login::{self, CallbackListener};
+use crate::nomad::acl::{self, AclToken, Grant, LoginToken};
+use crate::nomad::alloc::Allocation;
+use crate::nomad::api::NomadApi;
+use crate::nomad::dispatch::{DispatchPayload, DispatchRes};
+use crate::nomad::job::{self, Job, JobStatus};
+use crate::nomad::meta::SecretKeys;
+use crate::nomad::namespace::Namespace;
+use crate::nomad::spec::Spec;
+use crate::nomad::stream::{self, LogEvent};
+use crate::nomad::summary::{self, FinishedTimes, ParentStatus};
+use crate::nomad::variable::{Variable, VariableMetadata};
+use crate::rest::{RestHandler, RestOptions};
+use crate::schema::Schema;
+
+/// Typed client used to dispatch parameterized jobs to Nomad and to follow their logs
+#[derive(Clone)]
+pub struct Client {
+    api: Arc<dyn NomadApi>,
+    /// Shared by the clones of the client so that watching the status lists the allocations of a dead job once
+    finished: Arc<FinishedTimes>,
+    /// Shared with the handler so that the value of the secret metas is redacted from the debug records
+    secrets: SecretKeys
+}
+
+impl Client {
+    /// Create a new client which send the requests to the Nomad server
+    ///
+    /// # Arguments
+    ///
+    /// * `base_url` - Option<String>
+    /// * `token` - Option<String>
+    /// * `options` - RestOptions
+    pub fn new(base_url: Option<String>, token: Option<String>, options: RestOptions) -> Result<Client, Error> {
+        let secrets = options.secrets.clone();
+        let handler = RestHandler::new(base_url, token, options)?;
+
+        Ok(Client { secrets, ..Client::with_api(Arc::new(handler)) })
+    }
+
+    /// Create a new client on top of any implementation of the Nomad API
+    ///
+    /// # Arguments
+    ///
+    /// * `api` - Arc<dyn NomadApi>
+    pub fn with_api(api: Arc<dyn NomadApi>) -> Client {
+        Client { api, finished: Arc::default(), secrets: SecretKeys::default() }
+    }
+
+    /// Redact the value of the given metas from the requests and the responses given to the debug hook
+    ///
+    /// # Arguments
+    ///
+    /// * `&self` - Client
+    /// * `keys` - I
+    pub fn register_secrets<'a, I: IntoIterator<Item = &'a String>>(&self, keys: I) {
+        self.secrets.extend(keys);
+    }
+
+    /// Return the Nomad API used by the client
+    ///
+    /// # Arguments
+    ///
+    /// * `&self` - Client
+    pub fn api(&self) -> &dyn NomadApi {
+        self.api.as_ref()
+    }
+
+    /// List the jobs of every namespaces
+    ///
+    /// # Arguments
+    ///
+    /// * `&self` - Client
+    pub async fn jobs(&self) -> Result<Vec<Job>, Error> {
+        job::get_nomad_job_list(self.api()).await
+    }
+
+    /// List the parameterized jobs which can be dispatched
+    ///
+    /// # Arguments
+    ///
+    /// * `&self` - Client
+    pub async fn parameterized_jobs(&self) -> Result<Vec<Job>, Error> {
+        job::get_parameterized_jobs(self.api()).await
+    }
+
+    /// List the running jobs which can be stopped
+    ///
+    /// # Arguments
+    ///
+    /// * `&self` - Client
+    pub async fn running_jobs(&self) -> Result<Vec<Job>, Error> {
+        job::get_running_jobs(self.api()).await
+    }
+
+    /// Get the spec of a job
+    ///
+    /// # Arguments
+    ///
+    /// * `&self` - Client
+    /// * `job` - &Job
+    pub async fn spec(&self, job: &Job) -> Result<Spec, Error> {
+        Spec::get(&job.namespace, &job.id, self.api()).await
+    }
+
+    /// Get the spec of a job from its namespace and id
+    ///
+    /// # Arguments
+    ///
+    /// * `&self` - Client
+    /// * `namespace` - &str
+    /// * `job_id` - &str
+    pub async fn inspect(&self, namespace: &str, job_id: &str) -> Result<Spec, Error> {
+        Spec::get(namespace, job_id, self.api()).await
+    }
+
+    /// Dispatch a parameterized job with the provided metas
+    ///
+    /// # Arguments
+    ///
+    /// * `&self` - Client
+    /// * `job` - &Job
+    /// * `metas` - HashMap<String, String>
+    pub async fn dispatch(&self, job: &Job, metas: HashMap<String, String>) -> Result<DispatchRes, Error> {
+        job.dispatch_job(self.api(), metas).await
+    }
+
+    /// Dispatch a parameterized job with the provided metas and payload
+    ///
+    /// # Arguments
+    ///
+    /// * `&self` - Client
+    /// * `job` - &Job
+    /// * `metas` - HashMap<String, String>
+    /// * `payload` - String
+    pub async fn dispatch_with_payload(&self, job: &Job, metas: HashMap<String, String>, payload: String) -> Result<DispatchRes, Error> {
+        job.dispatch_job_with_payload(self.api(), metas, payload).await
+    }
+
+    /// Send a dispatch payload built beforehand, e.g. with an idempotency token
+    ///
+    /// # Arguments
+    ///
+    /// * `&self` - Client
+    /// * `job` - &Job
+    /// * `payload` - &DispatchPayload
+    pub async fn send_dispatch(&self, job: &Job, payload: &DispatchPayload) -> Result<DispatchRes, Error> {
+        job.send_dispatch(self.api(), payload).await
+    }
+
+    /// Wait for the allocation of a dispatched job
+    ///
+    /// # Arguments
+    ///
+    /// * `&self` - Client
+    /// * `namespace` - &str
+    /// * `job_id` - &str
+    pub async fn allocation(&self, namespace: &str, job_id: &str) -> Result<Allocation, Error> {
+        Allocation::fetch_single_alloc(namespace, job_id, self.api()).await
+    }
+
+    /// Follow the logs of a task until the task finish
+    ///
+    /// # Arguments
+    ///
+    /// * `&self` - Client
+    /// * `alloc` - &Allocation
+    /// * `task_name` - &str
+    pub fn follow_logs(&self, alloc: &Allocation, task_name: &str) -> BoxStream<'static, Result<LogEvent, Error>> {
+        stream::follow(self.api.clone(), &alloc.alloc_id, task_name)
+    }
+
+    /// Wait for a job to be dead
+    ///
+    /// # Arguments
+    ///
+    /// * `&self` - Client
+    /// * `namespace` - &str
+    /// * `job_id` - &str
+    pub async fn wait_for_dead_job(&self, namespace: &str, job_id: &str) -> Result<JobStatus, Error> {
+        job::wait_for_dead_job(self.api(), namespace, job_id).await
+    }
+
+    /// Get the status of the jobs dispatched from the parameterized jobs, or from a single parent.
+    /// The status can be watched by passing the index returned by the previous call
+    ///
+    /// # Arguments
+    ///
+    /// * `&self` - Client
+    /// * `parent` - Option<&str>
+    /// * `schema` - Option<&Schema>
+    /// * `index` - u64
+    /// * `wait` - Duration
+    pub async fn dispatch_status(
+        &self,
+        parent: Option<&str>,
+        schema: Option<&Schema>,
+        index: u64,
+        wait: Duration
+    ) -> Result<(Vec<ParentStatus>, u64), Error> {
+        summary::get_dispatch_status(self.api(), parent, schema, &self.finished, index, wait).await
+    }
+
+    /// Stop the provided jobs. The result of each job is returned in the same order
+    ///
+    /// # Arguments
+    ///
+    /// * `&self` - Client
+    /// * `jobs` - &[Job]
+    pub async fn stop(&self, jobs: &[Job]) -> Vec<Result<(), Error>> {
+        job::stop_jobs(self.api(), jobs, false).await
+    }
+
+    /// Stop the provided jobs and remove them from Nomad. The result of each job is returned in the same order
+    ///
+    /// # Arguments
+    ///
+    /// * `&self` - Client
+    /// * `jobs` - &[Job]
+    pub async fn purge(&self, jobs: &[Job]) -> Vec<Result<(), Error>> {
+        job::stop_jobs(self.api(), jobs, true).await
+    }
+
+    /// List the variables of a namespace whose path start with the prefix
+    ///
+    /// # Arguments
+    ///
+    /// * `&self` - Client
+    /// * `namespace` - &str
+    /// * `prefix` - &str
+    pub async fn variables(&self, namespace: &str, prefix: &str) -> Result<Vec<VariableMetadata>, Error> {
+        self.api().variables(namespace, prefix).await
+    }
+
+    /// Get a variable along with its items
+    ///
+    /// # Arguments
+    ///
+    /// * `&self` - Client
+    /// * `namespace` - &str
+    /// * `path` - &str
+    pub async fn variable(&self, namespace: &str, path: &str) -> Result<Variable, Error> {
+        self.api().variable(namespace, path).await
+    }
+
+    /// Create or replace a variable. The variable is only written when the check-and-set index match its modify index
+    ///
+    /// # Arguments
+    ///
+    /// * `&self` - Client
+    /// * `variable` - &Variable
+    /// * `cas` - Option<u64>
+    pub async fn put_variable(&self, variable: &Variable, cas: Option<u64>) -> Result<Variable, Error> {
+        self.api().put_variable(variable, cas).await
+    }
+
+    /// Delete a variable. The variable is only deleted when the check-and-set index match its modify index
+    ///
+    /// # Arguments
+    ///
+    /// * `&self` - Client
+    /// * `namespace` - &str
+    /// * `path` - &str
+    /// * `cas` - Option<u64>
+    pub async fn delete_variable(&self, namespace: &str, path: &str, cas: Option<u64>) -> Result<(), Error> {
+        self.api().delete_variable(namespace, path, cas).await
+    }
+
+    /// List the namespaces of the cluster
+    ///
+    /// # Arguments
+    ///
+    /// * `&self` - Client
+    pub async fn namespaces(&self) -> Result<Vec<Namespace>, Error> {
+        self.api().namespaces().await
+    }
+
+    /// Get the ACL token used to query Nomad
+    ///
+    /// # Arguments
+    ///
+    /// * `&self` - Client
+    pub async fn token_self(&self) -> Result<AclToken, Error> {
+        self.api().token_self().await
+    }
+
+    /// Check whether the token is allowed to dispatch the jobs of a namespace
+    ///
+    /// # Arguments
+    ///
+    /// * `&self` - Client
+    /// * `namespace` - &str
+    pub async fn dispatch_grant(&self, namespace: &str) -> Result<Grant, Error> {
+        acl::token_grant(self.api(), namespace, acl::DISPATCH_CAPABILITY).await
+    }
+
+    /// Log in with an OIDC auth method, `open` receives the url of the provider
+    ///
+    /// # Arguments
+    ///
+    /// * `&self` - Client
+    /// * `method` - &str
+    /// * `listener` - CallbackListener
+    /// * `open` - F
+    pub async fn oidc_login<F: FnOnce(&str)>(&self, method: &str, listener: CallbackListener, open: F) -> Result<LoginToken, Error> {
+        login::oidc_login(self.api(), method, listener, open).await
+    }
+
+    /// Log in with a JWT auth method
+    ///
+    /// # Arguments
+    ///
+    /// * `&self` - Client
+    /// * `method` - &str
+    /// * `jwt` - &str
+    pub async fn jwt_login(&self, method: &str, jwt: &str) -> Result<LoginToken, Error> {
+        login::jwt_login(self.api(), method, jwt).await
+    }
+}
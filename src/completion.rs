@@ -0,0 +1,204 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use chrono::{DateTime, Duration, Utc};
+use futures::future::join_all;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use crate::client::Client;
+use crate::error::Error;
+
+// Constant
+const CACHE_DIR: &str = "noumead";
+const COMPLETIONS_DIR: &str = "completions";
+/// Number of characters of the hash of the address used to name the cache files
+const ADDRESS_HASH_LEN: usize = 16;
+
+/// Kind of values completed by the shell
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CompletionKind {
+    Jobs,
+    Namespaces,
+    Tasks
+}
+
+/// Value proposed by the shell along with an help
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq, Eq)]
+pub struct Candidate {
+    pub value: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub help: Option<String>
+}
+
+/// Candidates fetched from Nomad and cached on disk
+#[derive(Debug, Deserialize, Serialize)]
+struct CacheEntry {
+    fetched_at: DateTime<Utc>,
+    candidates: Vec<Candidate>
+}
+
+/// Short-lived cache of the candidates so that pressing tab several times does not query Nomad every time.
+/// The candidates of each Nomad address are cached separately
+#[derive(Debug, Clone)]
+pub struct CompletionCache {
+    dir: PathBuf,
+    ttl: Duration
+}
+
+impl std::fmt::Display for CompletionKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CompletionKind::Jobs => write!(f, "jobs"),
+            CompletionKind::Namespaces => write!(f, "namespaces"),
+            CompletionKind::Tasks => write!(f, "tasks")
+        }
+    }
+}
+
+/// Return the directory of the cache in the cache directory of the user
+pub fn default_dir() -> Option<PathBuf> {
+    dirs::cache_dir().map(|dir| dir.join(CACHE_DIR).join(COMPLETIONS_DIR))
+}
+
+/// Return the value given to a flag on the command line being completed, e.g. `--job busybox` or `--job=busybox`.
+/// The last occurrence wins, and an empty value such as the word being completed is ignored
+///
+/// # Arguments
+///
+/// * `words` - &[String]
+/// * `flags` - &[&str]
+pub fn typed_value(words: &[String], flags: &[&str]) -> Option<String> {
+    words.iter()
+        .enumerate()
+        .filter_map(|(idx, word)| match word.split_once('=') {
+            Some((flag, value)) if flags.contains(&flag) => Some(value.to_string()),
+            _ if flags.contains(&word.as_str()) => words.get(idx + 1).cloned(),
+            _ => None
+        })
+        .rfind(|value| !value.is_empty())
+}
+
+/// Fetch the candidates from Nomad. Jobs and tasks are the ones of the parameterized jobs, the tasks
+/// are limited to the ones of the given job when present
+///
+/// # Arguments
+///
+/// * `client` - &Client
+/// * `kind` - CompletionKind
+/// * `job` - Option<&str>
+pub async fn fetch(client: &Client, kind: CompletionKind, job: Option<&str>) -> Result<Vec<Candidate>, Error> {
+    match kind {
+        CompletionKind::Jobs => Ok(client.parameterized_jobs()
+            .await?
+            .into_iter()
+            .map(|job| Candidate { value: job.id, help: Some(job.namespace) })
+            .collect()),
+        CompletionKind::Namespaces => Ok(client.namespaces()
+            .await?
+            .into_iter()
+            .map(|ns| Candidate { value: ns.name, help: Some(ns.description).filter(|d| !d.is_empty()) })
+            .collect()),
+        CompletionKind::Tasks => {
+            let jobs: Vec<_> = client.parameterized_jobs()
+                .await?
+                .into_iter()
+                .filter(|j| job.is_none_or(|id| j.id == id))
+                .collect();
+            let specs = join_all(jobs.iter().map(|j| client.spec(j))).await;
+
+            // tasks with the same name in several jobs are proposed once, and a job whose spec
+            // can't be fetched does not prevent the tasks of the others from being proposed
+            let mut tasks = BTreeMap::new();
+            for (job, spec) in jobs.iter().zip(specs) {
+                let Ok(spec) = spec else {
+                    continue;
+                };
+
+                for task in spec.task_groups.iter().flat_map(|group| &group.tasks) {
+                    tasks.entry(task.name.clone()).or_insert_with(|| job.id.clone());
+                }
+            }
+
+            Ok(tasks.into_iter()
+                .map(|(value, job)| Candidate { value, help: Some(job) })
+                .collect())
+        }
+    }
+}
+
+impl CompletionCache {
+    /// Create a new cache
+    ///
+    /// # Arguments
+    ///
+    /// * `dir` - PathBuf
+    /// * `ttl` - Duration
+    pub fn new(dir: PathBuf, ttl: Duration) -> Self {
+        CompletionCache { dir, ttl }
+    }
+
+    /// Return the candidates from the cache, or fetch them when the cache has expired. Failing
+    /// to write the cache does not fail the completion
+    ///
+    /// # Arguments
+    ///
+    /// * `&self` - CompletionCache
+    /// * `client` - &Client
+    /// * `address` - &str
+    /// * `kind` - CompletionKind
+    /// * `job` - Option<&str>
+    pub async fn candidates(&self, client: &Client, address: &str, kind: CompletionKind, job: Option<&str>) -> Result<Vec<Candidate>, Error> {
+        let path = self.path(address, kind, job);
+        if let Some(entry) = read_entry(&path).filter(|e| Utc::now() - e.fetched_at < self.ttl) {
+            return Ok(entry.candidates);
+        }
+
+        let candidates = fetch(client, kind, job).await?;
+        write_entry(&path, &CacheEntry { fetched_at: Utc::now(), candidates: candidates.clone() });
+
+        Ok(candidates)
+    }
+
+    /// Return the path of the cache file of a kind of candidates for a Nomad address, and for a job
+    /// when the candidates are limited to this job
+    ///
+    /// # Arguments
+    ///
+    /// * `&self` - CompletionCache
+    /// * `address` - &str
+    /// * `kind` - CompletionKind
+    /// * `job` - Option<&str>
+    fn path(&self, address: &str, kind: CompletionKind, job: Option<&str>) -> PathBuf {
+        let key = match job {
+            Some(job) => format!("{address}#{job}"),
+            None => address.to_string()
+        };
+        let hash = format!("{:x}", Sha256::digest(key.as_bytes()));
+
+        self.dir.join(format!("{}-{kind}.json", &hash[..ADDRESS_HASH_LEN]))
+    }
+}
+
+/// Read a cache file. An unreadable cache is ignored
+///
+/// # Arguments
+///
+/// * `path` - &Path
+fn read_entry(path: &Path) -> Option<CacheEntry> {
+    let content = fs::read_to_string(path).ok()?;
+
+    serde_json::from_str(&content).ok()
+}
+
+/// Write a cache file, the directory is created if needed
+///
+/// # Arguments
+///
+/// * `path` - &Path
+/// * `entry` - &CacheEntry
+fn write_entry(path: &Path, entry: &CacheEntry) -> Option<()> {
+    fs::create_dir_all(path.parent()?).ok()?;
+    let content = serde_json::to_string(entry).ok()?;
+
+    fs::write(path, content).ok()
+}
@@ -0,0 +1,333 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use serde::{Deserialize, Serialize};
+use crate::error::{Error, self};
+use crate::nomad::acl::LoginToken;
+
+// Constant
+const CONFIG_DIR: &str = "noumead";
+const CONFIG_FILE: &str = "config.toml";
+/// Service under which the tokens of the contexts are stored in the keyring of the OS
+const KEYRING_SERVICE: &str = "noumead";
+/// Service under which the tokens issued by the auth methods are cached until they expire
+const LOGIN_KEYRING_SERVICE: &str = "noumead-login";
+pub const NOMAD_ADDR_ENV: &str = "NOMAD_ADDR";
+pub const NOMAD_TOKEN_ENV: &str = "NOMAD_TOKEN";
+
+/// Configuration of noumead. Each context describes how to reach a Nomad cluster
+#[derive(Debug, Deserialize, Serialize, Default, Clone)]
+pub struct Config {
+    /// Context used when none is given on the command line
+    #[serde(default)]
+    pub context: Option<String>,
+
+    #[serde(default)]
+    pub contexts: BTreeMap<String, Context>
+}
+
+#[derive(Debug, Deserialize, Serialize, Default, Clone, PartialEq, Eq)]
+pub struct Context {
+    /// Address of the Nomad server
+    #[serde(default)]
+    pub address: Option<String>,
+
+    /// Command printing the token on its standard output, e.g. `pass show nomad/prod`
+    #[serde(default)]
+    pub token_command: Option<String>,
+
+    /// Auth method used by `noumead login` when no method is given
+    #[serde(default)]
+    pub auth_method: Option<String>,
+
+    /// Headers added to every request, e.g. the key expected by an auth gateway in front of Nomad
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>
+}
+
+/// Connection flags typed on the command line
+#[derive(Debug, Clone, Default)]
+pub struct ConnectionArgs {
+    pub context: Option<String>,
+    pub address: Option<String>,
+    pub token: Option<String>,
+    pub token_file: Option<PathBuf>,
+    pub headers: Vec<(String, String)>
+}
+
+/// Sources from which the token of a context can be read
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TokenSources {
+    /// No token is read, e.g. when a new token is about to be stored
+    None,
+    /// Only the flags, the environment and the token cached by a login, no command is run and the token stored
+    /// in the keyring is not read
+    Cached,
+    /// Every source, including the `token_command` of the context and the token stored in the keyring
+    All
+}
+
+/// Address, token and headers used to reach a Nomad cluster
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Connection {
+    /// Context in use, the one given on the command line or the default one of the config
+    pub context: Option<String>,
+    pub address: Option<String>,
+    pub token: Option<String>,
+    pub headers: Vec<(String, String)>
+}
+
+/// Return the path of the config in the config directory of the user
+pub fn default_path() -> Option<PathBuf> {
+    dirs::config_dir().map(|dir| dir.join(CONFIG_DIR).join(CONFIG_FILE))
+}
+
+impl Config {
+    /// Load the config. A missing config is returned as an empty config
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - &Path
+    pub fn load(path: &Path) -> Result<Config, Error> {
+        if !path.exists() {
+            return Ok(Config::default());
+        }
+
+        let to_err = |msg: String| Error::Config(format!("{}: {msg}", path.display()));
+        let content = fs::read_to_string(path).map_err(|err| to_err(err.to_string()))?;
+
+        toml::from_str(&content).map_err(|err| to_err(err.to_string()))
+    }
+
+    /// Return the name of the context to use, the given one or the default one of the config
+    ///
+    /// # Arguments
+    ///
+    /// * `&self` - Config
+    /// * `name` - Option<&str>
+    pub fn context_name<'a>(&'a self, name: Option<&'a str>) -> Option<&'a str> {
+        name.or(self.context.as_deref())
+    }
+
+    /// Return the context to use. A context given on the command line must exist in the config
+    ///
+    /// # Arguments
+    ///
+    /// * `&self` - Config
+    /// * `name` - Option<&str>
+    pub fn context(&self, name: Option<&str>) -> Result<Option<&Context>, Error> {
+        match self.context_name(name) {
+            Some(name) => self.contexts.get(name)
+                .map(Some)
+                .ok_or_else(|| Error::Config(format!("{} {name}", error::CONTEXT_NOT_FOUND_ERR))),
+            None => Ok(None)
+        }
+    }
+
+    /// Resolve the connection to Nomad. The address is read from the flag, the environment, then the context.
+    /// The token is read from the flags, the environment, then the context. A context given on the command line
+    /// takes precedence over the environment, which is ignored, so that the token of a cluster is never sent
+    /// to another one. The headers of the flags override the ones of the context
+    ///
+    /// # Arguments
+    ///
+    /// * `&self` - Config
+    /// * `args` - ConnectionArgs
+    /// * `env` - F
+    /// * `sources` - TokenSources
+    pub fn connection<F>(&self, args: ConnectionArgs, env: F, sources: TokenSources) -> Result<Connection, Error>
+        where
+            F: Fn(&str) -> Option<String>
+    {
+        let explicit_context = args.context.is_some();
+        let from_env = |var: &str| match explicit_context {
+            true => None,
+            false => env(var)
+        };
+
+        let name = self.context_name(args.context.as_deref()).map(str::to_string);
+        let context = self.context(name.as_deref())?;
+
+        let address = args.address.or_else(|| from_env(NOMAD_ADDR_ENV)).or_else(|| context.and_then(|c| c.address.clone()));
+
+        let mut headers = args.headers;
+        if let Some(context) = context {
+            let inherited = context.headers.iter()
+                .filter(|(name, _)| !headers.iter().any(|(flag, _)| flag.eq_ignore_ascii_case(name)))
+                .map(|(name, value)| (name.clone(), value.clone()))
+                .collect::<Vec<_>>();
+            headers.extend(inherited);
+        }
+
+        let token = match (args.token, sources) {
+            (Some(token), _) => Some(token),
+            (None, TokenSources::None) => None,
+            (None, sources) => match (&args.token_file, from_env(NOMAD_TOKEN_ENV), context) {
+                (Some(path), _, _) => Some(token_from_file(path)?),
+                (None, Some(token), _) => Some(token),
+                (None, None, Some(context)) => context_token(name.as_deref().unwrap_or_default(), context, sources)?,
+                (None, None, None) => None
+            }
+        };
+
+        Ok(Connection { context: name, address, token, headers })
+    }
+}
+
+/// Return the token of a context from its command or from the keyring, where the token cached by the login
+/// with an auth method is used until it expires. The keyring may be unavailable, e.g. without a secret service,
+/// in which case the context has no token. Only the token cached by the login is read from the cached sources
+///
+/// # Arguments
+///
+/// * `name` - &str
+/// * `context` - &Context
+/// * `sources` - TokenSources
+fn context_token(name: &str, context: &Context, sources: TokenSources) -> Result<Option<String>, Error> {
+    if let (Some(command), TokenSources::All) = (&context.token_command, sources) {
+        return token_from_command(command).map(Some);
+    }
+
+    let login_token = login_token_from_keyring(name).ok().flatten()
+        .filter(|token| !token.is_expired(chrono::Utc::now()));
+
+    match (login_token, sources) {
+        (Some(token), _) => Ok(Some(token.secret_id)),
+        (None, TokenSources::All) => Ok(token_from_keyring(name).ok().flatten()),
+        (None, _) => Ok(None)
+    }
+}
+
+/// Read a token from a file. Surrounding whitespaces such as the trailing newline are removed
+///
+/// # Arguments
+///
+/// * `path` - &Path
+pub fn token_from_file(path: &Path) -> Result<String, Error> {
+    let content = fs::read_to_string(path)
+        .map_err(|err| Error::Token(format!("{}: {err}", path.display())))?;
+
+    non_empty(content.trim().to_string(), &path.display().to_string())
+}
+
+/// Run a command through the shell and read the token from its standard output
+///
+/// # Arguments
+///
+/// * `command` - &str
+pub fn token_from_command(command: &str) -> Result<String, Error> {
+    let output = shell(command)
+        .output()
+        .map_err(|err| Error::Token(format!("{command}: {err}")))?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(Error::Token(format!("{command} exited with {}: {}", output.status, stderr.trim())));
+    }
+
+    let stdout = String::from_utf8(output.stdout)
+        .map_err(|err| Error::Token(format!("{command}: {err}")))?;
+
+    non_empty(stdout.trim().to_string(), command)
+}
+
+/// Read the token of a context from the keyring of the OS. A context without token returns None
+///
+/// # Arguments
+///
+/// * `context` - &str
+pub fn token_from_keyring(context: &str) -> Result<Option<String>, Error> {
+    read_keyring(KEYRING_SERVICE, context)
+}
+
+/// Store the token of a context in the keyring of the OS
+///
+/// # Arguments
+///
+/// * `context` - &str
+/// * `token` - &str
+pub fn store_token(context: &str, token: &str) -> Result<(), Error> {
+    write_keyring(KEYRING_SERVICE, context, token)
+}
+
+/// Read the token issued by an auth method for a context from the keyring of the OS. The token is returned
+/// even when it has expired
+///
+/// # Arguments
+///
+/// * `context` - &str
+pub fn login_token_from_keyring(context: &str) -> Result<Option<LoginToken>, Error> {
+    read_keyring(LOGIN_KEYRING_SERVICE, context)?
+        .map(|content| serde_json::from_str(&content).map_err(|err| Error::Token(err.to_string())))
+        .transpose()
+}
+
+/// Cache the token issued by an auth method for a context in the keyring of the OS, along with its expiration
+///
+/// # Arguments
+///
+/// * `context` - &str
+/// * `token` - &LoginToken
+pub fn store_login_token(context: &str, token: &LoginToken) -> Result<(), Error> {
+    let content = serde_json::to_string(token).map_err(|err| Error::Serialize(err.to_string()))?;
+
+    write_keyring(LOGIN_KEYRING_SERVICE, context, &content)
+}
+
+/// Read an entry of the keyring. A missing entry returns None
+///
+/// # Arguments
+///
+/// * `service` - &str
+/// * `context` - &str
+fn read_keyring(service: &str, context: &str) -> Result<Option<String>, Error> {
+    let entry = keyring::Entry::new(service, context).map_err(|err| Error::Token(err.to_string()))?;
+
+    match entry.get_password() {
+        Ok(content) => Ok(Some(content)),
+        Err(keyring::Error::NoEntry) => Ok(None),
+        Err(err) => Err(Error::Token(err.to_string()))
+    }
+}
+
+/// Write an entry of the keyring
+///
+/// # Arguments
+///
+/// * `service` - &str
+/// * `context` - &str
+/// * `content` - &str
+fn write_keyring(service: &str, context: &str, content: &str) -> Result<(), Error> {
+    keyring::Entry::new(service, context)
+        .and_then(|entry| entry.set_password(content))
+        .map_err(|err| Error::Token(err.to_string()))
+}
+
+/// Build the command running the given command line through the shell of the OS
+///
+/// # Arguments
+///
+/// * `command` - &str
+fn shell(command: &str) -> Command {
+    let mut cmd = match cfg!(windows) {
+        true => Command::new("cmd"),
+        false => Command::new("sh")
+    };
+
+    cmd.arg(if cfg!(windows) { "/C" } else { "-c" }).arg(command);
+    cmd
+}
+
+/// Refuse an empty token which would be sent as an anonymous token
+///
+/// # Arguments
+///
+/// * `token` - String
+/// * `source` - &str
+fn non_empty(token: String, source: &str) -> Result<String, Error> {
+    match token.is_empty() {
+        true => Err(Error::Token(format!("{source}: {}", error::EMPTY_TOKEN_ERR))),
+        false => Ok(token)
+    }
+}
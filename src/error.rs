@@ -1,27 +1,88 @@
 use std::env::VarError;
-use inquire::InquireError;
 
 // Error constant for scenario error
 pub const SELECTED_JOB_NOT_FOUND_ERR: &str = "Unable to found the selected job";
+pub const AMBIGUOUS_JOB_ERR: &str = "Several jobs share this id, select one with --namespace among";
 pub const NO_RUNNING_JOB_ERR: &str = "No running job has been found";
 pub const MISSING_ALLOCATION_ERR: &str = "Unable to found an allocation for the given dispatch";
 pub const JOBS_NOT_FOUND_ERR: &str = "No jobs with parameterized options has been founded";
-pub const SELECTED_ITEM_NOT_FOUND_ERR: &str = "Unable to found the selected item";
-pub const MISSING_REQUIRED_FIELD_ERR: &str = "You must fill this field as the value is required";
 pub const REQ_BUILD_FAIL_ERR: &str = "Failed to build request";
 pub const MISSING_BASE_URL_ERR: &str = "Failed to get the url of the nomad server";
+pub const INVALID_ADDRESS_ERR: &str = "The address of Nomad must be an http(s) url or a unix socket, got";
+pub const HEADER_FORMAT_ERR: &str = "The header must be formatted as Name:Value, got";
+pub const INVALID_HEADER_ERR: &str = "Invalid name or value for the header";
+pub const UNIX_SOCKET_ERR: &str = "Unix sockets are not supported on this platform, got";
 pub const REQ_BUILD_ERR: &str = "Unable to build the client for sending request to nomad";
+pub const NO_DISPATCHED_JOB_ERR: &str = "No dispatched job has been selected";
+pub const META_FORMAT_ERR: &str = "The meta must be formatted as key=value, got";
+pub const SCHEMA_FORMAT_ERR: &str = "The schema must be a toml or a yaml file";
+pub const SCHEMA_NOT_FOUND_ERR: &str = "No noumead.schema.toml or noumead.schema.yaml has been found";
+pub const SCHEMA_DRIFT_ERR: &str = "The schema does not match the jobs";
+pub const BATCH_FORMAT_ERR: &str = "The batch file must be a csv or a jsonl file";
+pub const BATCH_ROW_ERR: &str = "Each line must be a json object of metas, got";
+pub const VAR_FIELD_ERR: &str = "the variable has no item named";
+pub const VAR_NOT_FOUND_ERR: &str = "no variable has been found in the namespace";
+pub const DISPATCH_NOT_GRANTED_WARN: &str = "The policies of the token don't grant dispatch-job on the namespace";
+pub const VAR_ITEM_FORMAT_ERR: &str = "The item must be formatted as key=value, got";
+pub const VAR_ITEMS_ERR: &str = "the items must be a json object of strings";
+pub const VAR_AMBIGUOUS_ERR: &str = "the variable has several items, select one with path#item";
+pub const BATCH_FAILED_ERR: &str = "Some rows could not be dispatched, see the results file";
+pub const STOP_FAILED_ERR: &str = "Some jobs could not be stopped";
+pub const CONTEXT_NOT_FOUND_ERR: &str = "no context has been found in the config named";
+pub const MISSING_CONTEXT_ERR: &str = "a context is required, use --context or set the default context in the config";
+pub const EMPTY_TOKEN_ERR: &str = "the token is empty";
+pub const LOGIN_TIMEOUT_ERR: &str = "the OIDC provider did not redirect to the callback in time";
+pub const CALLBACK_QUERY_ERR: &str = "the redirection of the OIDC provider has no code or state";
+
+// Reason constant for invalid metas
+pub const REQUIRED_META_REASON: &str = "a value is required";
+pub const UNKNOWN_META_REASON: &str = "the job does not accept this meta";
+pub const PATTERN_META_REASON: &str = "the value does not match the pattern";
+pub const CHOICES_META_REASON: &str = "the value must be one of";
+pub const INVALID_PATTERN_REASON: &str = "the job define an invalid pattern";
+pub const TYPE_META_REASON: &str = "the value is not a valid";
+pub const UNKNOWN_TYPE_REASON: &str = "the job define an unknown type";
+pub const ENV_META_REASON: &str = "unable to read the environment variable";
+pub const SECRET_REDISPATCH_REASON: &str = "the value of a secret is redacted and can't be dispatched again";
+pub const FILE_META_REASON: &str = "unable to read the file";
+
+// Hint constant for errors returned by the Nomad API
+const FORBIDDEN_HINT: &str = "check that the token is valid and that its ACL policy allows this operation";
+const NOT_FOUND_HINT: &str = "check the namespace and the name of the job";
+const CONFLICT_HINT: &str = "the data has been modified since it was read, read it again before writing";
+const SERVER_ERROR_HINT: &str = "the Nomad server failed to process the request, check the server logs";
 
 #[derive(Debug)]
 pub enum Error {
     MissingEnv(String),
     NomadReqErr(String),
+    Api {
+        status: u16,
+        method: String,
+        endpoint: String,
+        body: String
+    },
     Serialize(String),
-    Dispatch,
     ScenarioFinished,
     ScenarioErr(String),
     MissingTask,
-    MaxRetry
+    MaxRetry,
+    Terminal(String),
+    InvalidMeta {
+        key: String,
+        reason: String
+    },
+    Schema(String),
+    Batch(String),
+    AlreadyDispatched {
+        job: String
+    },
+    Variable(String),
+    Usage(String),
+    Config(String),
+    Token(String),
+    Login(String),
+    Timeout(String)
 }
 
 impl std::fmt::Display for Error {
@@ -29,12 +90,50 @@ impl std::fmt::Display for Error {
         match self {
             Error::MissingEnv(msg) => write!(f, "Unable to find environment variable due to: {msg}"),
             Error::NomadReqErr(msg) => write!(f, "An error occurred while querying the HTTP endpoint of Nomad: {msg}"),
+            Error::Api { status, method, endpoint, body } => {
+                write!(f, "Nomad returned {status} for {method} {endpoint}: {}", body.trim())?;
+                match self.hint() {
+                    Some(hint) => write!(f, " (hint: {hint})"),
+                    None => Ok(())
+                }
+            },
             Error::Serialize(msg) => write!(f, "Error while serializing data: {msg}"),
-            Error::Dispatch => write!(f, "Job dispatching has fail"),
             Error::ScenarioFinished => write!(f, "No option selected. Terminating the program"),
             Error::ScenarioErr(msg) => write!(f, "The command has stopped due to: {msg}"),
             Error::MissingTask => write!(f, "The selected task could not be found"),
-            Error::MaxRetry => write!(f, "Max retry has been achieved when fetching data")
+            Error::MaxRetry => write!(f, "Max retry has been achieved when fetching data"),
+            Error::Terminal(msg) => write!(f, "Unable to render the terminal interface due to: {msg}"),
+            Error::InvalidMeta { key, reason } => write!(f, "Invalid value for the meta {key}: {reason}"),
+            Error::Schema(msg) => write!(f, "Unable to load the schema due to: {msg}"),
+            Error::Batch(msg) => write!(f, "Unable to process the batch file due to: {msg}"),
+            Error::AlreadyDispatched { job } => write!(f, "The job has already been dispatched as {job}"),
+            Error::Variable(msg) => write!(f, "Unable to read the variable {msg}"),
+            Error::Usage(msg) => write!(f, "Unable to read the usage stats due to: {msg}"),
+            Error::Config(msg) => write!(f, "Unable to load the config due to: {msg}"),
+            Error::Token(msg) => write!(f, "Unable to read the token due to: {msg}"),
+            Error::Login(msg) => write!(f, "Unable to log in due to: {msg}"),
+            Error::Timeout(msg) => write!(f, "Nomad did not respond in time: {msg}")
+        }
+    }
+}
+
+impl Error {
+    /// Return a hint which could help the user to fix an error returned by the Nomad API
+    ///
+    /// # Arguments
+    ///
+    /// * `&self` - Error
+    pub fn hint(&self) -> Option<&'static str> {
+        let Error::Api { status, .. } = self else {
+            return None;
+        };
+
+        match status {
+            401 | 403 => Some(FORBIDDEN_HINT),
+            404 => Some(NOT_FOUND_HINT),
+            409 => Some(CONFLICT_HINT),
+            500..=599 => Some(SERVER_ERROR_HINT),
+            _ => None
         }
     }
 }
@@ -52,9 +151,3 @@ impl From<reqwest::Error> for Error {
         Error::NomadReqErr(err.to_string())
     }
 }
-
-impl From<InquireError> for Error {
-    fn from(_: InquireError) -> Self {
-        Error::ScenarioFinished
-    }
-}
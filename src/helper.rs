@@ -1,4 +1,4 @@
-use serde::Serialize;
+use serde::{Deserialize, Deserializer, Serialize};
 use crate::error::Error;
 
 /// Convert a struct to a stringify json value
@@ -13,6 +13,21 @@ pub fn to_json<T: Serialize>(arg: T) -> Result<String, Error> {
     Ok(res)
 }
 
+/// Deserialize a value which could be null into its default value
+///
+/// # Arguments
+///
+/// * `deserializer` - D
+pub fn null_as_default<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+    where
+        D: Deserializer<'de>,
+        T: Deserialize<'de> + Default
+{
+    let value = Option::<T>::deserialize(deserializer)?;
+
+    Ok(value.unwrap_or_default())
+}
+
 pub trait Base64 {
     /// Convert a string to a base64 string
     fn to_base64(&self) -> String;
@@ -1,73 +1,498 @@
-use std::collections::HashMap;
-use inquire::{Select, Text, MultiSelect};
-use crate::error::{Error, self};
+use std::collections::{BTreeMap, HashMap};
+use std::str::FromStr;
+use chrono::{NaiveDate, Utc};
+use crossterm::style::Stylize;
+use fuzzy_matcher::FuzzyMatcher;
+use fuzzy_matcher::skim::SkimMatcherV2;
+use inquire::{Confirm, CustomType, DateSelect, Editor, MultiSelect, Password, PasswordDisplayMode, Select, Text};
+use inquire::error::{CustomUserError, InquireResult};
+use inquire::list_option::ListOption;
+use inquire::validator::Validation;
+use noumead::error::{self, Error};
+use noumead::nomad::job::Job;
+use noumead::nomad::meta::{MetaField, MetaPrompt, MetaType, DATE_FORMAT};
+use noumead::nomad::variable;
+use noumead::usage::Usage;
+use crate::cli::source::VAR_PREFIX;
 
 // constant
 const SELECT_PAGE_SIZE: usize = 20;
+const FUZZY_SCORE_SCALE: i64 = 100_000;
+const SKIP_CHOICE: &str = "(none)";
+const JSON_EXTENSION: &str = ".json";
+const NUMBER_ERR: &str = "Please type a valid number";
+const VALUE_SOURCE: &str = "Enter a value";
+const VAR_SOURCE: &str = "Read from a Nomad variable";
 
-/// Display a list of items to the user
+/// Item of a list shown to the user. The choice carries the original item so that the selected item is
+/// returned as is, even when several items have the same label. The help is rendered dimmed after the label
+/// and is not searched
+struct Choice<'a, T> {
+    item: &'a T,
+    label: String,
+    help: Option<String>
+}
+
+impl<T> std::fmt::Display for Choice<'_, T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.help {
+            Some(help) => write!(f, "{}  {}", self.label, help.as_str().dim()),
+            None => write!(f, "{}", self.label)
+        }
+    }
+}
+
+impl<'a, T: std::fmt::Display> Choice<'a, T> {
+    /// Create a choice labelled with the item
+    ///
+    /// # Arguments
+    ///
+    /// * `item` - &T
+    fn new(item: &'a T) -> Self {
+        Choice { item, label: item.to_string(), help: None }
+    }
+}
+
+impl<'a> Choice<'a, Job> {
+    /// Create a choice for a job with its namespace, status and type as help
+    ///
+    /// # Arguments
+    ///
+    /// * `job` - &Job
+    fn job(job: &'a Job) -> Self {
+        Choice {
+            item: job,
+            label: job.name.clone(),
+            help: Some(format!("{} · {} · {}", job.namespace, job.status, job.kind))
+        }
+    }
+}
+
+/// Display a list of items to the user and return the selected item
+///
+/// # Arguments
+///
+/// * `items` - &[T]
+/// * `question` - &str
+pub fn select<'a, T: std::fmt::Display>(items: &'a [T], question: &str) -> Result<&'a T, Error> {
+    select_choice(items.iter().map(Choice::new).collect(), question)
+}
+
+/// Display a list of jobs to the user with their namespace, status and type. The recently or frequently
+/// dispatched jobs are shown first
 ///
 /// # Arguments
 ///
-/// * `args` - &[T: ToString]
+/// * `jobs` - &[Job]
+/// * `usage` - &Usage
 /// * `question` - &str
-pub fn select<T: ToString>(args: &[T], question: &str) -> Result<(String, usize), Error> {
-    let items: Vec<String> = args.iter()
-        .map(|i| i.to_string())
+pub fn select_job<'a>(jobs: &'a [Job], usage: &Usage, question: &str) -> Result<&'a Job, Error> {
+    let choices = usage.rank(jobs, Utc::now())
+        .into_iter()
+        .map(|idx| Choice::job(&jobs[idx]))
         .collect();
 
-    let res = Select::new(question, items.clone())
-        .with_page_size(SELECT_PAGE_SIZE)
-        .prompt()?;
+    select_choice(choices, question)
+}
+
+/// Display a list of jobs where the user can select multiple jobs. The namespace, status and type
+/// of the jobs are shown
+///
+/// # Arguments
+///
+/// * `jobs` - &[Job]
+/// * `question` - &str
+pub fn multi_select_jobs<'a>(jobs: &'a [Job], question: &str) -> Result<Vec<&'a Job>, Error> {
+    select_choices(jobs.iter().map(Choice::job).collect(), question)
+}
+
+/// Display a list of choices which can be searched with a fuzzy matching on their label. The best matches
+/// are shown first, then the choices keep their order
+///
+/// # Arguments
+///
+/// * `choices` - Vec<Choice<T>>
+/// * `question` - &str
+fn select_choice<'a, T>(choices: Vec<Choice<'a, T>>, question: &str) -> Result<&'a T, Error> {
+    let matcher = SkimMatcherV2::default();
+    let scorer = |input: &str, choice: &Choice<T>, _: &str, idx: usize| fuzzy_score(&matcher, input, &choice.label, idx);
+    let formatter = |option: ListOption<&Choice<T>>| option.value.label.clone();
 
-    let idx = items.binary_search(&res)
-        .map_err(|_| Error::ScenarioErr(error::SELECTED_ITEM_NOT_FOUND_ERR.to_string()))?;
+    let res = Select::new(question, choices)
+        .with_page_size(SELECT_PAGE_SIZE)
+        .with_scorer(&scorer)
+        .with_formatter(&formatter)
+        .prompt()
+        .map_err(|_| Error::ScenarioFinished)?;
 
-    Ok((res, idx))
+    Ok(res.item)
 }
 
-/// Display a list of items where the user can select multiple options
+/// Display a list of choices where the user can select multiple options. The choices are searched
+/// the same way as a single selection
 ///
 /// # Arguments
 ///
-/// * `args` - &[T]
+/// * `choices` - Vec<Choice<T>>
 /// * `question` - &str
-pub fn multi_select<T: ToString>(args: &[T], question: &str) -> Result<(Vec<String>, Vec<usize>), Error> {
-    let items: Vec<String> = args.iter()
-        .map(|arg| arg.to_string())
-        .collect();
+fn select_choices<'a, T>(choices: Vec<Choice<'a, T>>, question: &str) -> Result<Vec<&'a T>, Error> {
+    let matcher = SkimMatcherV2::default();
+    let scorer = |input: &str, choice: &Choice<T>, _: &str, idx: usize| fuzzy_score(&matcher, input, &choice.label, idx);
+    let formatter = |options: &[ListOption<&Choice<T>>]| options.iter()
+        .map(|option| option.value.label.as_str())
+        .collect::<Vec<_>>()
+        .join(", ");
 
-    let res = MultiSelect::new(question, items.clone())
+    let res = MultiSelect::new(question, choices)
         .with_page_size(SELECT_PAGE_SIZE)
-        .prompt()?;
+        .with_scorer(&scorer)
+        .with_formatter(&formatter)
+        .prompt()
+        .map_err(|_| Error::ScenarioFinished)?;
 
-    let indexes: Vec<usize> = res
-        .iter()
-        .filter_map(|v| items.binary_search(v).ok())
-        .collect();
+    Ok(res.into_iter().map(|choice| choice.item).collect())
+}
+
+/// Score a choice against the search input. The score of the match is scaled so that the position of the choice
+/// break the ties, which keeps the order of the list when the input is empty
+///
+/// # Arguments
+///
+/// * `matcher` - &SkimMatcherV2
+/// * `input` - &str
+/// * `label` - &str
+/// * `idx` - usize
+fn fuzzy_score(matcher: &SkimMatcherV2, input: &str, label: &str, idx: usize) -> Option<i64> {
+    let score = match input.is_empty() {
+        true => 0,
+        false => matcher.fuzzy_match(label, input)?
+    };
 
-    Ok((res, indexes))
+    Some(score * FUZZY_SCORE_SCALE - idx as i64)
 }
 
-/// Show a prompt from multiple options the user need may (or not) answer
+/// Prompt the value of each meta. The description of the meta is shown as an help message, the default value
+/// is used when the answer is empty and a list is shown when the meta define a fixed set of choices.
+/// Otherwise the prompt depends on the type of the meta. Secret metas are masked. Optional typed metas can be skipped with esc.
+/// When the namespace has variables, the value of the metas which can't be typed as var:path#item is either entered
+/// or read from a variable, which is returned as var: alone
 ///
 /// # Arguments
 ///
-/// * `items` - Vec<String>
-/// * `msg` - &str
-/// * `required` - bool
-pub fn prompt_vector(items: Vec<String>, msg: &str, required: bool) -> Result<HashMap<String, String>, Error> {
+/// * `fields` - &[MetaField]
+/// * `variables` - bool
+pub fn prompt_metas(fields: &[MetaField], variables: bool) -> Result<HashMap<String, String>, Error> {
     let mut map = HashMap::new();
-    for item in items {
-        let answer = Text::new(&format!("{}: {}", msg, item))
-            .prompt()?;
+    for field in fields {
+        let msg = match field.required {
+            true => format!("Input the required value for: {}", field.name),
+            false => format!("Input value for optional: {}", field.name)
+        };
 
-        if answer.is_empty() && required {
-            return Err(Error::ScenarioErr(error::MISSING_REQUIRED_FIELD_ERR.to_string()))
+        // the text and the secret prompts accept a reference to a variable as is
+        let prompt = field.prompt();
+        let typed = !matches!(prompt, MetaPrompt::Text | MetaPrompt::Password);
+        if variables && typed && select_source(&msg)? == VAR_SOURCE {
+            map.insert(field.name.clone(), VAR_PREFIX.to_string());
+            continue;
         }
 
-        map.insert(item, answer);
+        let answer = match prompt {
+            MetaPrompt::Select => prompt_choice(field, &msg)?,
+            MetaPrompt::Password => prompt_secret(field, &msg)?,
+            MetaPrompt::Int => prompt_number::<i64>(field, &msg)?,
+            MetaPrompt::Float => prompt_number::<f64>(field, &msg)?,
+            MetaPrompt::Confirm => prompt_bool(field, &msg)?,
+            MetaPrompt::DateSelect => prompt_date(field, &msg)?,
+            MetaPrompt::Editor => prompt_json(field, &msg)?,
+            MetaPrompt::Text => prompt_text(field, &msg)?
+        };
+
+        map.insert(field.name.clone(), answer);
     }
 
     Ok(map)
 }
+
+/// Ask whether the value of a meta is entered or read from a Nomad variable
+///
+/// # Arguments
+///
+/// * `msg` - &str
+fn select_source(msg: &str) -> Result<&'static str, Error> {
+    Select::new(msg, vec![VALUE_SOURCE, VAR_SOURCE])
+        .prompt()
+        .map_err(|_| Error::ScenarioFinished)
+}
+
+/// Prompt a free form value which is validated against the meta. A reference to a Nomad variable
+/// starting with var: is validated once its value has been read
+///
+/// # Arguments
+///
+/// * `field` - &MetaField
+/// * `msg` - &str
+fn prompt_text(field: &MetaField, msg: &str) -> Result<String, Error> {
+    let validated = field.clone();
+    let validator = move |input: &str| match input.starts_with(VAR_PREFIX) {
+        true => Ok(Validation::Valid),
+        false => to_validation(validated.validate(input))
+    };
+
+    let mut prompt = Text::new(msg).with_validator(validator);
+    if let Some(description) = &field.description {
+        prompt = prompt.with_help_message(description);
+    }
+
+    if let Some(default) = &field.default {
+        prompt = prompt.with_default(default);
+    }
+
+    prompt.prompt().map_err(|_| Error::ScenarioFinished)
+}
+
+/// Prompt a secret value which is masked while typed. An empty answer fallback to the default of the meta
+/// and a reference to a Nomad variable is validated once its value has been read
+///
+/// # Arguments
+///
+/// * `field` - &MetaField
+/// * `msg` - &str
+fn prompt_secret(field: &MetaField, msg: &str) -> Result<String, Error> {
+    let validated = field.clone();
+    let validator = move |input: &str| match (input.is_empty() && validated.default.is_some()) || input.starts_with(VAR_PREFIX) {
+        true => Ok(Validation::Valid),
+        false => to_validation(validated.validate(input))
+    };
+
+    let mut prompt = Password::new(msg)
+        .with_display_mode(PasswordDisplayMode::Masked)
+        .without_confirmation()
+        .with_validator(validator);
+
+    if let Some(description) = &field.description {
+        prompt = prompt.with_help_message(description);
+    }
+
+    let answer = match field.required {
+        true => prompt.prompt().map(Some),
+        false => prompt.prompt_skippable()
+    };
+
+    to_answer(answer)
+}
+
+/// Prompt a value among the choices of the meta. Optional metas can be skipped
+///
+/// # Arguments
+///
+/// * `field` - &MetaField
+/// * `msg` - &str
+fn prompt_choice(field: &MetaField, msg: &str) -> Result<String, Error> {
+    let mut choices = field.choices.clone();
+    if !field.required {
+        choices.insert(0, SKIP_CHOICE.to_string());
+    }
+
+    let cursor = field.default
+        .as_ref()
+        .and_then(|d| choices.iter().position(|c| c == d))
+        .unwrap_or_default();
+
+    let mut prompt = Select::new(msg, choices)
+        .with_page_size(SELECT_PAGE_SIZE)
+        .with_starting_cursor(cursor);
+
+    if let Some(description) = &field.description {
+        prompt = prompt.with_help_message(description);
+    }
+
+    let answer = prompt.prompt().map_err(|_| Error::ScenarioFinished)?;
+    match answer == SKIP_CHOICE {
+        true => Ok(String::new()),
+        false => Ok(answer)
+    }
+}
+
+/// Prompt a number which is parsed before being validated against the meta
+///
+/// # Arguments
+///
+/// * `field` - &MetaField
+/// * `msg` - &str
+fn prompt_number<T>(field: &MetaField, msg: &str) -> Result<String, Error>
+    where
+        T: FromStr + ToString + Clone + 'static
+{
+    let validated = field.clone();
+    let validator = move |input: &T| to_validation(validated.validate(&input.to_string()));
+
+    let mut prompt = CustomType::<T>::new(msg)
+        .with_error_message(NUMBER_ERR)
+        .with_validator(validator);
+
+    if let Some(default) = field.default.as_ref().and_then(|d| d.parse::<T>().ok()) {
+        prompt = prompt.with_default(default);
+    }
+
+    if let Some(description) = &field.description {
+        prompt = prompt.with_help_message(description);
+    }
+
+    let answer = match field.required {
+        true => prompt.prompt().map(Some),
+        false => prompt.prompt_skippable()
+    };
+
+    to_answer(answer.map(|v| v.map(|v| v.to_string())))
+}
+
+/// Prompt a yes / no answer
+///
+/// # Arguments
+///
+/// * `field` - &MetaField
+/// * `msg` - &str
+fn prompt_bool(field: &MetaField, msg: &str) -> Result<String, Error> {
+    let mut prompt = Confirm::new(msg);
+
+    let default = field.default
+        .as_ref()
+        .and_then(|d| MetaType::Bool.normalize(d))
+        .and_then(|d| d.parse::<bool>().ok());
+
+    if let Some(default) = default {
+        prompt = prompt.with_default(default);
+    }
+
+    if let Some(description) = &field.description {
+        prompt = prompt.with_help_message(description);
+    }
+
+    let answer = match field.required {
+        true => prompt.prompt().map(Some),
+        false => prompt.prompt_skippable()
+    };
+
+    to_answer(answer.map(|v| v.map(|v| v.to_string())))
+}
+
+/// Prompt a date with a calendar
+///
+/// # Arguments
+///
+/// * `field` - &MetaField
+/// * `msg` - &str
+fn prompt_date(field: &MetaField, msg: &str) -> Result<String, Error> {
+    let validated = field.clone();
+    let validator = move |input: NaiveDate| to_validation(validated.validate(&input.format(DATE_FORMAT).to_string()));
+
+    let mut prompt = DateSelect::new(msg).with_validator(validator);
+    if let Some(default) = field.default.as_ref().and_then(|d| NaiveDate::parse_from_str(d, DATE_FORMAT).ok()) {
+        prompt = prompt.with_default(default);
+    }
+
+    if let Some(description) = &field.description {
+        prompt = prompt.with_help_message(description);
+    }
+
+    let answer = match field.required {
+        true => prompt.prompt().map(Some),
+        false => prompt.prompt_skippable()
+    };
+
+    to_answer(answer.map(|v| v.map(|v| v.format(DATE_FORMAT).to_string())))
+}
+
+/// Prompt a JSON document with the editor of the user
+///
+/// # Arguments
+///
+/// * `field` - &MetaField
+/// * `msg` - &str
+fn prompt_json(field: &MetaField, msg: &str) -> Result<String, Error> {
+    let validated = field.clone();
+    let validator = move |input: &str| to_validation(validated.validate(input));
+
+    let mut prompt = Editor::new(msg)
+        .with_file_extension(JSON_EXTENSION)
+        .with_validator(validator);
+
+    if let Some(default) = &field.default {
+        prompt = prompt.with_predefined_text(default);
+    }
+
+    if let Some(description) = &field.description {
+        prompt = prompt.with_help_message(description);
+    }
+
+    let answer = match field.required {
+        true => prompt.prompt().map(Some),
+        false => prompt.prompt_skippable()
+    };
+
+    to_answer(answer)
+}
+
+/// Prompt the token of a context which is masked while typed
+///
+/// # Arguments
+///
+/// * `context` - &str
+pub fn prompt_token(context: &str) -> Result<String, Error> {
+    let answer = Password::new(&format!("Token of the context {context}"))
+        .with_display_mode(PasswordDisplayMode::Masked)
+        .without_confirmation()
+        .with_validator(|input: &str| match input.trim().is_empty() {
+            true => Ok(Validation::Invalid(error::EMPTY_TOKEN_ERR.into())),
+            false => Ok(Validation::Valid)
+        })
+        .prompt()
+        .map_err(|_| Error::ScenarioFinished)?;
+
+    Ok(answer.trim().to_string())
+}
+
+/// Edit the items of a variable as a json object with the editor of the user
+///
+/// # Arguments
+///
+/// * `path` - &str
+/// * `items` - &BTreeMap<String, String>
+pub fn edit_items(path: &str, items: &BTreeMap<String, String>) -> Result<BTreeMap<String, String>, Error> {
+    let predefined = serde_json::to_string_pretty(items)
+        .map_err(|err| Error::Serialize(err.to_string()))?;
+    let validator = |input: &str| to_validation(variable::parse_items(input).map(|_| ()));
+
+    let content = Editor::new(&format!("Edit the items of {path}"))
+        .with_file_extension(JSON_EXTENSION)
+        .with_predefined_text(&predefined)
+        .with_validator(validator)
+        .prompt()
+        .map_err(|_| Error::ScenarioFinished)?;
+
+    variable::parse_items(&content)
+}
+
+/// Convert the validation of a meta into a validation of inquire
+///
+/// # Arguments
+///
+/// * `res` - Result<(), Error>
+fn to_validation(res: Result<(), Error>) -> Result<Validation, CustomUserError> {
+    match res {
+        Ok(_) => Ok(Validation::Valid),
+        Err(err) => Ok(Validation::Invalid(err.to_string().into()))
+    }
+}
+
+/// Convert the answer of a skippable prompt. A skipped prompt is returned as an empty value
+///
+/// # Arguments
+///
+/// * `answer` - InquireResult<Option<String>>
+fn to_answer(answer: InquireResult<Option<String>>) -> Result<String, Error> {
+    answer
+        .map(Option::unwrap_or_default)
+        .map_err(|_| Error::ScenarioFinished)
+}
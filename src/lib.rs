@@ -0,0 +1,18 @@
+//! Dispatch parameterized jobs to Nomad and follow the logs of the dispatched jobs.
+//!
+//! The [`Client`] is the entrypoint of the library. It can be created for a Nomad server
+//! or on top of any implementation of [`nomad::api::NomadApi`], such as the in-memory [`nomad::fake::FakeNomad`].
+pub mod batch;
+pub mod client;
+pub mod completion;
+pub mod config;
+pub mod error;
+pub mod login;
+pub mod nomad;
+pub mod rest;
+pub mod schema;
+pub mod usage;
+mod helper;
+
+pub use client::Client;
+pub use error::Error;
@@ -12,32 +12,41 @@ impl Logger {
         println!("✔️ {}", msg.green());
     }
 
-    /// Log a warning message
+    /// Show a notice message
     ///
     /// # Arguments
     ///
-    /// * `msg` - &str
-    pub fn warn<T: ToString>(msg: T) {
-        println!("⚠️  {}", msg.to_string().yellow());
+    /// * `msg` - T
+    pub fn notice<T: ToString>(msg: T) {
+        println!("{}", msg.to_string().trim().blue());
     }
 
-
-    /// Show a notice message
+    /// Show a warning. Warnings are written on stderr so that they don't mix with the output of the commands
     ///
     /// # Arguments
     ///
-    /// * `msg` - T
-    pub fn notice<T: ToString>(msg: T) {
-        println!("{}", msg.to_string().trim().blue());
+    /// * `msg` - &str
+    pub fn warn(msg: &str) {
+        eprintln!("⚠️ {}", msg.yellow());
     }
 
-    /// Show an error message
+    /// Show an error message. Errors are written on stderr, like the warnings
     ///
     /// # Arguments
     ///
     /// * `msg` - &str
     /// * `highlight` - &str
     pub fn error<T: ToString>(msg: &str, highlight: T) {
-        println!("❌ {} {}", msg.red(), highlight.to_string().bold());
+        eprintln!("❌ {} {}", msg.red(), highlight.to_string().bold());
+    }
+
+    /// Show a debug message on the standard error
+    ///
+    /// # Arguments
+    ///
+    /// * `title` - &str
+    /// * `msg` - T
+    pub fn debug<T: ToString>(title: &str, msg: T) {
+        eprintln!("🐛 {}\n{}", title.magenta().bold(), msg.to_string().trim().dark_grey());
     }
 }
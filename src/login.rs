@@ -0,0 +1,233 @@
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::task::JoinSet;
+use tokio::time::{timeout, Duration};
+use crate::error::{Error, self};
+use crate::nomad::acl::{JwtLoginRequest, LoginToken, OidcAuthUrlRequest, OidcCompleteAuthRequest};
+use crate::nomad::api::NomadApi;
+
+// Constant
+/// Port of the callback listener, the redirect uri `http://localhost:4649/oidc/callback` must be allowed by the auth method
+pub const DEFAULT_CALLBACK_PORT: u16 = 4649;
+const CALLBACK_HOST: &str = "127.0.0.1";
+const CALLBACK_PATH: &str = "/oidc/callback";
+const CALLBACK_TIMEOUT_SECONDS: u64 = 300;
+const READ_TIMEOUT_SECONDS: u64 = 10;
+const MAX_REQUEST_SIZE: usize = 16 * 1024;
+const NONCE_SIZE: usize = 32;
+const CODE_PARAM: &str = "code";
+const STATE_PARAM: &str = "state";
+const ERROR_PARAM: &str = "error";
+const SUCCESS_PAGE: &str = "<html><body>Logged in to Nomad, the window can be closed.</body></html>";
+const FAILURE_PAGE: &str = "<html><body>The login to Nomad failed, see the terminal.</body></html>";
+
+/// Local http server receiving the redirection of the OIDC provider once the user is authenticated
+pub struct CallbackListener {
+    listener: TcpListener,
+    redirect_uri: String
+}
+
+/// Query of the redirection of the OIDC provider
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OidcCallback {
+    pub code: String,
+    pub state: String
+}
+
+impl CallbackListener {
+    /// Listen on the loopback interface. The port 0 picks any available port
+    ///
+    /// # Arguments
+    ///
+    /// * `port` - u16
+    pub async fn bind(port: u16) -> Result<CallbackListener, Error> {
+        let listener = TcpListener::bind((CALLBACK_HOST, port)).await
+            .map_err(|err| Error::Login(format!("{CALLBACK_HOST}:{port}: {err}")))?;
+        let port = listener.local_addr()
+            .map_err(|err| Error::Login(err.to_string()))?
+            .port();
+
+        Ok(CallbackListener {
+            listener,
+            redirect_uri: format!("http://localhost:{port}{CALLBACK_PATH}")
+        })
+    }
+
+    /// Return the uri to which the OIDC provider redirects the user
+    ///
+    /// # Arguments
+    ///
+    /// * `&self` - CallbackListener
+    pub fn redirect_uri(&self) -> &str {
+        &self.redirect_uri
+    }
+
+    /// Wait for the redirection of the OIDC provider. Requests on other paths, such as the favicon, are ignored.
+    /// Each connection is handled in its own task, so a connection opened ahead by the browser without
+    /// any request does not hold the redirection
+    ///
+    /// # Arguments
+    ///
+    /// * `self` - CallbackListener
+    pub async fn wait(self) -> Result<OidcCallback, Error> {
+        let accept = async {
+            let mut connections = JoinSet::new();
+            loop {
+                tokio::select! {
+                    accepted = self.listener.accept() => {
+                        let (stream, _) = accepted.map_err(|err| Error::Login(err.to_string()))?;
+                        connections.spawn(handle_connection(stream));
+                    },
+                    Some(handled) = connections.join_next() => {
+                        if let Ok(Some(callback)) = handled {
+                            return callback;
+                        }
+                    }
+                }
+            }
+        };
+
+        timeout(Duration::from_secs(CALLBACK_TIMEOUT_SECONDS), accept).await
+            .map_err(|_| Error::Login(error::LOGIN_TIMEOUT_ERR.to_string()))?
+    }
+}
+
+/// Answer a connection to the callback listener. None is returned when the request is not the redirection,
+/// or when it is not received in time
+///
+/// # Arguments
+///
+/// * `stream` - TcpStream
+async fn handle_connection(mut stream: TcpStream) -> Option<Result<OidcCallback, Error>> {
+    let target = timeout(Duration::from_secs(READ_TIMEOUT_SECONDS), read_target(&mut stream)).await.ok()??;
+
+    let Some(callback) = parse_callback(&target) else {
+        let _ = stream.write_all(response("404 Not Found", "").as_bytes()).await;
+        return None;
+    };
+
+    let page = match callback {
+        Ok(_) => response("200 OK", SUCCESS_PAGE),
+        Err(_) => response("400 Bad Request", FAILURE_PAGE)
+    };
+    let _ = stream.write_all(page.as_bytes()).await;
+
+    Some(callback)
+}
+
+/// Log in with an OIDC auth method. The url of the provider is given to `open`, which is expected to open it
+/// in the browser of the user, then the code of the redirection is exchanged for a token
+///
+/// # Arguments
+///
+/// * `api` - &dyn NomadApi
+/// * `method` - &str
+/// * `listener` - CallbackListener
+/// * `open` - F
+pub async fn oidc_login<F>(api: &dyn NomadApi, method: &str, listener: CallbackListener, open: F) -> Result<LoginToken, Error>
+    where
+        F: FnOnce(&str)
+{
+    let nonce = nonce()?;
+    let redirect_uri = listener.redirect_uri().to_string();
+    let auth_url = api.oidc_auth_url(&OidcAuthUrlRequest {
+        auth_method: method.to_string(),
+        redirect_uri: redirect_uri.clone(),
+        client_nonce: nonce.clone()
+    }).await?;
+
+    open(&auth_url.auth_url);
+    let callback = listener.wait().await?;
+
+    api.oidc_complete_auth(&OidcCompleteAuthRequest {
+        auth_method: method.to_string(),
+        redirect_uri,
+        client_nonce: nonce,
+        state: callback.state,
+        code: callback.code
+    }).await
+}
+
+/// Log in with a JWT auth method by exchanging a JWT for a token
+///
+/// # Arguments
+///
+/// * `api` - &dyn NomadApi
+/// * `method` - &str
+/// * `jwt` - &str
+pub async fn jwt_login(api: &dyn NomadApi, method: &str, jwt: &str) -> Result<LoginToken, Error> {
+    api.jwt_login(&JwtLoginRequest {
+        auth_method: method.to_string(),
+        login_token: jwt.trim().to_string()
+    }).await
+}
+
+/// Generate the nonce binding the url of the provider to its completion from the random source of the OS
+fn nonce() -> Result<String, Error> {
+    let mut bytes = [0; NONCE_SIZE];
+    getrandom::getrandom(&mut bytes).map_err(|err| Error::Login(err.to_string()))?;
+
+    Ok(bytes.iter().map(|b| format!("{b:02x}")).collect())
+}
+
+/// Read the target of an http request, e.g. `/oidc/callback?code=...`
+///
+/// # Arguments
+///
+/// * `stream` - &mut S
+async fn read_target<S: AsyncReadExt + Unpin>(stream: &mut S) -> Option<String> {
+    let mut buf = Vec::new();
+    let mut chunk = [0; 1024];
+
+    while !buf.windows(4).any(|w| w == b"\r\n\r\n") && buf.len() < MAX_REQUEST_SIZE {
+        let read = stream.read(&mut chunk).await.ok()?;
+        if read == 0 {
+            break;
+        }
+
+        buf.extend_from_slice(&chunk[..read]);
+    }
+
+    let request = String::from_utf8_lossy(&buf);
+    request.lines()
+        .next()
+        .and_then(|line| line.split_whitespace().nth(1))
+        .map(str::to_string)
+}
+
+/// Parse the redirection of the OIDC provider. None is returned when the target is not the callback
+///
+/// # Arguments
+///
+/// * `target` - &str
+fn parse_callback(target: &str) -> Option<Result<OidcCallback, Error>> {
+    let url = reqwest::Url::parse(&format!("http://localhost{target}")).ok()?;
+    if url.path() != CALLBACK_PATH {
+        return None;
+    }
+
+    let param = |key: &str| url.query_pairs()
+        .find(|(k, _)| k == key)
+        .map(|(_, v)| v.to_string());
+
+    let callback = match (param(CODE_PARAM), param(STATE_PARAM), param(ERROR_PARAM)) {
+        (_, _, Some(err)) => Err(Error::Login(err)),
+        (Some(code), Some(state), None) => Ok(OidcCallback { code, state }),
+        _ => Err(Error::Login(error::CALLBACK_QUERY_ERR.to_string()))
+    };
+
+    Some(callback)
+}
+
+/// Build an http response closing the connection
+///
+/// # Arguments
+///
+/// * `status` - &str
+/// * `body` - &str
+fn response(status: &str, body: &str) -> String {
+    format!(
+        "HTTP/1.1 {status}\r\nContent-Type: text/html; charset=utf-8\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    )
+}
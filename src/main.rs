@@ -1,21 +1,43 @@
 mod cli;
-mod nomad;
-mod error;
-mod rest;
 mod inquiry;
-mod helper;
 mod log;
+mod output;
+mod ui;
 
+use std::process::ExitCode;
 use log::Logger;
 use crate::cli::Cli;
 
+// Constant
+const STOPPED_MSG: &str = "Noumead stopped due to";
+
+fn main() -> ExitCode {
+    // the completion exits before the runtime is started as the candidates are fetched with their own runtime
+    cli::complete();
+
+    run()
+}
+
 #[tokio::main]
-async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    if let Err(err) = Cli::new()
-        .init_rest_handler()?
-        .run().await {
-            Logger::error("Noumead stopped due to", err)
-    }
+async fn run() -> ExitCode {
+    let mut cli = match Cli::new() {
+        Ok(cli) => cli,
+        Err(err) => {
+            Logger::error(STOPPED_MSG, err);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let res = match cli.init_client() {
+        Ok(cli) => cli.run().await,
+        Err(err) => Err(err)
+    };
 
-    Ok(())
+    match res {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            cli.formatter().error(STOPPED_MSG, err);
+            ExitCode::FAILURE
+        }
+    }
 }
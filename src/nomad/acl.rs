@@ -0,0 +1,367 @@
+use chrono::{DateTime, Utc};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use crate::error::Error;
+use crate::helper::null_as_default;
+use super::api::NomadApi;
+use super::meta::REDACTED;
+
+// Constant
+pub const MANAGEMENT_TYPE: &str = "management";
+pub const DISPATCH_CAPABILITY: &str = "dispatch-job";
+const WRITE_POLICY: &str = "write";
+const DENY_POLICY: &str = "deny";
+const DENY_CAPABILITY: &str = "deny";
+const NAMESPACE_BLOCK: &str = "namespace";
+const POLICY_ATTR: &str = "policy";
+const CAPABILITIES_ATTR: &str = "capabilities";
+const WILDCARD: char = '*';
+
+/// ACL token used to query Nomad. The secret of the token is never deserialized
+#[derive(Debug, Deserialize, Serialize, Default, Clone)]
+pub struct AclToken {
+    #[serde(rename = "AccessorID", default)]
+    pub accessor_id: String,
+
+    #[serde(rename = "Name", default)]
+    pub name: String,
+
+    #[serde(rename = "Type", default)]
+    pub kind: String,
+
+    #[serde(rename = "Policies", default, deserialize_with = "null_as_default")]
+    pub policies: Vec<String>,
+
+    #[serde(rename = "Roles", default, deserialize_with = "null_as_default")]
+    pub roles: Vec<AclRoleLink>,
+
+    #[serde(rename = "Global", default)]
+    pub global: bool,
+
+    #[serde(rename = "ExpirationTime", default, skip_serializing_if = "Option::is_none")]
+    pub expiration_time: Option<DateTime<Utc>>
+}
+
+/// Role attached to a token or policy attached to a role
+#[derive(Debug, Deserialize, Serialize, Default, Clone)]
+pub struct AclRoleLink {
+    #[serde(rename = "ID", default)]
+    pub id: String,
+
+    #[serde(rename = "Name", default)]
+    pub name: String
+}
+
+/// ACL role grouping a set of policies
+#[derive(Debug, Deserialize, Serialize, Default, Clone)]
+pub struct AclRole {
+    #[serde(rename = "ID")]
+    pub id: String,
+
+    #[serde(rename = "Name", default)]
+    pub name: String,
+
+    #[serde(rename = "Policies", default, deserialize_with = "null_as_default")]
+    pub policies: Vec<AclRoleLink>
+}
+
+/// ACL policy along with its rules written in HCL or in json
+#[derive(Debug, Deserialize, Serialize, Default, Clone)]
+pub struct AclPolicy {
+    #[serde(rename = "Name")]
+    pub name: String,
+
+    #[serde(rename = "Rules", default)]
+    pub rules: String
+}
+
+/// Rules of a policy applying to a namespace
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct NamespaceRule {
+    pub namespace: String,
+    pub policy: Option<String>,
+    pub capabilities: Vec<String>
+}
+
+/// Outcome of the rules of the policies for a capability
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Grant {
+    Allowed,
+    Denied,
+    NotGranted
+}
+
+/// Request of the url of the OIDC provider used to log in with an auth method
+#[derive(Debug, Deserialize, Serialize, Default, Clone)]
+pub struct OidcAuthUrlRequest {
+    #[serde(rename = "AuthMethodName")]
+    pub auth_method: String,
+
+    #[serde(rename = "RedirectUri")]
+    pub redirect_uri: String,
+
+    #[serde(rename = "ClientNonce")]
+    pub client_nonce: String
+}
+
+#[derive(Debug, Deserialize, Serialize, Default, Clone)]
+pub struct OidcAuthUrl {
+    #[serde(rename = "AuthURL")]
+    pub auth_url: String
+}
+
+/// Request exchanging the code returned by the OIDC provider for a Nomad token
+#[derive(Debug, Deserialize, Serialize, Default, Clone)]
+pub struct OidcCompleteAuthRequest {
+    #[serde(rename = "AuthMethodName")]
+    pub auth_method: String,
+
+    #[serde(rename = "RedirectUri")]
+    pub redirect_uri: String,
+
+    #[serde(rename = "ClientNonce")]
+    pub client_nonce: String,
+
+    #[serde(rename = "State")]
+    pub state: String,
+
+    #[serde(rename = "Code")]
+    pub code: String
+}
+
+/// Request exchanging a JWT for a Nomad token
+#[derive(Debug, Deserialize, Serialize, Default, Clone)]
+pub struct JwtLoginRequest {
+    #[serde(rename = "AuthMethodName")]
+    pub auth_method: String,
+
+    #[serde(rename = "LoginToken")]
+    pub login_token: String
+}
+
+/// Token issued by an auth method. Unlike [`AclToken`], the secret is deserialized so that it can be used
+#[derive(Deserialize, Serialize, Default, Clone)]
+pub struct LoginToken {
+    #[serde(rename = "AccessorID", default)]
+    pub accessor_id: String,
+
+    #[serde(rename = "SecretID")]
+    pub secret_id: String,
+
+    #[serde(rename = "ExpirationTime", default, skip_serializing_if = "Option::is_none")]
+    pub expiration_time: Option<DateTime<Utc>>
+}
+
+impl std::fmt::Debug for LoginToken {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("LoginToken")
+            .field("accessor_id", &self.accessor_id)
+            .field("secret_id", &REDACTED)
+            .field("expiration_time", &self.expiration_time)
+            .finish()
+    }
+}
+
+impl LoginToken {
+    /// Return whether the token has expired. A token without expiration never expires
+    ///
+    /// # Arguments
+    ///
+    /// * `&self` - LoginToken
+    /// * `now` - DateTime<Utc>
+    pub fn is_expired(&self, now: DateTime<Utc>) -> bool {
+        self.expiration_time.is_some_and(|expiration| expiration <= now)
+    }
+}
+
+impl AclToken {
+    /// Return whether the token is a management token, which is allowed to do anything
+    ///
+    /// # Arguments
+    ///
+    /// * `&self` - AclToken
+    pub fn is_management(&self) -> bool {
+        self.kind == MANAGEMENT_TYPE
+    }
+}
+
+impl NamespaceRule {
+    /// Return whether the rule grant or deny a capability
+    ///
+    /// # Arguments
+    ///
+    /// * `&self` - NamespaceRule
+    /// * `capability` - &str
+    fn grant(&self, capability: &str) -> Grant {
+        let denied = self.policy.as_deref() == Some(DENY_POLICY)
+            || self.capabilities.iter().any(|c| c == DENY_CAPABILITY);
+        // the write policy include every capability of the jobs
+        let allowed = self.policy.as_deref() == Some(WRITE_POLICY)
+            || self.capabilities.iter().any(|c| c == capability);
+
+        match (denied, allowed) {
+            (true, _) => Grant::Denied,
+            (false, true) => Grant::Allowed,
+            (false, false) => Grant::NotGranted
+        }
+    }
+}
+
+/// Parse the namespace rules of a policy. Rules can be written in HCL or in json. Only the attributes of the
+/// namespace blocks are read, nested blocks such as variables are ignored
+///
+/// # Arguments
+///
+/// * `rules` - &str
+pub fn namespace_rules(rules: &str) -> Vec<NamespaceRule> {
+    match serde_json::from_str::<Value>(rules) {
+        Ok(value) => json_namespace_rules(&value),
+        Err(_) => hcl_namespace_rules(rules)
+    }
+}
+
+/// Parse the namespace rules of a policy written in json
+///
+/// # Arguments
+///
+/// * `value` - &Value
+fn json_namespace_rules(value: &Value) -> Vec<NamespaceRule> {
+    let Some(namespaces) = value.get(NAMESPACE_BLOCK).and_then(Value::as_object) else {
+        return Vec::new();
+    };
+
+    namespaces.iter()
+        .map(|(namespace, rule)| NamespaceRule {
+            namespace: namespace.clone(),
+            policy: rule.get(POLICY_ATTR).and_then(Value::as_str).map(str::to_string),
+            capabilities: rule.get(CAPABILITIES_ATTR)
+                .and_then(Value::as_array)
+                .map(|caps| caps.iter().filter_map(Value::as_str).map(str::to_string).collect())
+                .unwrap_or_default()
+        })
+        .collect()
+}
+
+/// Parse the namespace rules of a policy written in HCL
+///
+/// # Arguments
+///
+/// * `rules` - &str
+fn hcl_namespace_rules(rules: &str) -> Vec<NamespaceRule> {
+    let (Ok(block), Ok(policy), Ok(capabilities), Ok(item)) = (
+        Regex::new(r#"namespace\s+"([^"]*)"\s*\{"#),
+        Regex::new(r#"policy\s*=\s*"([^"]*)""#),
+        Regex::new(r"capabilities\s*=\s*\[([^\]]*)\]"),
+        Regex::new(r#""([^"]*)""#)
+    ) else {
+        return Vec::new();
+    };
+
+    block.captures_iter(rules)
+        .filter_map(|caps| {
+            let start = caps.get(0)?.end();
+            let body = top_level_body(&rules[start..]);
+
+            Some(NamespaceRule {
+                namespace: caps[1].to_string(),
+                policy: policy.captures(&body).map(|p| p[1].to_string()),
+                capabilities: capabilities.captures(&body)
+                    .map(|c| item.captures_iter(&c[1]).map(|i| i[1].to_string()).collect())
+                    .unwrap_or_default()
+            })
+        })
+        .collect()
+}
+
+/// Return the content of a block without its nested blocks. The content starts after the opening brace
+///
+/// # Arguments
+///
+/// * `content` - &str
+fn top_level_body(content: &str) -> String {
+    let mut depth = 0;
+    let mut body = String::new();
+    for c in content.chars() {
+        match c {
+            '{' => depth += 1,
+            '}' if depth == 0 => break,
+            '}' => depth -= 1,
+            c if depth == 0 => body.push(c),
+            _ => {}
+        }
+    }
+
+    body
+}
+
+/// Return whether a namespace match the namespace of a rule, which may contain wildcards
+///
+/// # Arguments
+///
+/// * `pattern` - &str
+/// * `namespace` - &str
+fn matches_namespace(pattern: &str, namespace: &str) -> bool {
+    if !pattern.contains(WILDCARD) {
+        return pattern == namespace;
+    }
+
+    let regex = pattern.split(WILDCARD)
+        .map(regex::escape)
+        .collect::<Vec<_>>()
+        .join(".*");
+
+    Regex::new(&format!("^{regex}$")).is_ok_and(|r| r.is_match(namespace))
+}
+
+/// Check whether the rules grant a capability on a namespace. Like Nomad, a rule matching the namespace exactly
+/// take precedence over the rules with wildcards, and a deny take precedence over a grant
+///
+/// # Arguments
+///
+/// * `rules` - &[NamespaceRule]
+/// * `namespace` - &str
+/// * `capability` - &str
+pub fn grant(rules: &[NamespaceRule], namespace: &str, capability: &str) -> Grant {
+    let exact: Vec<&NamespaceRule> = rules.iter().filter(|r| r.namespace == namespace).collect();
+    let matching: Vec<&NamespaceRule> = match exact.is_empty() {
+        true => rules.iter().filter(|r| matches_namespace(&r.namespace, namespace)).collect(),
+        false => exact
+    };
+
+    let grants: Vec<Grant> = matching.iter().map(|r| r.grant(capability)).collect();
+    match (grants.contains(&Grant::Denied), grants.contains(&Grant::Allowed)) {
+        (true, _) => Grant::Denied,
+        (false, true) => Grant::Allowed,
+        (false, false) => Grant::NotGranted
+    }
+}
+
+/// Check whether the token used to query Nomad is granted a capability on a namespace. The rules of the
+/// policies attached to the token and to its roles are read
+///
+/// # Arguments
+///
+/// * `api` - &dyn NomadApi
+/// * `namespace` - &str
+/// * `capability` - &str
+pub async fn token_grant(api: &dyn NomadApi, namespace: &str, capability: &str) -> Result<Grant, Error> {
+    let token = api.token_self().await?;
+    if token.is_management() {
+        return Ok(Grant::Allowed);
+    }
+
+    let mut policies = token.policies.clone();
+    for role in &token.roles {
+        policies.extend(api.acl_role(&role.id).await?.policies.into_iter().map(|p| p.name));
+    }
+    policies.sort();
+    policies.dedup();
+
+    let mut rules = Vec::new();
+    for name in &policies {
+        rules.extend(namespace_rules(&api.acl_policy(name).await?.rules));
+    }
+
+    Ok(grant(&rules, namespace, capability))
+}
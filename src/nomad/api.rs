@@ -0,0 +1,208 @@
+use async_trait::async_trait;
+use tokio::time::Duration;
+use crate::error::Error;
+use crate::rest::RestHandler;
+use super::acl::{AclPolicy, AclRole, AclToken, JwtLoginRequest, LoginToken, OidcAuthUrl, OidcAuthUrlRequest, OidcCompleteAuthRequest};
+use super::alloc::Allocation;
+use super::dispatch::{DispatchPayload, DispatchRes};
+use super::job::{Job, JobStatus};
+use super::namespace::Namespace;
+use super::spec::Spec;
+use super::stream::{StdKind, StreamLog};
+use super::summary::{DispatchedJob, JobSummary};
+use super::variable::{Variable, VariableMetadata};
+
+// Constant
+const JOBS_ENDPOINT: &str = "v1/jobs";
+const JOB_ENDPOINT: &str = "v1/job";
+const ALLOCATION_ENDPOINT: &str = "v1/allocation";
+const LOGS_ENDPOINT: &str = "v1/client/fs/logs";
+const VARS_ENDPOINT: &str = "v1/vars";
+const VAR_ENDPOINT: &str = "v1/var";
+const NAMESPACES_ENDPOINT: &str = "v1/namespaces";
+const ACL_TOKEN_SELF_ENDPOINT: &str = "v1/acl/token/self";
+const ACL_POLICY_ENDPOINT: &str = "v1/acl/policy";
+const ACL_ROLE_ENDPOINT: &str = "v1/acl/role";
+const OIDC_AUTH_URL_ENDPOINT: &str = "v1/acl/oidc/auth-url";
+const OIDC_COMPLETE_AUTH_ENDPOINT: &str = "v1/acl/oidc/complete-auth";
+const ACL_LOGIN_ENDPOINT: &str = "v1/acl/login";
+
+/// Return the endpoint used to dispatch a parameterized job
+///
+/// # Arguments
+///
+/// * `namespace` - &str
+/// * `job_id` - &str
+pub fn dispatch_endpoint(namespace: &str, job_id: &str) -> String {
+    format!("{JOB_ENDPOINT}/{job_id}/dispatch?namespace={namespace}")
+}
+
+/// Operations of the Nomad API used by noumead. Blocking operations take the index returned
+/// by a previous call and return the data once it change past this index, or once the wait duration expire
+#[async_trait]
+pub trait NomadApi: Send + Sync {
+    /// List the jobs of every namespaces
+    async fn jobs(&self) -> Result<Vec<Job>, Error>;
+
+    /// Get the spec of a job
+    async fn spec(&self, namespace: &str, job_id: &str) -> Result<Spec, Error>;
+
+    /// Dispatch a parameterized job
+    async fn dispatch(&self, namespace: &str, job_id: &str, payload: &DispatchPayload) -> Result<DispatchRes, Error>;
+
+    /// Get the status of a job
+    async fn job_status(&self, namespace: &str, job_id: &str, index: u64, wait: Duration) -> Result<(JobStatus, u64), Error>;
+
+    /// List the allocations of a job
+    async fn allocations(&self, namespace: &str, job_id: &str, index: u64, wait: Duration) -> Result<(Vec<Allocation>, u64), Error>;
+
+    /// Get a single allocation
+    async fn allocation(&self, alloc_id: &str, index: u64, wait: Duration) -> Result<(Allocation, u64), Error>;
+
+    /// Read the logs of a task from the provided offset
+    async fn logs(&self, alloc_id: &str, task_name: &str, std_kind: &StdKind, offset: i64) -> Result<StreamLog, Error>;
+
+    /// List the jobs of every namespaces whose id start with the prefix, along with their metas and summary
+    async fn dispatched_jobs(&self, prefix: &str, index: u64, wait: Duration) -> Result<(Vec<DispatchedJob>, u64), Error>;
+
+    /// Get the summary of a job
+    async fn job_summary(&self, namespace: &str, job_id: &str) -> Result<JobSummary, Error>;
+
+    /// Stop and deregister a job. A purged job is removed from Nomad straight away
+    async fn deregister(&self, namespace: &str, job_id: &str, purge: bool) -> Result<(), Error>;
+
+    /// List the metadata of the variables of a namespace whose path start with the prefix
+    async fn variables(&self, namespace: &str, prefix: &str) -> Result<Vec<VariableMetadata>, Error>;
+
+    /// Get a variable along with its items
+    async fn variable(&self, namespace: &str, path: &str) -> Result<Variable, Error>;
+
+    /// Create or replace a variable. With a check-and-set index, the variable is only written when its
+    /// modify index still match, 0 meaning that the variable must not exist
+    async fn put_variable(&self, variable: &Variable, cas: Option<u64>) -> Result<Variable, Error>;
+
+    /// Delete a variable. The check-and-set index behaves as for the put
+    async fn delete_variable(&self, namespace: &str, path: &str, cas: Option<u64>) -> Result<(), Error>;
+
+    /// List the namespaces of the cluster
+    async fn namespaces(&self) -> Result<Vec<Namespace>, Error>;
+
+    /// Get the ACL token used to query Nomad
+    async fn token_self(&self) -> Result<AclToken, Error>;
+
+    /// Get an ACL policy along with its rules
+    async fn acl_policy(&self, name: &str) -> Result<AclPolicy, Error>;
+
+    /// Get an ACL role along with its policies
+    async fn acl_role(&self, id: &str) -> Result<AclRole, Error>;
+
+    /// Get the url of the OIDC provider of an auth method
+    async fn oidc_auth_url(&self, req: &OidcAuthUrlRequest) -> Result<OidcAuthUrl, Error>;
+
+    /// Exchange the code returned by the OIDC provider for a token
+    async fn oidc_complete_auth(&self, req: &OidcCompleteAuthRequest) -> Result<LoginToken, Error>;
+
+    /// Exchange a JWT for a token
+    async fn jwt_login(&self, req: &JwtLoginRequest) -> Result<LoginToken, Error>;
+}
+
+/// Build the query of a variable endpoint
+///
+/// # Arguments
+///
+/// * `namespace` - &str
+/// * `cas` - Option<u64>
+fn variable_query(namespace: &str, cas: Option<u64>) -> String {
+    match cas {
+        Some(cas) => format!("namespace={namespace}&cas={cas}"),
+        None => format!("namespace={namespace}")
+    }
+}
+
+#[async_trait]
+impl NomadApi for RestHandler {
+    async fn jobs(&self) -> Result<Vec<Job>, Error> {
+        self.get(format!("{JOBS_ENDPOINT}?meta=true&namespace=*")).await
+    }
+
+    async fn spec(&self, namespace: &str, job_id: &str) -> Result<Spec, Error> {
+        self.get(format!("{JOB_ENDPOINT}/{job_id}?namespace={namespace}")).await
+    }
+
+    async fn dispatch(&self, namespace: &str, job_id: &str, payload: &DispatchPayload) -> Result<DispatchRes, Error> {
+        self.post(dispatch_endpoint(namespace, job_id), payload).await
+    }
+
+    async fn job_status(&self, namespace: &str, job_id: &str, index: u64, wait: Duration) -> Result<(JobStatus, u64), Error> {
+        self.get_blocking(format!("{JOB_ENDPOINT}/{job_id}?namespace={namespace}"), index, wait).await
+    }
+
+    async fn allocations(&self, namespace: &str, job_id: &str, index: u64, wait: Duration) -> Result<(Vec<Allocation>, u64), Error> {
+        self.get_blocking(format!("{JOB_ENDPOINT}/{job_id}/allocations?namespace={namespace}"), index, wait).await
+    }
+
+    async fn allocation(&self, alloc_id: &str, index: u64, wait: Duration) -> Result<(Allocation, u64), Error> {
+        self.get_blocking(format!("{ALLOCATION_ENDPOINT}/{alloc_id}"), index, wait).await
+    }
+
+    async fn logs(&self, alloc_id: &str, task_name: &str, std_kind: &StdKind, offset: i64) -> Result<StreamLog, Error> {
+        self.get_logs(format!("{LOGS_ENDPOINT}/{alloc_id}?task={task_name}&type={std_kind}&offset={offset}")).await
+    }
+
+    async fn dispatched_jobs(&self, prefix: &str, index: u64, wait: Duration) -> Result<(Vec<DispatchedJob>, u64), Error> {
+        self.get_blocking(format!("{JOBS_ENDPOINT}?meta=true&namespace=*&prefix={prefix}"), index, wait).await
+    }
+
+    async fn job_summary(&self, namespace: &str, job_id: &str) -> Result<JobSummary, Error> {
+        self.get(format!("{JOB_ENDPOINT}/{job_id}/summary?namespace={namespace}")).await
+    }
+
+    async fn deregister(&self, namespace: &str, job_id: &str, purge: bool) -> Result<(), Error> {
+        self.delete(format!("{JOB_ENDPOINT}/{job_id}?namespace={namespace}&purge={purge}")).await
+    }
+
+    async fn variables(&self, namespace: &str, prefix: &str) -> Result<Vec<VariableMetadata>, Error> {
+        self.get(format!("{VARS_ENDPOINT}?namespace={namespace}&prefix={prefix}")).await
+    }
+
+    async fn variable(&self, namespace: &str, path: &str) -> Result<Variable, Error> {
+        self.get(format!("{VAR_ENDPOINT}/{path}?namespace={namespace}")).await
+    }
+
+    async fn put_variable(&self, variable: &Variable, cas: Option<u64>) -> Result<Variable, Error> {
+        let query = variable_query(&variable.namespace, cas);
+        self.put(format!("{VAR_ENDPOINT}/{}?{query}", variable.path), variable).await
+    }
+
+    async fn delete_variable(&self, namespace: &str, path: &str, cas: Option<u64>) -> Result<(), Error> {
+        self.delete(format!("{VAR_ENDPOINT}/{path}?{}", variable_query(namespace, cas))).await
+    }
+
+    async fn namespaces(&self) -> Result<Vec<Namespace>, Error> {
+        self.get(NAMESPACES_ENDPOINT).await
+    }
+
+    async fn token_self(&self) -> Result<AclToken, Error> {
+        self.get(ACL_TOKEN_SELF_ENDPOINT).await
+    }
+
+    async fn acl_policy(&self, name: &str) -> Result<AclPolicy, Error> {
+        self.get(format!("{ACL_POLICY_ENDPOINT}/{name}")).await
+    }
+
+    async fn acl_role(&self, id: &str) -> Result<AclRole, Error> {
+        self.get(format!("{ACL_ROLE_ENDPOINT}/{id}")).await
+    }
+
+    async fn oidc_auth_url(&self, req: &OidcAuthUrlRequest) -> Result<OidcAuthUrl, Error> {
+        self.post(OIDC_AUTH_URL_ENDPOINT, req).await
+    }
+
+    async fn oidc_complete_auth(&self, req: &OidcCompleteAuthRequest) -> Result<LoginToken, Error> {
+        self.post(OIDC_COMPLETE_AUTH_ENDPOINT, req).await
+    }
+
+    async fn jwt_login(&self, req: &JwtLoginRequest) -> Result<LoginToken, Error> {
+        self.post(ACL_LOGIN_ENDPOINT, req).await
+    }
+}
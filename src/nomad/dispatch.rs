@@ -1,19 +1,146 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use crate::error::Error;
+use crate::helper::{to_json, Base64};
+use super::meta;
 
-#[derive(Serialize)]
-pub(crate) struct DispatchPayload {
-    #[serde(rename(serialize = "Payload"))]
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct DispatchPayload {
+    #[serde(rename = "Payload")]
     pub payload: String,
-    #[serde(rename(serialize = "Meta"))]
-    pub metas: HashMap<String, String>
+    #[serde(rename = "Meta")]
+    pub metas: HashMap<String, String>,
+    #[serde(rename = "IdempotencyToken", default, skip_serializing_if = "Option::is_none")]
+    pub idempotency_token: Option<String>
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Serialize)]
 pub struct DispatchRes {
-    #[serde(rename(deserialize = "EvalID"))]
+    #[serde(rename = "EvalID", default)]
     pub eval_id: String,
 
-    #[serde(rename(deserialize = "DispatchedJobID"))]
+    #[serde(rename = "DispatchedJobID")]
     pub dispatch_id: String
 }
+
+impl DispatchPayload {
+    /// Create the payload sent to the dispatch endpoint. The metas are sent as a json payload
+    /// when no payload is provided
+    ///
+    /// # Arguments
+    ///
+    /// * `metas` - HashMap<String, String>
+    /// * `payload` - Option<String>
+    pub fn new(metas: HashMap<String, String>, payload: Option<String>) -> Result<Self, Error> {
+        DispatchPayload::from_bytes(metas, payload.map(String::into_bytes))
+    }
+
+    /// Create the payload sent to the dispatch endpoint from a payload which may not be text, e.g. a file
+    ///
+    /// # Arguments
+    ///
+    /// * `metas` - HashMap<String, String>
+    /// * `payload` - Option<Vec<u8>>
+    pub fn from_bytes(metas: HashMap<String, String>, payload: Option<Vec<u8>>) -> Result<Self, Error> {
+        let payload = match payload {
+            Some(payload) => payload,
+            None => to_json(&metas)?.into_bytes()
+        };
+
+        Ok(DispatchPayload {
+            payload: base64::encode(payload),
+            metas,
+            idempotency_token: None
+        })
+    }
+
+    /// Return the payload decoded from its base64 representation
+    ///
+    /// # Arguments
+    ///
+    /// * `&self` - DispatchPayload
+    pub fn decoded_payload(&self) -> Option<String> {
+        String::from_base64(self.payload.clone()).ok()
+    }
+
+    /// Return a copy of the payload where the value of the secret metas are replaced by a placeholder.
+    /// The payload is built again from the redacted metas with `render`, the same way as the original payload,
+    /// since a secret escaped or encoded in the payload can't be searched reliably
+    ///
+    /// # Arguments
+    ///
+    /// * `&self` - DispatchPayload
+    /// * `secrets` - &HashSet<String>
+    /// * `render` - F
+    pub fn redacted<F>(&self, secrets: &HashSet<String>, render: F) -> Result<DispatchPayload, Error>
+        where
+            F: FnOnce(&HashMap<String, String>) -> Option<String>
+    {
+        let metas = meta::redact_metas(&self.metas, secrets);
+        let payload = render(&metas);
+
+        Ok(DispatchPayload::new(metas, payload)?.with_idempotency_token(self.idempotency_token.clone()))
+    }
+
+    /// Set the token used by Nomad to avoid dispatching the same job twice
+    ///
+    /// # Arguments
+    ///
+    /// * `self` - Self
+    /// * `token` - Option<String>
+    pub fn with_idempotency_token(self, token: Option<String>) -> Self {
+        DispatchPayload { idempotency_token: token, ..self }
+    }
+}
+
+/// Derive an idempotency token from the job, the metas and the payload. The same inputs
+/// always give the same token
+///
+/// # Arguments
+///
+/// * `job_id` - &str
+/// * `metas` - &HashMap<String, String>
+/// * `payload` - Option<&[u8]>
+pub fn idempotency_token(job_id: &str, metas: &HashMap<String, String>, payload: Option<&[u8]>) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(job_id.as_bytes());
+    hash_metas(&mut hasher, metas);
+
+    if let Some(payload) = payload {
+        hasher.update([1]);
+        hasher.update(payload);
+    }
+
+    format!("{:x}", hasher.finalize())
+}
+
+/// Hash the metas, e.g. to detect a change of their value without storing it
+///
+/// # Arguments
+///
+/// * `metas` - &HashMap<String, String>
+pub fn metas_hash(metas: &HashMap<String, String>) -> String {
+    let mut hasher = Sha256::new();
+    hash_metas(&mut hasher, metas);
+
+    format!("{:x}", hasher.finalize())
+}
+
+/// Feed the metas to a hasher
+///
+/// # Arguments
+///
+/// * `hasher` - &mut Sha256
+/// * `metas` - &HashMap<String, String>
+fn hash_metas(hasher: &mut Sha256, metas: &HashMap<String, String>) {
+    // the metas are sorted as the order of a hashmap is not stable
+    let metas: BTreeMap<&String, &String> = metas.iter().collect();
+
+    for (key, value) in metas {
+        hasher.update([0]);
+        hasher.update(key.as_bytes());
+        hasher.update([0]);
+        hasher.update(value.as_bytes());
+    }
+}
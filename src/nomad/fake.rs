@@ -0,0 +1,842 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use async_trait::async_trait;
+use tokio::time::Duration;
+use crate::error::Error;
+use crate::helper::Base64;
+use super::acl::{
+    AclPolicy, AclRole, AclRoleLink, AclToken, JwtLoginRequest, LoginToken, OidcAuthUrl, OidcAuthUrlRequest,
+    OidcCompleteAuthRequest
+};
+use super::alloc::{Allocation, TaskState};
+use super::api::NomadApi;
+use super::dispatch::{DispatchPayload, DispatchRes};
+use super::job::{Job, JobStatus};
+use super::namespace::{Namespace, DEFAULT_NAMESPACE};
+use super::spec::{DispatchPayloadConfig, Parameterized, Spec, Task, TaskGroup};
+use super::stream::{StdKind, StreamLog};
+use super::summary::{ChildrenSummary, DispatchedJob, JobSummary, TaskGroupSummary};
+use super::variable::{Variable, VariableMetadata};
+
+// Constant
+const RUNNING_STATUS: &str = "running";
+const DEAD_STATUS: &str = "dead";
+const FINISHED_AT: &str = "2023-01-01T00:00:00Z";
+const BASE_TIME: i64 = 1_672_531_200;
+const TASK_GROUP: &str = "group";
+const BATCH_TYPE: &str = "batch";
+const DATACENTER: &str = "dc1";
+const DRIVER: &str = "docker";
+const PAYLOAD_MODE: &str = "optional";
+const PAYLOAD_FILE: &str = "local/payload.json";
+const OIDC_PROVIDER_URL: &str = "https://idp.example.com/authorize";
+const LOGIN_TOKEN_TTL_SECONDS: i64 = 3600;
+
+/// In-memory implementation of the Nomad API. It simulates parameterized jobs, their dispatches
+/// and the allocations of the dispatched jobs. A task finishes once its logs has been read entirely
+#[derive(Debug, Default)]
+pub struct FakeNomad {
+    state: Mutex<State>
+}
+
+#[derive(Debug, Default)]
+struct State {
+    index: u64,
+    jobs: Vec<FakeJob>,
+    allocations: Vec<FakeAllocation>,
+    dispatches: Vec<(String, DispatchPayload)>,
+    /// Ids of the jobs whose allocations have been listed
+    listed_allocations: Vec<String>,
+    variables: Vec<Variable>,
+    token: Option<AclToken>,
+    policies: Vec<AclPolicy>,
+    roles: Vec<AclRole>,
+    /// Auth methods along with the code or the JWT they accept
+    auth_methods: Vec<(String, String)>,
+    /// Pending OIDC logins, the state sent to the provider along with the nonce of the client
+    oidc_states: Vec<(String, String)>
+}
+
+#[derive(Debug, Clone)]
+struct FakeJob {
+    job: Job,
+    spec: Option<Parameterized>,
+    tasks: Vec<FakeTask>,
+    parent_id: String,
+    metas: HashMap<String, String>,
+    submit_time: i64,
+    idempotency_token: Option<String>,
+    /// The dispatch of a periodic job creates no evaluation
+    periodic: bool
+}
+
+#[derive(Debug, Clone)]
+struct FakeTask {
+    name: String,
+    stdout: String,
+    stderr: String,
+    stdout_read: bool,
+    stderr_read: bool
+}
+
+#[derive(Debug)]
+struct FakeAllocation {
+    id: String,
+    job_id: String,
+    tasks: Vec<FakeTask>,
+    modify_time: i64
+}
+
+impl FakeTask {
+    fn finished(&self) -> bool {
+        self.stdout_read && self.stderr_read
+    }
+}
+
+impl FakeAllocation {
+    fn to_allocation(&self) -> Allocation {
+        let task_states = self.tasks.iter()
+            .map(|t| (t.name.clone(), TaskState {
+                finished_at: t.finished().then(|| FINISHED_AT.to_string())
+            }))
+            .collect();
+
+        Allocation {
+            task_states,
+            alloc_id: self.id.clone(),
+            modify_time: self.modify_time
+        }
+    }
+}
+
+impl FakeJob {
+    fn new(job: Job) -> Self {
+        FakeJob {
+            job,
+            spec: None,
+            tasks: Vec::new(),
+            parent_id: String::new(),
+            metas: HashMap::new(),
+            submit_time: to_time(0),
+            idempotency_token: None,
+            periodic: false
+        }
+    }
+}
+
+impl State {
+    fn variable(&self, namespace: &str, path: &str) -> Option<&Variable> {
+        self.variables.iter().find(|v| v.namespace == namespace && v.path == path)
+    }
+
+    /// Like nomad, a check-and-set conflict returns 409 along with the current variable
+    fn check_cas(&self, namespace: &str, path: &str, cas: Option<u64>, method: &str) -> Result<(), Error> {
+        let current = self.variable(namespace, path);
+        let index = current.map(|v| v.modify_index).unwrap_or_default();
+
+        match cas {
+            Some(cas) if cas != index => Err(api_error(
+                409,
+                method,
+                format!("/v1/var/{path}"),
+                serde_json::to_string(&current.cloned().unwrap_or_default()).unwrap_or_default()
+            )),
+            _ => Ok(())
+        }
+    }
+
+    /// Like nomad, a job is looked up in the given namespace, the empty namespace being the default one
+    fn job(&self, namespace: &str, job_id: &str, method: &str) -> Result<&FakeJob, Error> {
+        let namespace = match namespace.is_empty() {
+            true => DEFAULT_NAMESPACE,
+            false => namespace
+        };
+
+        self.jobs.iter()
+            .find(|j| j.job.namespace == namespace && j.job.id == job_id)
+            .ok_or_else(|| api_error(404, method, format!("/v1/job/{job_id}?namespace={namespace}"), "job not found"))
+    }
+
+    fn job_status(&self, job: &FakeJob) -> String {
+        let mut allocs = self.allocations.iter()
+            .filter(|a| a.job_id == job.job.id)
+            .peekable();
+
+        if allocs.peek().is_none() || job.job.status == DEAD_STATUS {
+            return job.job.status.clone();
+        }
+
+        match allocs.all(|a| a.tasks.iter().all(FakeTask::finished)) {
+            true => DEAD_STATUS.to_string(),
+            false => RUNNING_STATUS.to_string()
+        }
+    }
+}
+
+impl FakeNomad {
+    /// Create an empty fake Nomad
+    pub fn new() -> Self {
+        FakeNomad::default()
+    }
+
+    /// Register a parameterized job. Each task is described by its name and the logs it output on stdout
+    ///
+    /// # Arguments
+    ///
+    /// * `self` - Self
+    /// * `id` - &str
+    /// * `required` - &[&str]
+    /// * `optional` - &[&str]
+    /// * `tasks` - &[(&str, &str)]
+    pub fn with_parameterized_job(self, id: &str, required: &[&str], optional: &[&str], tasks: &[(&str, &str)]) -> Self {
+        let to_vec = |items: &[&str]| match items.is_empty() {
+            true => None,
+            false => Some(items.iter().map(|s| s.to_string()).collect())
+        };
+
+        let job = FakeJob {
+            spec: Some(Parameterized {
+                payload: PAYLOAD_MODE.to_string(),
+                meta_required: to_vec(required),
+                meta_optional: to_vec(optional)
+            }),
+            tasks: tasks.iter()
+                .map(|(name, stdout)| FakeTask {
+                    name: name.to_string(),
+                    stdout: stdout.to_string(),
+                    stderr: String::new(),
+                    stdout_read: false,
+                    stderr_read: false
+                })
+                .collect(),
+            ..FakeJob::new(Job {
+                id: id.to_string(),
+                name: id.to_string(),
+                namespace: DEFAULT_NAMESPACE.to_string(),
+                kind: BATCH_TYPE.to_string(),
+                parameterized: true,
+                status: RUNNING_STATUS.to_string()
+            })
+        };
+
+        self.state.lock().unwrap().jobs.push(job);
+        self
+    }
+
+    /// Register a job which is not parameterized
+    ///
+    /// # Arguments
+    ///
+    /// * `self` - Self
+    /// * `id` - &str
+    /// * `status` - &str
+    pub fn with_job(self, id: &str, status: &str) -> Self {
+        let job = FakeJob::new(Job {
+            id: id.to_string(),
+            name: id.to_string(),
+            namespace: DEFAULT_NAMESPACE.to_string(),
+            kind: BATCH_TYPE.to_string(),
+            parameterized: false,
+            status: status.to_string()
+        });
+
+        self.state.lock().unwrap().jobs.push(job);
+        self
+    }
+
+    /// Move the last registered job to a namespace
+    ///
+    /// # Arguments
+    ///
+    /// * `self` - Self
+    /// * `namespace` - &str
+    pub fn in_namespace(self, namespace: &str) -> Self {
+        if let Some(job) = self.state.lock().unwrap().jobs.last_mut() {
+            job.job.namespace = namespace.to_string();
+        }
+
+        self
+    }
+
+    /// Give a name to the last registered job, which is otherwise named after its id
+    ///
+    /// # Arguments
+    ///
+    /// * `self` - Self
+    /// * `name` - &str
+    pub fn named(self, name: &str) -> Self {
+        if let Some(job) = self.state.lock().unwrap().jobs.last_mut() {
+            job.job.name = name.to_string();
+        }
+
+        self
+    }
+
+    /// Make the last registered job periodic, its dispatches are then returned without evaluation
+    ///
+    /// # Arguments
+    ///
+    /// * `self` - Self
+    pub fn periodic(self) -> Self {
+        if let Some(job) = self.state.lock().unwrap().jobs.last_mut() {
+            job.periodic = true;
+        }
+
+        self
+    }
+
+    /// Add a meta to the spec of the last registered job with the given id
+    ///
+    /// # Arguments
+    ///
+    /// * `self` - Self
+    /// * `id` - &str
+    /// * `key` - &str
+    /// * `value` - &str
+    pub fn with_job_meta(self, id: &str, key: &str, value: &str) -> Self {
+        if let Some(job) = self.state.lock().unwrap().jobs.iter_mut().rev().find(|j| j.job.id == id) {
+            job.metas.insert(key.to_string(), value.to_string());
+        }
+
+        self
+    }
+
+    /// Add a variable with its items
+    ///
+    /// # Arguments
+    ///
+    /// * `self` - Self
+    /// * `namespace` - &str
+    /// * `path` - &str
+    /// * `items` - &[(&str, &str)]
+    pub fn with_variable(self, namespace: &str, path: &str, items: &[(&str, &str)]) -> Self {
+        {
+            let mut state = self.state.lock().unwrap();
+            state.index += 1;
+
+            let variable = Variable {
+                namespace: namespace.to_string(),
+                path: path.to_string(),
+                items: items.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect(),
+                create_index: state.index,
+                modify_index: state.index,
+                modify_time: to_time(state.index)
+            };
+            state.variables.push(variable);
+        }
+
+        self
+    }
+
+    /// Enable the ACLs with the token used to query the fake. Without a token, the ACLs are disabled
+    ///
+    /// # Arguments
+    ///
+    /// * `self` - Self
+    /// * `name` - &str
+    /// * `kind` - &str
+    /// * `policies` - &[&str]
+    /// * `roles` - &[&str]
+    pub fn with_acl_token(self, name: &str, kind: &str, policies: &[&str], roles: &[&str]) -> Self {
+        self.state.lock().unwrap().token = Some(AclToken {
+            accessor_id: format!("accessor-{name}"),
+            name: name.to_string(),
+            kind: kind.to_string(),
+            policies: policies.iter().map(|p| p.to_string()).collect(),
+            roles: roles.iter()
+                .map(|id| AclRoleLink { id: id.to_string(), name: id.to_string() })
+                .collect(),
+            ..Default::default()
+        });
+
+        self
+    }
+
+    /// Add an ACL policy with its rules
+    ///
+    /// # Arguments
+    ///
+    /// * `self` - Self
+    /// * `name` - &str
+    /// * `rules` - &str
+    pub fn with_acl_policy(self, name: &str, rules: &str) -> Self {
+        self.state.lock().unwrap().policies.push(AclPolicy {
+            name: name.to_string(),
+            rules: rules.to_string()
+        });
+
+        self
+    }
+
+    /// Add an ACL role grouping policies
+    ///
+    /// # Arguments
+    ///
+    /// * `self` - Self
+    /// * `id` - &str
+    /// * `policies` - &[&str]
+    pub fn with_acl_role(self, id: &str, policies: &[&str]) -> Self {
+        self.state.lock().unwrap().roles.push(AclRole {
+            id: id.to_string(),
+            name: id.to_string(),
+            policies: policies.iter()
+                .map(|name| AclRoleLink { id: String::new(), name: name.to_string() })
+                .collect()
+        });
+
+        self
+    }
+
+    /// Add an auth method. An OIDC login succeeds when the provider returns the credential as code
+    /// and a JWT login succeeds when the credential is given as JWT
+    ///
+    /// # Arguments
+    ///
+    /// * `self` - Self
+    /// * `name` - &str
+    /// * `credential` - &str
+    pub fn with_auth_method(self, name: &str, credential: &str) -> Self {
+        self.state.lock().unwrap().auth_methods.push((name.to_string(), credential.to_string()));
+
+        self
+    }
+
+    /// Return the payloads received by the dispatch endpoint along with the id of the dispatched job
+    pub fn dispatched(&self) -> Vec<(String, DispatchPayload)> {
+        self.state.lock().unwrap().dispatches.clone()
+    }
+
+    /// Return the ids of the jobs whose allocations have been listed, in the order of the requests
+    pub fn listed_allocations(&self) -> Vec<String> {
+        self.state.lock().unwrap().listed_allocations.clone()
+    }
+}
+
+#[async_trait]
+impl NomadApi for FakeNomad {
+    async fn jobs(&self) -> Result<Vec<Job>, Error> {
+        let state = self.state.lock().unwrap();
+        let jobs = state.jobs.iter()
+            .map(|j| Job { status: state.job_status(j), ..j.job.clone() })
+            .collect();
+
+        Ok(jobs)
+    }
+
+    async fn spec(&self, namespace: &str, job_id: &str) -> Result<Spec, Error> {
+        let state = self.state.lock().unwrap();
+        let job = state.job(namespace, job_id, "GET")?;
+
+        let tasks = job.tasks.iter()
+            .map(|t| Task {
+                name: t.name.clone(),
+                driver: DRIVER.to_string(),
+                config: HashMap::from([("image".to_string(), format!("{}:latest", job.job.name).into())]),
+                dispatch_payload: job.spec.as_ref().map(|_| DispatchPayloadConfig { file: PAYLOAD_FILE.to_string() }),
+                metas: HashMap::new()
+            })
+            .collect();
+
+        Ok(Spec {
+            id: job.job.id.clone(),
+            name: job.job.name.clone(),
+            namespace: job.job.namespace.clone(),
+            kind: BATCH_TYPE.to_string(),
+            datacenters: vec![DATACENTER.to_string()],
+            parameterized: job.spec.clone().unwrap_or_default(),
+            metas: job.metas.clone(),
+            task_groups: vec![TaskGroup {
+                name: TASK_GROUP.to_string(),
+                count: 1,
+                metas: HashMap::new(),
+                tasks
+            }]
+        })
+    }
+
+    async fn dispatch(&self, namespace: &str, job_id: &str, payload: &DispatchPayload) -> Result<DispatchRes, Error> {
+        let mut state = self.state.lock().unwrap();
+        let endpoint = format!("/v1/job/{job_id}/dispatch?namespace={namespace}");
+        let parent = state.job(namespace, job_id, "POST")?.clone();
+
+        let Some(spec) = parent.spec.as_ref() else {
+            return Err(api_error(400, "POST", endpoint, "Specified job is not a parameterized job"));
+        };
+
+        let required = spec.meta_required.clone().unwrap_or_default();
+        let optional = spec.meta_optional.clone().unwrap_or_default();
+
+        let missing: Vec<&String> = required.iter()
+            .filter(|k| !payload.metas.contains_key(*k))
+            .collect();
+        if !missing.is_empty() {
+            return Err(api_error(400, "POST", endpoint, format!("Dispatch did not provide required meta keys: {missing:?}")));
+        }
+
+        let unpermitted: Vec<&String> = payload.metas.keys()
+            .filter(|k| !required.contains(k) && !optional.contains(k))
+            .collect();
+        if !unpermitted.is_empty() {
+            return Err(api_error(400, "POST", endpoint, format!("Dispatch includes unpermitted metadata keys: {unpermitted:?}")));
+        }
+
+        // like nomad, the job already dispatched with the same token is returned without evaluation
+        let existing = state.jobs.iter().find(|j| {
+            j.job.namespace == parent.job.namespace && j.parent_id == job_id && payload.idempotency_token.is_some() && j.idempotency_token == payload.idempotency_token
+        });
+        if let Some(existing) = existing {
+            return Ok(DispatchRes {
+                eval_id: String::new(),
+                dispatch_id: existing.job.id.clone()
+            });
+        }
+
+        state.index += 1;
+        let count = state.dispatches.len();
+        let dispatch_id = format!("{job_id}/dispatch-{}-{count:08x}", state.index);
+
+        // like nomad, the dispatched job inherit the meta of its parent
+        let mut metas = parent.metas.clone();
+        metas.extend(payload.metas.clone());
+
+        let submit_time = to_time(state.index);
+        state.jobs.push(FakeJob {
+            parent_id: job_id.to_string(),
+            metas,
+            submit_time,
+            idempotency_token: payload.idempotency_token.clone(),
+            ..FakeJob::new(Job {
+                id: dispatch_id.clone(),
+                name: dispatch_id.clone(),
+                namespace: parent.job.namespace.clone(),
+                kind: parent.job.kind.clone(),
+                parameterized: false,
+                status: RUNNING_STATUS.to_string()
+            })
+        });
+        state.allocations.push(FakeAllocation {
+            id: format!("alloc-{count}"),
+            job_id: dispatch_id.clone(),
+            tasks: parent.tasks.clone(),
+            modify_time: submit_time
+        });
+        state.dispatches.push((dispatch_id.clone(), payload.clone()));
+
+        // like nomad, the dispatch of a periodic job creates no evaluation
+        let eval_id = match parent.periodic {
+            true => String::new(),
+            false => format!("eval-{count}")
+        };
+
+        Ok(DispatchRes {
+            eval_id,
+            dispatch_id
+        })
+    }
+
+    async fn job_status(&self, namespace: &str, job_id: &str, _: u64, _: Duration) -> Result<(JobStatus, u64), Error> {
+        let state = self.state.lock().unwrap();
+        let job = state.job(namespace, job_id, "GET")?;
+
+        Ok((JobStatus { id: job.job.id.clone(), status: state.job_status(job) }, state.index))
+    }
+
+    async fn allocations(&self, namespace: &str, job_id: &str, _: u64, _: Duration) -> Result<(Vec<Allocation>, u64), Error> {
+        let mut state = self.state.lock().unwrap();
+        state.job(namespace, job_id, "GET")?;
+        state.listed_allocations.push(job_id.to_string());
+
+        let allocs = state.allocations.iter()
+            .filter(|a| a.job_id == job_id)
+            .map(FakeAllocation::to_allocation)
+            .collect();
+
+        Ok((allocs, state.index))
+    }
+
+    async fn allocation(&self, alloc_id: &str, _: u64, _: Duration) -> Result<(Allocation, u64), Error> {
+        let state = self.state.lock().unwrap();
+        let Some(alloc) = state.allocations.iter().find(|a| a.id == alloc_id) else {
+            return Err(api_error(404, "GET", format!("/v1/allocation/{alloc_id}"), "alloc not found"));
+        };
+
+        Ok((alloc.to_allocation(), state.index))
+    }
+
+    async fn logs(&self, alloc_id: &str, task_name: &str, std_kind: &StdKind, offset: i64) -> Result<StreamLog, Error> {
+        let mut state = self.state.lock().unwrap();
+        let endpoint = format!("/v1/client/fs/logs/{alloc_id}");
+
+        let time = to_time(state.index + 1);
+        let Some(alloc) = state.allocations.iter_mut().find(|a| a.id == alloc_id) else {
+            return Err(api_error(404, "GET", endpoint, "alloc not found"));
+        };
+
+        let Some(task) = alloc.tasks.iter_mut().find(|t| t.name == task_name) else {
+            return Err(api_error(404, "GET", endpoint, "unknown task name"));
+        };
+
+        let (content, read) = match std_kind {
+            StdKind::Stdout => (&task.stdout, &mut task.stdout_read),
+            StdKind::Stderr => (&task.stderr, &mut task.stderr_read)
+        };
+
+        let start = (offset.max(0) as usize).min(content.len());
+        let data = content[start..].to_string();
+        let log = StreamLog {
+            offset: Some(content.len() as i64),
+            data: (!data.is_empty()).then(|| data.to_base64())
+        };
+
+        // reading the logs for the first time makes the task progress
+        if !std::mem::replace(read, true) {
+            alloc.modify_time = time;
+            state.index += 1;
+        }
+
+        Ok(log)
+    }
+
+    async fn dispatched_jobs(&self, prefix: &str, _: u64, _: Duration) -> Result<(Vec<DispatchedJob>, u64), Error> {
+        let state = self.state.lock().unwrap();
+        let jobs = state.jobs.iter()
+            .filter(|j| j.job.id.starts_with(prefix))
+            .map(|j| {
+                let summary = state.allocations.iter()
+                    .filter(|a| a.job_id == j.job.id)
+                    .fold(TaskGroupSummary::default(), |acc, a| match a.tasks.iter().all(FakeTask::finished) {
+                        true => TaskGroupSummary { complete: acc.complete + 1, ..acc },
+                        false => TaskGroupSummary { running: acc.running + 1, ..acc }
+                    });
+
+                DispatchedJob {
+                    id: j.job.id.clone(),
+                    parent_id: j.parent_id.clone(),
+                    namespace: j.job.namespace.clone(),
+                    status: state.job_status(j),
+                    submit_time: j.submit_time,
+                    metas: j.metas.clone(),
+                    summary: Some(JobSummary {
+                        job_id: j.job.id.clone(),
+                        task_groups: HashMap::from([(TASK_GROUP.to_string(), summary)]),
+                        children: None
+                    })
+                }
+            })
+            .collect();
+
+        Ok((jobs, state.index))
+    }
+
+    async fn job_summary(&self, namespace: &str, job_id: &str) -> Result<JobSummary, Error> {
+        let state = self.state.lock().unwrap();
+        let parent = state.job(namespace, job_id, "GET")?;
+
+        let children = state.jobs.iter()
+            .filter(|j| j.job.namespace == parent.job.namespace && j.parent_id == job_id)
+            .fold(ChildrenSummary::default(), |acc, j| match state.job_status(j).as_str() {
+                DEAD_STATUS => ChildrenSummary { dead: acc.dead + 1, ..acc },
+                _ => ChildrenSummary { running: acc.running + 1, ..acc }
+            });
+
+        Ok(JobSummary {
+            job_id: job_id.to_string(),
+            task_groups: HashMap::new(),
+            children: Some(children)
+        })
+    }
+
+    async fn deregister(&self, namespace: &str, job_id: &str, purge: bool) -> Result<(), Error> {
+        let mut state = self.state.lock().unwrap();
+        let namespace = state.job(namespace, job_id, "DELETE")?.job.namespace.clone();
+        state.index += 1;
+
+        if purge {
+            state.jobs.retain(|j| j.job.namespace != namespace || j.job.id != job_id);
+            state.allocations.retain(|a| a.job_id != job_id);
+
+            return Ok(());
+        }
+
+        if let Some(job) = state.jobs.iter_mut().find(|j| j.job.namespace == namespace && j.job.id == job_id) {
+            job.job.status = DEAD_STATUS.to_string();
+        }
+
+        Ok(())
+    }
+
+    async fn variables(&self, namespace: &str, prefix: &str) -> Result<Vec<VariableMetadata>, Error> {
+        let state = self.state.lock().unwrap();
+        let mut variables: Vec<VariableMetadata> = state.variables.iter()
+            .filter(|v| v.namespace == namespace && v.path.starts_with(prefix))
+            .map(|v| VariableMetadata {
+                namespace: v.namespace.clone(),
+                path: v.path.clone(),
+                create_index: v.create_index,
+                modify_index: v.modify_index,
+                modify_time: v.modify_time
+            })
+            .collect();
+        variables.sort_by(|a, b| a.path.cmp(&b.path));
+
+        Ok(variables)
+    }
+
+    async fn variable(&self, namespace: &str, path: &str) -> Result<Variable, Error> {
+        let state = self.state.lock().unwrap();
+
+        state.variable(namespace, path)
+            .cloned()
+            .ok_or_else(|| api_error(404, "GET", format!("/v1/var/{path}"), "variable not found"))
+    }
+
+    async fn put_variable(&self, variable: &Variable, cas: Option<u64>) -> Result<Variable, Error> {
+        let mut state = self.state.lock().unwrap();
+        state.check_cas(&variable.namespace, &variable.path, cas, "PUT")?;
+        state.index += 1;
+
+        let create_index = state.variable(&variable.namespace, &variable.path)
+            .map(|v| v.create_index)
+            .unwrap_or(state.index);
+        let written = Variable {
+            create_index,
+            modify_index: state.index,
+            modify_time: to_time(state.index),
+            ..variable.clone()
+        };
+
+        state.variables.retain(|v| v.namespace != variable.namespace || v.path != variable.path);
+        state.variables.push(written.clone());
+
+        Ok(written)
+    }
+
+    async fn delete_variable(&self, namespace: &str, path: &str, cas: Option<u64>) -> Result<(), Error> {
+        let mut state = self.state.lock().unwrap();
+        state.check_cas(namespace, path, cas, "DELETE")?;
+        state.index += 1;
+        state.variables.retain(|v| v.namespace != namespace || v.path != path);
+
+        Ok(())
+    }
+
+    async fn namespaces(&self) -> Result<Vec<Namespace>, Error> {
+        let state = self.state.lock().unwrap();
+        let mut names: Vec<&String> = state.jobs.iter()
+            .map(|j| &j.job.namespace)
+            .chain(state.variables.iter().map(|v| &v.namespace))
+            .collect();
+        names.sort();
+        names.dedup();
+
+        Ok(names.into_iter()
+            .map(|name| Namespace { name: name.clone(), description: String::new() })
+            .collect())
+    }
+
+    async fn token_self(&self) -> Result<AclToken, Error> {
+        self.state.lock().unwrap()
+            .token
+            .clone()
+            .ok_or_else(|| api_error(400, "GET", "/v1/acl/token/self".to_string(), "ACL support disabled"))
+    }
+
+    async fn acl_policy(&self, name: &str) -> Result<AclPolicy, Error> {
+        self.state.lock().unwrap()
+            .policies
+            .iter()
+            .find(|p| p.name == name)
+            .cloned()
+            .ok_or_else(|| api_error(404, "GET", format!("/v1/acl/policy/{name}"), "ACL policy not found"))
+    }
+
+    async fn acl_role(&self, id: &str) -> Result<AclRole, Error> {
+        self.state.lock().unwrap()
+            .roles
+            .iter()
+            .find(|r| r.id == id)
+            .cloned()
+            .ok_or_else(|| api_error(404, "GET", format!("/v1/acl/role/{id}"), "ACL role not found"))
+    }
+
+    async fn oidc_auth_url(&self, req: &OidcAuthUrlRequest) -> Result<OidcAuthUrl, Error> {
+        let endpoint = "/v1/acl/oidc/auth-url".to_string();
+        let mut state = self.state.lock().unwrap();
+        if !state.auth_methods.iter().any(|(name, _)| *name == req.auth_method) {
+            return Err(api_error(400, "POST", endpoint, "auth-method not found"));
+        }
+
+        let oidc_state = format!("state-{}", state.oidc_states.len());
+        state.oidc_states.push((oidc_state.clone(), req.client_nonce.clone()));
+
+        let params = [("redirect_uri", req.redirect_uri.as_str()), ("state", oidc_state.as_str())];
+        let url = reqwest::Url::parse_with_params(OIDC_PROVIDER_URL, params)
+            .map_err(|err| api_error(400, "POST", endpoint, err))?;
+
+        Ok(OidcAuthUrl { auth_url: url.to_string() })
+    }
+
+    async fn oidc_complete_auth(&self, req: &OidcCompleteAuthRequest) -> Result<LoginToken, Error> {
+        let endpoint = "/v1/acl/oidc/complete-auth".to_string();
+        let state = self.state.lock().unwrap();
+        let pending = state.oidc_states.iter()
+            .any(|(oidc_state, nonce)| *oidc_state == req.state && *nonce == req.client_nonce);
+
+        if !pending {
+            return Err(api_error(400, "POST", endpoint, "invalid state or nonce"));
+        }
+
+        login(&state, &req.auth_method, &req.code, endpoint)
+    }
+
+    async fn jwt_login(&self, req: &JwtLoginRequest) -> Result<LoginToken, Error> {
+        let state = self.state.lock().unwrap();
+        login(&state, &req.auth_method, &req.login_token, "/v1/acl/login".to_string())
+    }
+}
+
+/// Issue a token when the credential is accepted by the auth method
+///
+/// # Arguments
+///
+/// * `state` - &State
+/// * `auth_method` - &str
+/// * `credential` - &str
+/// * `endpoint` - String
+fn login(state: &State, auth_method: &str, credential: &str, endpoint: String) -> Result<LoginToken, Error> {
+    match state.auth_methods.iter().find(|(name, _)| name == auth_method) {
+        Some((_, accepted)) if accepted == credential => Ok(LoginToken {
+            accessor_id: format!("accessor-{auth_method}"),
+            secret_id: format!("secret-{auth_method}"),
+            expiration_time: Some(chrono::Utc::now() + chrono::Duration::seconds(LOGIN_TOKEN_TTL_SECONDS))
+        }),
+        Some(_) => Err(api_error(403, "POST", endpoint, "Permission denied")),
+        None => Err(api_error(400, "POST", endpoint, "auth-method not found"))
+    }
+}
+
+/// Convert an index of the fake into a timestamp in nanoseconds
+///
+/// # Arguments
+///
+/// * `index` - u64
+fn to_time(index: u64) -> i64 {
+    (BASE_TIME + index as i64) * 1_000_000_000
+}
+
+/// Build an error as it would be returned by the Nomad API
+///
+/// # Arguments
+///
+/// * `status` - u16
+/// * `method` - &str
+/// * `endpoint` - String
+/// * `body` - S
+fn api_error<S: ToString>(status: u16, method: &str, endpoint: String, body: S) -> Error {
+    Error::Api {
+        status,
+        method: method.to_string(),
+        endpoint,
+        body: body.to_string()
+    }
+}
@@ -1,26 +1,45 @@
-use std::collections::HashMap;
-use serde::Deserialize;
+use std::collections::{HashMap, HashSet};
+use serde::{Deserialize, Serialize};
+use futures::future::join_all;
+use tokio::time::Duration;
 use crate::error::{Error, self};
-use crate::helper::{to_json, Base64};
-use crate::rest::RestHandler;
+use super::api::NomadApi;
 use super::spec::Spec;
 use super::dispatch::{DispatchRes, DispatchPayload};
+use super::summary::DISPATCH_SEPARATOR;
 
 // Constant
-const JOB_ENDPOINT: &str = "v1/jobs";
+const JOB_STATUS_WAIT: u64 = 10000;
+const DEAD_STATUS: &str = "dead";
+const RUNNING_STATUS: &str = "running";
 
-#[derive(Debug, Deserialize, Default)]
+#[derive(Debug, Deserialize, Serialize, Default, Clone)]
 pub struct Job {
-    #[serde(rename(deserialize = "ID"))]
+    #[serde(rename = "ID")]
     pub id: String,
 
-    #[serde(rename(deserialize = "Name"))]
+    #[serde(rename = "Name")]
     pub name: String,
 
-    #[serde(rename(deserialize = "ParameterizedJob"))]
+    #[serde(rename = "Namespace", default)]
+    pub namespace: String,
+
+    #[serde(rename = "Type", default)]
+    pub kind: String,
+
+    #[serde(rename = "ParameterizedJob")]
     pub parameterized: bool,
 
-    #[serde(rename(deserialize = "Status"))]
+    #[serde(rename = "Status")]
+    pub status: String
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct JobStatus {
+    #[serde(rename = "ID")]
+    pub id: String,
+
+    #[serde(rename = "Status")]
     pub status: String
 }
 
@@ -30,9 +49,9 @@ impl Job {
     /// # Arguments
     ///
     /// * `&self` - &Job
-    /// * `handler` - &RestHandler
-    pub async fn get_job_meta(&self, handler: &RestHandler) -> Result<(Option<Vec<String>>, Option<Vec<String>>), Error> {
-        let spec = Spec::get(&self.name, handler).await?;
+    /// * `api` - &dyn NomadApi
+    pub async fn get_job_meta(&self, api: &dyn NomadApi) -> Result<(Option<Vec<String>>, Option<Vec<String>>), Error> {
+        let spec = Spec::get(&self.namespace, &self.id, api).await?;
 
         Ok((spec.parameterized.meta_required, spec.parameterized.meta_optional))
     }
@@ -42,27 +61,72 @@ impl Job {
     /// # Arguments
     ///
     /// * `&self` - &Job
-    /// * `handler` - &RestHandler
+    /// * `api` - &dyn NomadApi
     /// * `metas` - HashMap<String, String>
-    pub async fn dispatch_job(&self, handler: &RestHandler, metas: HashMap<String, String>) -> Result<DispatchRes, Error> {
-        let endpoint = format!("v1/job/{}/dispatch", self.id);
+    pub async fn dispatch_job(&self, api: &dyn NomadApi, metas: HashMap<String, String>) -> Result<DispatchRes, Error> {
+        // the metas are sent as a json payload
+        let payload = DispatchPayload::new(metas, None)?;
 
-        // transform the hashmap into a json in order to convert it to a base64
-        let map_json = to_json(&metas)?;
-        let payload = map_json.to_base64();
+        self.send_dispatch(api, &payload).await
+    }
 
-        let payload = DispatchPayload { payload, metas };
+    /// Dispatch a job to nomad with the selected metas and a custom payload
+    ///
+    /// # Arguments
+    ///
+    /// * `&self` - &Job
+    /// * `api` - &dyn NomadApi
+    /// * `metas` - HashMap<String, String>
+    /// * `payload` - String
+    pub async fn dispatch_job_with_payload(&self, api: &dyn NomadApi, metas: HashMap<String, String>, payload: String) -> Result<DispatchRes, Error> {
+        let payload = DispatchPayload::new(metas, Some(payload))?;
 
-        // send the dispatch to nomad
-        let res: DispatchRes = handler.post(&endpoint, payload).await?;
+        self.send_dispatch(api, &payload).await
+    }
 
-        Ok(res)
+    /// Send a dispatch payload to nomad. Nomad returns the job previously dispatched with the same
+    /// idempotency token, which is reported as an error. A new job may have no evaluation either,
+    /// e.g. for a periodic job, so the returned job is looked up among the jobs dispatched beforehand
+    ///
+    /// # Arguments
+    ///
+    /// * `&self` - &Job
+    /// * `api` - &dyn NomadApi
+    /// * `payload` - &DispatchPayload
+    pub async fn send_dispatch(&self, api: &dyn NomadApi, payload: &DispatchPayload) -> Result<DispatchRes, Error> {
+        let dispatched = match payload.idempotency_token {
+            Some(_) => self.dispatched_ids(api).await?,
+            None => HashSet::new()
+        };
+
+        let res = api.dispatch(&self.namespace, &self.id, payload).await?;
+
+        match dispatched.contains(&res.dispatch_id) {
+            true => Err(Error::AlreadyDispatched { job: res.dispatch_id }),
+            false => Ok(res)
+        }
+    }
+
+    /// Get the ids of the jobs dispatched from the job
+    ///
+    /// # Arguments
+    ///
+    /// * `&self` - &Job
+    /// * `api` - &dyn NomadApi
+    async fn dispatched_ids(&self, api: &dyn NomadApi) -> Result<HashSet<String>, Error> {
+        let prefix = format!("{}{DISPATCH_SEPARATOR}", self.id);
+        let (jobs, _) = api.dispatched_jobs(&prefix, 0, Duration::ZERO).await?;
+
+        Ok(jobs.into_iter()
+            .filter(|j| j.namespace == self.namespace && j.parent_id == self.id)
+            .map(|j| j.id)
+            .collect())
     }
 }
 
-impl ToString for Job {
-    fn to_string(&self) -> String {
-        self.name.to_owned()
+impl std::fmt::Display for Job {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name)
     }
 }
 
@@ -70,13 +134,9 @@ impl ToString for Job {
 ///
 /// # Arguments
 ///
-/// * `handler` - &RestHandler
-pub async fn get_nomad_job_list(handler: &RestHandler) -> Result<Vec<Job>, Error> {
-    let endpoint = format!("{}?meta=true&namespace=*", JOB_ENDPOINT);
-    let jobs: Vec<Job> = handler.get::<Vec<Job>, _>(&endpoint)
-        .await?
-        .into_iter()
-        .collect();
+/// * `api` - &dyn NomadApi
+pub async fn get_nomad_job_list(api: &dyn NomadApi) -> Result<Vec<Job>, Error> {
+    let jobs = api.jobs().await?;
 
     if jobs.is_empty() {
         return Err(Error::ScenarioErr(error::JOBS_NOT_FOUND_ERR.to_string()));
@@ -84,3 +144,93 @@ pub async fn get_nomad_job_list(handler: &RestHandler) -> Result<Vec<Job>, Error
 
     Ok(jobs)
 }
+
+/// Find a job by its id. The namespace is required when jobs of several namespaces share the id
+///
+/// # Arguments
+///
+/// * `jobs` - &[Job]
+/// * `namespace` - Option<&str>
+/// * `id` - &str
+pub fn find_job<'a>(jobs: &'a [Job], namespace: Option<&str>, id: &str) -> Result<&'a Job, Error> {
+    let matches: Vec<&Job> = jobs.iter()
+        .filter(|j| j.id == id && namespace.is_none_or(|ns| j.namespace == ns))
+        .collect();
+
+    match matches.as_slice() {
+        [job] => Ok(job),
+        [] => Err(Error::ScenarioErr(error::SELECTED_JOB_NOT_FOUND_ERR.to_string())),
+        _ => {
+            let namespaces: Vec<&str> = matches.iter().map(|j| j.namespace.as_str()).collect();
+            Err(Error::ScenarioErr(format!("{} {}", error::AMBIGUOUS_JOB_ERR, namespaces.join(", "))))
+        }
+    }
+}
+
+/// Get the list of parameterized job which can be dispatched
+///
+/// # Arguments
+///
+/// * `api` - &dyn NomadApi
+pub async fn get_parameterized_jobs(api: &dyn NomadApi) -> Result<Vec<Job>, Error> {
+    let jobs = get_nomad_job_list(api)
+        .await?
+        .into_iter()
+        .filter(|j| j.parameterized)
+        .collect();
+
+    Ok(jobs)
+}
+
+/// Get the list of running jobs which can be stopped. Parameterized job are excluded
+///
+/// # Arguments
+///
+/// * `api` - &dyn NomadApi
+pub async fn get_running_jobs(api: &dyn NomadApi) -> Result<Vec<Job>, Error> {
+    let jobs = get_nomad_job_list(api)
+        .await?
+        .into_iter()
+        .filter(|j| !j.parameterized && j.status == RUNNING_STATUS)
+        .collect();
+
+    Ok(jobs)
+}
+
+/// Stop the provided jobs concurrently. The result of each job is returned in the same order
+///
+/// # Arguments
+///
+/// * `api` - &dyn NomadApi
+/// * `jobs` - &[Job]
+/// * `purge` - bool
+pub async fn stop_jobs(api: &dyn NomadApi, jobs: &[Job], purge: bool) -> Vec<Result<(), Error>> {
+    let tasks = jobs.iter()
+        .map(|job| api.deregister(&job.namespace, &job.id, purge));
+
+    join_all(tasks).await
+}
+
+/// Watch the status of a job until the job is dead. A blocking query is used in order to
+/// be notified as soon as the status of the job change
+///
+/// # Arguments
+///
+/// * `api` - &dyn NomadApi
+/// * `namespace` - &str
+/// * `job_id` - &str
+pub async fn wait_for_dead_job(api: &dyn NomadApi, namespace: &str, job_id: &str) -> Result<JobStatus, Error> {
+    let mut index = 0;
+
+    loop {
+        let (job, last_index) = api
+            .job_status(namespace, job_id, index, Duration::from_millis(JOB_STATUS_WAIT))
+            .await?;
+
+        if job.status == DEAD_STATUS {
+            return Ok(job);
+        }
+
+        index = last_index.max(1);
+    }
+}
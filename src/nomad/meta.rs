@@ -0,0 +1,305 @@
+use std::collections::{HashMap, HashSet};
+use std::str::FromStr;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+use chrono::NaiveDate;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use crate::error::{Error, self};
+
+// Constant
+pub const DATE_FORMAT: &str = "%Y-%m-%d";
+const TRUE_VALUES: [&str; 4] = ["true", "yes", "y", "1"];
+const FALSE_VALUES: [&str; 4] = ["false", "no", "n", "0"];
+const NANOS_PER_SECOND: u128 = 1_000_000_000;
+pub const REDACTED: &str = "<redacted>";
+
+/// Type of the value expected by a meta. Values are normalized according to their type before being dispatched
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MetaType {
+    #[default]
+    String,
+    Int,
+    Float,
+    Bool,
+    Duration,
+    Date,
+    Json
+}
+
+/// Keys of the secret metas whose value is redacted from the requests and the responses dumped for debugging.
+/// The clones share the same keys, so the secrets known once a job is selected reach the handler sending the requests
+#[derive(Debug, Clone, Default)]
+pub struct SecretKeys(Arc<RwLock<HashSet<String>>>);
+
+/// Prompt asking the value of a meta
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MetaPrompt {
+    /// List of the choices of the meta
+    Select,
+    /// Masked text
+    Password,
+    /// Integer parsed while typed
+    Int,
+    /// Float parsed while typed
+    Float,
+    /// Yes / no question
+    Confirm,
+    /// Calendar
+    DateSelect,
+    /// Editor of the user
+    Editor,
+    /// Free form text
+    Text
+}
+
+/// Meta accepted by a parameterized job along with the rules used to prompt and validate its value
+#[derive(Debug, Clone, Default)]
+pub struct MetaField {
+    pub name: String,
+    pub required: bool,
+    pub kind: MetaType,
+    pub description: Option<String>,
+    pub default: Option<String>,
+    pub pattern: Option<Regex>,
+    pub choices: Vec<String>,
+    pub secret: bool
+}
+
+impl MetaType {
+    /// Parse a value of this type and return its normalized representation
+    ///
+    /// # Arguments
+    ///
+    /// * `&self` - MetaType
+    /// * `value` - &str
+    pub fn normalize(&self, value: &str) -> Option<String> {
+        let trimmed = value.trim();
+
+        match self {
+            MetaType::String => Some(value.to_string()),
+            MetaType::Int => trimmed.parse::<i64>().ok().map(|v| v.to_string()),
+            MetaType::Float => trimmed.parse::<f64>().ok().filter(|v| v.is_finite()).map(|v| v.to_string()),
+            MetaType::Bool => {
+                let lower = trimmed.to_lowercase();
+                match (TRUE_VALUES.contains(&lower.as_str()), FALSE_VALUES.contains(&lower.as_str())) {
+                    (true, _) => Some(true.to_string()),
+                    (_, true) => Some(false.to_string()),
+                    _ => None
+                }
+            },
+            MetaType::Duration => parse_duration(trimmed).map(format_duration),
+            MetaType::Date => NaiveDate::parse_from_str(trimmed, DATE_FORMAT).ok().map(|d| d.format(DATE_FORMAT).to_string()),
+            MetaType::Json => serde_json::from_str::<Value>(trimmed).ok().map(|v| v.to_string())
+        }
+    }
+}
+
+impl FromStr for MetaType {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "string" => Ok(MetaType::String),
+            "int" => Ok(MetaType::Int),
+            "float" => Ok(MetaType::Float),
+            "bool" => Ok(MetaType::Bool),
+            "duration" => Ok(MetaType::Duration),
+            "date" => Ok(MetaType::Date),
+            "json" => Ok(MetaType::Json),
+            other => Err(format!("{} {other}", error::UNKNOWN_TYPE_REASON))
+        }
+    }
+}
+
+impl std::fmt::Display for MetaType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MetaType::String => write!(f, "string"),
+            MetaType::Int => write!(f, "int"),
+            MetaType::Float => write!(f, "float"),
+            MetaType::Bool => write!(f, "bool"),
+            MetaType::Duration => write!(f, "duration"),
+            MetaType::Date => write!(f, "date"),
+            MetaType::Json => write!(f, "json")
+        }
+    }
+}
+
+impl MetaField {
+    /// Check that a value is accepted by the meta and return its normalized representation.
+    /// An empty value is returned as is for optional metas
+    ///
+    /// # Arguments
+    ///
+    /// * `&self` - MetaField
+    /// * `value` - &str
+    pub fn normalize(&self, value: &str) -> Result<String, Error> {
+        let invalid = |reason: String| Err(Error::InvalidMeta {
+            key: self.name.clone(),
+            reason
+        });
+
+        if value.is_empty() {
+            return match self.required {
+                true => invalid(error::REQUIRED_META_REASON.to_string()),
+                false => Ok(String::new())
+            };
+        }
+
+        let Some(value) = self.kind.normalize(value) else {
+            return invalid(format!("{} {}", error::TYPE_META_REASON, self.kind));
+        };
+
+        // choices are normalized as well so that 1.0 match the choice 1 of a float
+        let is_choice = |choice: &String| self.kind.normalize(choice).as_ref() == Some(&value);
+        if !self.choices.is_empty() && !self.choices.iter().any(is_choice) {
+            return invalid(format!("{} {}", error::CHOICES_META_REASON, self.choices.join(", ")));
+        }
+
+        match &self.pattern {
+            Some(pattern) if !pattern.is_match(&value) => invalid(format!("{} {}", error::PATTERN_META_REASON, pattern.as_str())),
+            _ => Ok(value)
+        }
+    }
+
+    /// Return the prompt asking the value of the meta. The choices take precedence over the type and
+    /// secret metas are masked
+    ///
+    /// # Arguments
+    ///
+    /// * `&self` - MetaField
+    pub fn prompt(&self) -> MetaPrompt {
+        match (self.choices.is_empty(), self.kind) {
+            (false, _) => MetaPrompt::Select,
+            (true, _) if self.secret => MetaPrompt::Password,
+            (true, MetaType::Int) => MetaPrompt::Int,
+            (true, MetaType::Float) => MetaPrompt::Float,
+            (true, MetaType::Bool) => MetaPrompt::Confirm,
+            (true, MetaType::Date) => MetaPrompt::DateSelect,
+            (true, MetaType::Json) => MetaPrompt::Editor,
+            (true, MetaType::String | MetaType::Duration) => MetaPrompt::Text
+        }
+    }
+
+    /// Check that a value is accepted by the meta
+    ///
+    /// # Arguments
+    ///
+    /// * `&self` - MetaField
+    /// * `value` - &str
+    pub fn validate(&self, value: &str) -> Result<(), Error> {
+        self.normalize(value).map(|_| ())
+    }
+}
+
+/// Parse a duration written with the Go syntax such as 1h30m or 1.5s
+///
+/// # Arguments
+///
+/// * `value` - &str
+pub fn parse_duration(value: &str) -> Option<Duration> {
+    if value == "0" {
+        return Some(Duration::ZERO);
+    }
+
+    let mut rest = value;
+    let mut nanos = 0f64;
+    while !rest.is_empty() {
+        let number_end = rest.find(|c: char| !c.is_ascii_digit() && c != '.')?;
+        let number: f64 = rest[..number_end].parse().ok()?;
+        rest = &rest[number_end..];
+
+        let unit_end = rest.find(|c: char| c.is_ascii_digit() || c == '.').unwrap_or(rest.len());
+        let unit = match &rest[..unit_end] {
+            "ns" => 1f64,
+            "us" | "µs" => 1e3,
+            "ms" => 1e6,
+            "s" => 1e9,
+            "m" => 60e9,
+            "h" => 3600e9,
+            _ => return None
+        };
+
+        nanos += number * unit;
+        rest = &rest[unit_end..];
+    }
+
+    match value.is_empty() {
+        true => None,
+        false => Some(Duration::from_nanos(nanos.round() as u64))
+    }
+}
+
+/// Format a duration with the Go syntax so that it can be parsed by the task
+///
+/// # Arguments
+///
+/// * `duration` - Duration
+pub fn format_duration(duration: Duration) -> String {
+    let nanos = duration.as_nanos();
+    if nanos == 0 {
+        return "0s".to_string();
+    }
+
+    if nanos < NANOS_PER_SECOND {
+        return match nanos {
+            n if n < 1_000 => format!("{n}ns"),
+            n if n < 1_000_000 => format!("{}µs", n as f64 / 1e3),
+            n => format!("{}ms", n as f64 / 1e6)
+        };
+    }
+
+    let secs = duration.as_secs();
+    let (hours, minutes) = (secs / 3600, secs % 3600 / 60);
+    let seconds = (nanos % (60 * NANOS_PER_SECOND)) as f64 / 1e9;
+
+    match (hours, minutes) {
+        (0, 0) => format!("{seconds}s"),
+        (0, m) => format!("{m}m{seconds}s"),
+        (h, m) => format!("{h}h{m}m{seconds}s")
+    }
+}
+
+/// Replace the value of the secret metas by a placeholder
+///
+/// # Arguments
+///
+/// * `metas` - &HashMap<String, String>
+/// * `secrets` - &HashSet<String>
+pub fn redact_metas(metas: &HashMap<String, String>, secrets: &HashSet<String>) -> HashMap<String, String> {
+    metas.iter()
+        .map(|(key, value)| match secrets.contains(key) {
+            true => (key.clone(), REDACTED.to_string()),
+            false => (key.clone(), value.clone())
+        })
+        .collect()
+}
+
+impl SecretKeys {
+    /// Add the keys of secret metas
+    ///
+    /// # Arguments
+    ///
+    /// * `&self` - SecretKeys
+    /// * `keys` - I
+    pub fn extend<'a, I: IntoIterator<Item = &'a String>>(&self, keys: I) {
+        self.0.write()
+            .unwrap_or_else(|err| err.into_inner())
+            .extend(keys.into_iter().cloned());
+    }
+
+    /// Return whether a meta is secret
+    ///
+    /// # Arguments
+    ///
+    /// * `&self` - SecretKeys
+    /// * `key` - &str
+    pub fn contains(&self, key: &str) -> bool {
+        self.0.read()
+            .unwrap_or_else(|err| err.into_inner())
+            .contains(key)
+    }
+}
@@ -1,5 +1,12 @@
+pub mod acl;
 pub mod alloc;
+pub mod api;
 pub mod job;
+pub mod meta;
+pub mod namespace;
 pub mod stream;
-mod spec;
-mod dispatch;
+pub mod spec;
+pub mod dispatch;
+pub mod summary;
+pub mod variable;
+pub mod fake;
@@ -0,0 +1,15 @@
+use serde::{Deserialize, Serialize};
+
+// Constant
+/// Namespace of the jobs and variables when none is given
+pub const DEFAULT_NAMESPACE: &str = "default";
+
+/// Nomad namespace
+#[derive(Debug, Deserialize, Serialize, Default, Clone, PartialEq, Eq)]
+pub struct Namespace {
+    #[serde(rename = "Name")]
+    pub name: String,
+
+    #[serde(rename = "Description", default)]
+    pub description: String
+}
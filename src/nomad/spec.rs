@@ -1,36 +1,269 @@
-use serde::Deserialize;
-use crate::{error::Error, rest::RestHandler};
+use std::collections::{HashMap, HashSet};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use crate::error::{Error, self};
+use crate::helper::null_as_default;
+use super::api::NomadApi;
+use super::meta::{MetaField, MetaType, REDACTED};
 
 // Constant
-const SPEC_ENDPOINT: &str = "v1/job";
+const IMAGE_CONFIG: &str = "image";
+const META_PREFIX: &str = "noumead";
+const DESCRIPTION_ATTR: &str = "description";
+const DEFAULT_ATTR: &str = "default";
+const PATTERN_ATTR: &str = "pattern";
+const CHOICES_ATTR: &str = "choices";
+const TYPE_ATTR: &str = "type";
+const SECRET_ATTR: &str = "secret";
+const CHOICES_SEPARATOR: char = ',';
 
-#[derive(Debug, Deserialize)]
-pub(crate) struct Spec {
-    #[serde(rename(deserialize = "ParameterizedJob"))]
-    pub parameterized: Parameterized
+/// Return the metas marked as secret with the convention `noumead.<meta>.secret`. Dispatched jobs
+/// inherit the meta of their parent, so the convention can be read from their own meta as well
+///
+/// # Arguments
+///
+/// * `metas` - &HashMap<String, String>
+pub fn secret_keys(metas: &HashMap<String, String>) -> HashSet<String> {
+    metas.iter()
+        .filter(|(_, value)| MetaType::Bool.normalize(value).as_deref() == Some("true"))
+        .filter_map(|(key, _)| key
+            .strip_prefix(&format!("{META_PREFIX}."))
+            .and_then(|key| key.strip_suffix(&format!(".{SECRET_ATTR}")))
+            .map(str::to_string))
+        .collect()
 }
 
-#[derive(Debug, Deserialize)]
-pub(crate) struct Parameterized {
-    #[serde(rename(deserialize = "MetaRequired"))]
+/// Specification of a job as returned by Nomad. Only the fields describing how the job can be dispatched are kept
+#[derive(Debug, Deserialize, Serialize, Default, Clone)]
+pub struct Spec {
+    #[serde(rename = "ID", default)]
+    pub id: String,
+
+    #[serde(rename = "Name", default)]
+    pub name: String,
+
+    #[serde(rename = "Namespace", default)]
+    pub namespace: String,
+
+    #[serde(rename = "Type", default)]
+    pub kind: String,
+
+    #[serde(rename = "Datacenters", default, deserialize_with = "null_as_default")]
+    pub datacenters: Vec<String>,
+
+    #[serde(rename = "ParameterizedJob", default, deserialize_with = "null_as_default")]
+    pub parameterized: Parameterized,
+
+    #[serde(rename = "Meta", default, deserialize_with = "null_as_default")]
+    pub metas: HashMap<String, String>,
+
+    #[serde(rename = "TaskGroups", default, deserialize_with = "null_as_default")]
+    pub task_groups: Vec<TaskGroup>
+}
+
+#[derive(Debug, Deserialize, Serialize, Default, Clone)]
+pub struct Parameterized {
+    #[serde(rename = "Payload", default)]
+    pub payload: String,
+
+    #[serde(rename = "MetaRequired")]
     pub meta_required: Option<Vec<String>>,
 
-    #[serde(rename(deserialize = "MetaOptional"))]
+    #[serde(rename = "MetaOptional")]
     pub meta_optional: Option<Vec<String>>
 }
 
+#[derive(Debug, Deserialize, Serialize, Default, Clone)]
+pub struct TaskGroup {
+    #[serde(rename = "Name")]
+    pub name: String,
+
+    #[serde(rename = "Count", default)]
+    pub count: u64,
+
+    #[serde(rename = "Meta", default, deserialize_with = "null_as_default")]
+    pub metas: HashMap<String, String>,
+
+    #[serde(rename = "Tasks", default, deserialize_with = "null_as_default")]
+    pub tasks: Vec<Task>
+}
+
+#[derive(Debug, Deserialize, Serialize, Default, Clone)]
+pub struct Task {
+    #[serde(rename = "Name")]
+    pub name: String,
+
+    #[serde(rename = "Driver", default)]
+    pub driver: String,
+
+    #[serde(rename = "Config", default, deserialize_with = "null_as_default")]
+    pub config: HashMap<String, Value>,
+
+    #[serde(rename = "DispatchPayload", default)]
+    pub dispatch_payload: Option<DispatchPayloadConfig>,
+
+    #[serde(rename = "Meta", default, deserialize_with = "null_as_default")]
+    pub metas: HashMap<String, String>
+}
+
+/// Destination of the dispatch payload in the task directory
+#[derive(Debug, Deserialize, Serialize, Default, Clone)]
+pub struct DispatchPayloadConfig {
+    #[serde(rename = "File", default)]
+    pub file: String
+}
+
 impl Spec {
     /// Get the spec of the selected job
     ///
     /// # Arguments
     ///
+    /// * `namespace` - &str
     /// * `name` - &str
-    /// * `base_url` - &str
-    /// * `handler` - &RestHandler
-    pub async fn get(name: &str, handler: &RestHandler) -> Result<Spec, Error> {
-        let endpoint = format!("{}/{}", SPEC_ENDPOINT, name);
-        let spec = handler.get::<Spec, _>(&endpoint).await?;
+    /// * `api` - &dyn NomadApi
+    pub async fn get(namespace: &str, name: &str, api: &dyn NomadApi) -> Result<Spec, Error> {
+        let spec = api.spec(namespace, name).await?;
 
         Ok(spec)
     }
+
+    /// List the required and optional metas of the job along with the attributes defined in the meta of the job.
+    /// The attributes are read by following the convention `noumead.<meta>.<attribute>`
+    ///
+    /// # Arguments
+    ///
+    /// * `&self` - Spec
+    pub fn meta_fields(&self) -> Result<Vec<MetaField>, Error> {
+        let required = self.parameterized.meta_required.iter()
+            .flatten()
+            .map(|name| (name, true));
+
+        let optional = self.parameterized.meta_optional.iter()
+            .flatten()
+            .map(|name| (name, false));
+
+        required.chain(optional)
+            .map(|(name, required)| self.meta_field(name, required))
+            .collect()
+    }
+
+    /// Keep the metas of a dispatched job which can be sent again to the job. A dispatched job inherit the meta
+    /// of its parent, which Nomad refuses in a dispatch as the keys are not parameterized. The secret metas are
+    /// redacted from the status of the dispatched jobs, so a job with a secret can't be dispatched again
+    ///
+    /// # Arguments
+    ///
+    /// * `&self` - Spec
+    /// * `metas` - &HashMap<String, String>
+    pub fn dispatch_metas(&self, metas: &HashMap<String, String>) -> Result<HashMap<String, String>, Error> {
+        let keys: HashSet<&String> = self.parameterized.meta_required.iter()
+            .chain(self.parameterized.meta_optional.iter())
+            .flatten()
+            .collect();
+        let secrets = secret_keys(&self.metas);
+
+        let metas: HashMap<String, String> = metas.iter()
+            .filter(|(key, _)| keys.contains(key))
+            .map(|(key, value)| (key.clone(), value.clone()))
+            .collect();
+
+        match metas.iter().find(|(key, value)| secrets.contains(*key) || value.as_str() == REDACTED) {
+            Some((key, _)) => Err(Error::InvalidMeta {
+                key: key.clone(),
+                reason: error::SECRET_REDISPATCH_REASON.to_string()
+            }),
+            None => Ok(metas)
+        }
+    }
+
+    /// Validate and normalize the metas provided by the user. Missing metas are filled with their default value
+    /// and optional metas without value are removed
+    ///
+    /// # Arguments
+    ///
+    /// * `&self` - Spec
+    /// * `metas` - HashMap<String, String>
+    pub fn resolve_metas(&self, mut metas: HashMap<String, String>) -> Result<HashMap<String, String>, Error> {
+        let fields = self.meta_fields()?;
+
+        if let Some(key) = metas.keys().find(|k| !fields.iter().any(|f| &f.name == *k)) {
+            return Err(Error::InvalidMeta {
+                key: key.clone(),
+                reason: error::UNKNOWN_META_REASON.to_string()
+            });
+        }
+
+        let mut resolved = HashMap::new();
+        for field in fields {
+            let value = metas.remove(&field.name)
+                .filter(|v| !v.is_empty())
+                .or_else(|| field.default.clone())
+                .unwrap_or_default();
+
+            let value = field.normalize(&value)?;
+            if !value.is_empty() {
+                resolved.insert(field.name, value);
+            }
+        }
+
+        Ok(resolved)
+    }
+
+    /// Build a meta field from the attributes defined in the meta of the job
+    ///
+    /// # Arguments
+    ///
+    /// * `&self` - Spec
+    /// * `name` - &str
+    /// * `required` - bool
+    fn meta_field(&self, name: &str, required: bool) -> Result<MetaField, Error> {
+        let attr = |attr: &str| self.metas
+            .get(&format!("{META_PREFIX}.{name}.{attr}"))
+            .cloned();
+
+        let pattern = match attr(PATTERN_ATTR) {
+            Some(pattern) => Some(Regex::new(&format!("^(?:{pattern})$")).map_err(|err| Error::InvalidMeta {
+                key: name.to_string(),
+                reason: format!("{} {pattern}: {err}", error::INVALID_PATTERN_REASON)
+            })?),
+            None => None
+        };
+
+        let kind = match attr(TYPE_ATTR) {
+            Some(kind) => kind.parse::<MetaType>().map_err(|reason| Error::InvalidMeta {
+                key: name.to_string(),
+                reason
+            })?,
+            None => MetaType::default()
+        };
+
+        let choices = attr(CHOICES_ATTR)
+            .map(|c| c.split(CHOICES_SEPARATOR).map(|s| s.trim().to_string()).collect())
+            .unwrap_or_default();
+
+        Ok(MetaField {
+            name: name.to_string(),
+            required,
+            kind,
+            description: attr(DESCRIPTION_ATTR),
+            default: attr(DEFAULT_ATTR),
+            pattern,
+            choices,
+            secret: attr(SECRET_ATTR).and_then(|s| MetaType::Bool.normalize(&s)).as_deref() == Some("true")
+        })
+    }
+}
+
+impl Task {
+    /// Return the image run by the task when the driver use one (docker, podman...)
+    ///
+    /// # Arguments
+    ///
+    /// * `&self` - Task
+    pub fn image(&self) -> Option<&str> {
+        self.config
+            .get(IMAGE_CONFIG)
+            .and_then(Value::as_str)
+    }
 }
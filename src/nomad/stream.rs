@@ -1,21 +1,192 @@
-use serde::Deserialize;
+use std::collections::VecDeque;
+use std::sync::Arc;
+use chrono::{DateTime, Utc};
+use futures::stream::{self, BoxStream, StreamExt};
+use serde::{Deserialize, Serialize};
+use tokio::time::Duration;
 use crate::error::Error;
 use crate::helper::Base64;
-use crate::log::Logger;
-use crate::rest::RestHandler;
+use super::api::NomadApi;
 
+// Constant
+const LOG_WAIT: u64 = 1000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
 pub enum StdKind {
     Stdout,
     Stderr
 }
 
-#[derive(Debug, Deserialize)]
-pub(crate) struct StreamLog {
-    #[serde(rename(deserialize = "Offset"))]
-    offset: Option<i64>,
+#[derive(Debug, Deserialize, Serialize, Default)]
+pub struct StreamLog {
+    #[serde(rename = "Offset")]
+    pub offset: Option<i64>,
+
+    #[serde(rename = "Data", skip_serializing_if = "Option::is_none")]
+    pub data: Option<String>
+}
+
+/// A line outputted by a task of an allocation. The timestamp is the time when the line has been received
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct LogEvent {
+    pub alloc: String,
+    pub task: String,
+    pub stream: StdKind,
+    pub ts: DateTime<Utc>,
+    pub line: String
+}
+
+/// Offset and incomplete line of a log stream
+#[derive(Default)]
+struct Cursor {
+    offset: i64,
+    prev_offset: i64,
+    partial: String
+}
+
+/// State of the stream returned by the follow method
+struct Follow {
+    api: Arc<dyn NomadApi>,
+    alloc_id: String,
+    task_name: String,
+    stdout: Cursor,
+    stderr: Cursor,
+    index: u64,
+    finished: bool,
+    done: bool,
+    pending: VecDeque<LogEvent>
+}
 
-    #[serde(rename(deserialize = "Data"))]
-    data: Option<String>
+impl std::fmt::Display for StdKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            StdKind::Stdout => write!(f, "stdout"),
+            StdKind::Stderr => write!(f, "stderr")
+        }
+    }
+}
+
+impl Follow {
+    /// Fetch the new logs of both stdout & stderr and queue the completed lines
+    ///
+    /// # Arguments
+    ///
+    /// * `&mut self` - Follow
+    async fn fetch_logs(&mut self) -> Result<(), Error> {
+        for std_kind in [StdKind::Stdout, StdKind::Stderr] {
+            let cursor = match std_kind {
+                StdKind::Stdout => &mut self.stdout,
+                StdKind::Stderr => &mut self.stderr
+            };
+
+            let content = fetch_log(self.api.as_ref(), &self.alloc_id, &self.task_name, std_kind, cursor).await?;
+            if let Some(content) = content {
+                cursor.partial.push_str(&content);
+            }
+
+            // only complete lines are emitted unless the task has finished
+            let mut lines: Vec<String> = cursor.partial.split('\n').map(String::from).collect();
+            cursor.partial = lines.pop().unwrap_or_default();
+            if self.finished && !cursor.partial.is_empty() {
+                lines.push(std::mem::take(&mut cursor.partial));
+            }
+
+            let ts = Utc::now();
+            let events = lines.into_iter()
+                .map(|line| LogEvent {
+                    alloc: self.alloc_id.clone(),
+                    task: self.task_name.clone(),
+                    stream: std_kind,
+                    ts,
+                    line
+                });
+
+            self.pending.extend(events);
+        }
+
+        Ok(())
+    }
+
+    /// Return the next log event. The allocation is watched with a blocking query which returns
+    /// as soon as the allocation change.
+    ///
+    /// # Arguments
+    ///
+    /// * `&mut self` - Follow
+    async fn next(&mut self) -> Option<Result<LogEvent, Error>> {
+        loop {
+            if let Some(event) = self.pending.pop_front() {
+                return Some(Ok(event));
+            }
+
+            if self.done {
+                return None;
+            }
+
+            if let Err(err) = self.fetch_logs().await {
+                self.done = true;
+                return Some(Err(err));
+            }
+
+            // Task has finish and the remaining logs has been fetched
+            if self.finished {
+                self.done = true;
+                continue;
+            }
+
+            // wait for the allocation to change to get the update status of the allocation
+            let res = self.api
+                .allocation(&self.alloc_id, self.index, Duration::from_millis(LOG_WAIT))
+                .await;
+
+            let (alloc, last_index) = match res {
+                Ok(r) => r,
+                Err(err) => {
+                    self.done = true;
+                    return Some(Err(err));
+                }
+            };
+
+            self.index = last_index;
+            // check whether the task has finish
+            let Some(task) = alloc.task_states.get(&self.task_name) else {
+                self.done = true;
+                return Some(Err(Error::MissingTask));
+            };
+            // Task has finish, fetch the logs one last time
+            self.finished = task.finished_at.is_some();
+        }
+    }
+}
+
+/// Follow the logs of a task by calling the nomad endpoint repetitively until the task finish to run.
+/// Each line outputted by the task is returned as a LogEvent
+///
+/// # Arguments
+///
+/// * `api` - Arc<dyn NomadApi>
+/// * `alloc_id` - &str
+/// * `task_name` - &str
+pub fn follow(api: Arc<dyn NomadApi>, alloc_id: &str, task_name: &str) -> BoxStream<'static, Result<LogEvent, Error>> {
+    let state = Follow {
+        api,
+        alloc_id: alloc_id.to_string(),
+        task_name: task_name.to_string(),
+        stdout: Cursor { prev_offset: -1, ..Default::default() },
+        stderr: Cursor { prev_offset: -1, ..Default::default() },
+        index: 0,
+        finished: false,
+        done: false,
+        pending: VecDeque::new()
+    };
+
+    stream::unfold(state, |mut state| async move {
+        state.next()
+            .await
+            .map(|event| (event, state))
+    })
+    .boxed()
 }
 
 /// Fetch the job log by using the nomad fs/logs endpoint.
@@ -26,45 +197,39 @@ pub(crate) struct StreamLog {
 ///
 /// # Arguments
 ///
-/// * `req` - &RestHandler
+/// * `api` - &dyn NomadApi
 /// * `id` - &str
 /// * `task_name` - &str
 /// * `std_kind` - StdKind
-/// * `offset` - i64
-pub async fn stream_dispatch_job_log(
-    req: &RestHandler,
+/// * `cursor` - &mut Cursor
+async fn fetch_log(
+    api: &dyn NomadApi,
     id: &str,
     task_name: &str,
     std_kind: StdKind,
-    offset: i64,
-    prev_offset: &mut i64
-) -> Result<i64, Error> {
-    let std_kind_str = match std_kind {
-        StdKind::Stdout => "stdout",
-        StdKind::Stderr => "stderr"
-    };
-
-    let endpoint = format!("v1/client/fs/logs/{id}?task={task_name}&type={std_kind_str}&offset={offset}");
+    cursor: &mut Cursor
+) -> Result<Option<String>, Error> {
     // /!\ If nomad returns nothing this could cause reqwest to thrown an error as it could not
     //     deserialize the result. As a result we skip the error altogether.
-    let Ok(res) = req.get::<StreamLog, _>(&endpoint).await else {
-        return Ok(0);
+    let Ok(res) = api.logs(id, task_name, &std_kind, cursor.offset).await else {
+        return Ok(None);
     };
 
     let res_offset = res.offset.unwrap_or(0);
-    if let Some(data) = res.data {
-        let content = String::from_base64(data)
-            .map_err(|err| Error::ScenarioErr(err.to_string()))?;
-
-        if prev_offset != &res_offset {
-            match std_kind {
-                StdKind::Stdout => Logger::notice(content),
-                StdKind::Stderr => Logger::error("", content)
-            }
+    cursor.offset = res_offset;
 
-            *prev_offset = res_offset;
-        }
+    let Some(data) = res.data else {
+        return Ok(None);
+    };
+
+    if cursor.prev_offset == res_offset {
+        return Ok(None);
     }
 
-    Ok(res_offset)
+    let content = String::from_base64(data)
+        .map_err(|err| Error::ScenarioErr(err.to_string()))?;
+
+    cursor.prev_offset = res_offset;
+
+    Ok(Some(content))
 }
@@ -0,0 +1,298 @@
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Mutex;
+use chrono::{DateTime, Utc};
+use futures::future::join_all;
+use serde::{Deserialize, Serialize};
+use tokio::time::Duration;
+use crate::error::Error;
+use crate::helper::null_as_default;
+use crate::schema::Schema;
+use super::api::NomadApi;
+use super::meta::redact_metas;
+use super::spec::secret_keys;
+
+// Constant
+pub(crate) const DISPATCH_SEPARATOR: &str = "/dispatch-";
+const DEAD_STATUS: &str = "dead";
+
+/// Namespace and id of a job
+type JobKey = (String, String);
+
+/// A job created by the dispatch of a parameterized job
+#[derive(Debug, Deserialize, Serialize, Clone, Default)]
+pub struct DispatchedJob {
+    #[serde(rename = "ID")]
+    pub id: String,
+
+    #[serde(rename = "ParentID", default)]
+    pub parent_id: String,
+
+    #[serde(rename = "Namespace", default)]
+    pub namespace: String,
+
+    #[serde(rename = "Status")]
+    pub status: String,
+
+    #[serde(rename = "SubmitTime", default)]
+    pub submit_time: i64,
+
+    #[serde(rename = "Meta", default, deserialize_with = "null_as_default")]
+    pub metas: HashMap<String, String>,
+
+    #[serde(rename = "JobSummary", default)]
+    pub summary: Option<JobSummary>
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, Default)]
+pub struct JobSummary {
+    #[serde(rename = "JobID")]
+    pub job_id: String,
+
+    #[serde(rename = "Summary", default, deserialize_with = "null_as_default")]
+    pub task_groups: HashMap<String, TaskGroupSummary>,
+
+    #[serde(rename = "Children", default)]
+    pub children: Option<ChildrenSummary>
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TaskGroupSummary {
+    #[serde(rename = "Queued", default)]
+    pub queued: u64,
+
+    #[serde(rename = "Starting", default)]
+    pub starting: u64,
+
+    #[serde(rename = "Running", default)]
+    pub running: u64,
+
+    #[serde(rename = "Complete", default)]
+    pub complete: u64,
+
+    #[serde(rename = "Failed", default)]
+    pub failed: u64,
+
+    #[serde(rename = "Lost", default)]
+    pub lost: u64
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ChildrenSummary {
+    #[serde(rename = "Pending", default)]
+    pub pending: u64,
+
+    #[serde(rename = "Running", default)]
+    pub running: u64,
+
+    #[serde(rename = "Dead", default)]
+    pub dead: u64
+}
+
+/// State of a dispatched job computed from its status and its summary
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "lowercase")]
+pub enum DispatchState {
+    Queued,
+    Running,
+    Complete,
+    Failed
+}
+
+/// Status of a dispatched job
+#[derive(Debug, Serialize, Clone)]
+pub struct DispatchStatus {
+    pub id: String,
+    pub state: DispatchState,
+    pub metas: HashMap<String, String>,
+    pub summary: TaskGroupSummary,
+    pub submitted_at: Option<DateTime<Utc>>,
+    pub finished_at: Option<DateTime<Utc>>
+}
+
+/// Time when the dead jobs have finished. A dead job does not change anymore, so the allocations of each
+/// dead job are listed once instead of on every refresh of the status
+#[derive(Debug, Default)]
+pub struct FinishedTimes {
+    times: Mutex<HashMap<JobKey, Option<DateTime<Utc>>>>
+}
+
+/// Status of the jobs dispatched from a parameterized job
+#[derive(Debug, Serialize, Clone)]
+pub struct ParentStatus {
+    pub job: String,
+    pub namespace: String,
+    pub children: ChildrenSummary,
+    pub dispatched: Vec<DispatchStatus>
+}
+
+impl DispatchedJob {
+    /// Sum the summary of every task groups of the job
+    ///
+    /// # Arguments
+    ///
+    /// * `&self` - DispatchedJob
+    pub fn total(&self) -> TaskGroupSummary {
+        self.summary
+            .as_ref()
+            .map(|s| s.task_groups.values().fold(TaskGroupSummary::default(), |acc, g| TaskGroupSummary {
+                queued: acc.queued + g.queued,
+                starting: acc.starting + g.starting,
+                running: acc.running + g.running,
+                complete: acc.complete + g.complete,
+                failed: acc.failed + g.failed,
+                lost: acc.lost + g.lost
+            }))
+            .unwrap_or_default()
+    }
+
+    /// Compute the state of the dispatched job
+    ///
+    /// # Arguments
+    ///
+    /// * `&self` - DispatchedJob
+    pub fn state(&self) -> DispatchState {
+        let total = self.total();
+
+        match self.status == DEAD_STATUS {
+            true if total.failed > 0 || total.lost > 0 || total.complete == 0 => DispatchState::Failed,
+            true => DispatchState::Complete,
+            false if total.running > 0 => DispatchState::Running,
+            false => DispatchState::Queued
+        }
+    }
+}
+
+impl DispatchStatus {
+    /// Time spent by the dispatched job since its submission. The duration stop growing once the job has finished
+    ///
+    /// # Arguments
+    ///
+    /// * `&self` - DispatchStatus
+    pub fn duration(&self) -> Option<chrono::Duration> {
+        self.submitted_at
+            .map(|start| self.finished_at.unwrap_or_else(Utc::now) - start)
+    }
+}
+
+impl std::fmt::Display for DispatchState {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DispatchState::Queued => write!(f, "queued"),
+            DispatchState::Running => write!(f, "running"),
+            DispatchState::Complete => write!(f, "complete"),
+            DispatchState::Failed => write!(f, "failed")
+        }
+    }
+}
+
+/// Convert a Nomad timestamp in nanoseconds into a date
+///
+/// # Arguments
+///
+/// * `nanos` - i64
+pub(crate) fn to_date(nanos: i64) -> Option<DateTime<Utc>> {
+    match nanos {
+        0 => None,
+        n => Some(DateTime::from_timestamp_nanos(n))
+    }
+}
+
+impl FinishedTimes {
+    /// Get the time when a dead job has finished by looking at the last modification of its allocations.
+    /// The allocations are only listed the first time the job is seen dead
+    ///
+    /// # Arguments
+    ///
+    /// * `&self` - FinishedTimes
+    /// * `api` - &dyn NomadApi
+    /// * `job` - &DispatchedJob
+    async fn get(&self, api: &dyn NomadApi, job: &DispatchedJob) -> Result<Option<DateTime<Utc>>, Error> {
+        if job.status != DEAD_STATUS {
+            return Ok(None);
+        }
+
+        let key = (job.namespace.clone(), job.id.clone());
+        let cached = self.times.lock().unwrap_or_else(|err| err.into_inner()).get(&key).copied();
+        if let Some(time) = cached {
+            return Ok(time);
+        }
+
+        let (allocs, _) = api.allocations(&job.namespace, &job.id, 0, Duration::ZERO).await?;
+        let last = allocs.iter()
+            .map(|a| a.modify_time)
+            .max()
+            .and_then(to_date);
+
+        self.times.lock().unwrap_or_else(|err| err.into_inner()).insert(key, last);
+        Ok(last)
+    }
+}
+
+/// Get the status of the jobs dispatched from the parameterized jobs. When a parent is provided,
+/// only the jobs dispatched from this parent are returned. A blocking query is used so that the status
+/// can be watched by passing the index returned by the previous call
+///
+/// # Arguments
+///
+/// * `api` - &dyn NomadApi
+/// * `parent` - Option<&str>
+/// * `schema` - Option<&Schema>
+/// * `finished` - &FinishedTimes
+/// * `index` - u64
+/// * `wait` - Duration
+pub async fn get_dispatch_status(
+    api: &dyn NomadApi,
+    parent: Option<&str>,
+    schema: Option<&Schema>,
+    finished: &FinishedTimes,
+    index: u64,
+    wait: Duration
+) -> Result<(Vec<ParentStatus>, u64), Error> {
+    let prefix = parent
+        .map(|p| format!("{p}{DISPATCH_SEPARATOR}"))
+        .unwrap_or_default();
+
+    let (jobs, last_index) = api.dispatched_jobs(&prefix, index, wait).await?;
+
+    // group the dispatched jobs by their parent
+    let mut parents: BTreeMap<(String, String), Vec<DispatchedJob>> = BTreeMap::new();
+    for job in jobs.into_iter().filter(|j| !j.parent_id.is_empty() && j.id.contains(DISPATCH_SEPARATOR)) {
+        parents.entry((job.namespace.clone(), job.parent_id.clone()))
+            .or_default()
+            .push(job);
+    }
+
+    let mut status = Vec::new();
+    for ((namespace, parent_id), mut jobs) in parents {
+        jobs.sort_by_key(|j| std::cmp::Reverse(j.submit_time));
+
+        let summary = api.job_summary(&namespace, &parent_id).await?;
+        let schema_secrets = schema
+            .and_then(|s| s.job(&parent_id))
+            .map(|s| s.secret_keys())
+            .unwrap_or_default();
+        let finished_times = join_all(jobs.iter().map(|j| finished.get(api, j))).await;
+
+        let mut dispatched = Vec::new();
+        for (job, finished_at) in jobs.into_iter().zip(finished_times) {
+            dispatched.push(DispatchStatus {
+                id: job.id.clone(),
+                state: job.state(),
+                summary: job.total(),
+                submitted_at: to_date(job.submit_time),
+                finished_at: finished_at?,
+                // the value of the secret metas, marked by the convention or by the schema, are never returned
+                metas: redact_metas(&job.metas, &secret_keys(&job.metas).union(&schema_secrets).cloned().collect())
+            });
+        }
+
+        status.push(ParentStatus {
+            job: parent_id,
+            namespace,
+            children: summary.children.unwrap_or_default(),
+            dispatched
+        });
+    }
+
+    Ok((status, last_index))
+}
@@ -0,0 +1,126 @@
+use std::collections::BTreeMap;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use crate::error::{Error, self};
+use crate::nomad::summary::to_date;
+
+/// Metadata of a Nomad variable as returned by the list endpoint. The items are not included
+#[derive(Debug, Deserialize, Serialize, Default, Clone)]
+pub struct VariableMetadata {
+    #[serde(rename = "Namespace", default)]
+    pub namespace: String,
+
+    #[serde(rename = "Path")]
+    pub path: String,
+
+    #[serde(rename = "CreateIndex", default)]
+    pub create_index: u64,
+
+    #[serde(rename = "ModifyIndex", default)]
+    pub modify_index: u64,
+
+    #[serde(rename = "ModifyTime", default)]
+    pub modify_time: i64
+}
+
+/// Nomad variable along with its items. The items are never shown by the Debug implementation
+#[derive(Deserialize, Serialize, Default, Clone)]
+pub struct Variable {
+    #[serde(rename = "Namespace", default)]
+    pub namespace: String,
+
+    #[serde(rename = "Path")]
+    pub path: String,
+
+    #[serde(rename = "Items", default)]
+    pub items: BTreeMap<String, String>,
+
+    #[serde(rename = "CreateIndex", default)]
+    pub create_index: u64,
+
+    #[serde(rename = "ModifyIndex", default)]
+    pub modify_index: u64,
+
+    #[serde(rename = "ModifyTime", default)]
+    pub modify_time: i64
+}
+
+/// Parse the items of a variable written as a json object. Nomad only stores strings, other scalar
+/// values are converted to their json representation
+///
+/// # Arguments
+///
+/// * `content` - &str
+pub fn parse_items(content: &str) -> Result<BTreeMap<String, String>, Error> {
+    let object: Map<String, Value> = serde_json::from_str(content)
+        .map_err(|err| Error::Variable(format!("{}: {err}", error::VAR_ITEMS_ERR)))?;
+
+    object.into_iter()
+        .map(|(key, value)| match value {
+            Value::String(value) => Ok((key, value)),
+            Value::Object(_) | Value::Array(_) | Value::Null => Err(Error::Variable(format!("{}: {key}", error::VAR_ITEMS_ERR))),
+            value => Ok((key, value.to_string()))
+        })
+        .collect()
+}
+
+impl VariableMetadata {
+    /// Return the date of the last modification of the variable
+    ///
+    /// # Arguments
+    ///
+    /// * `&self` - VariableMetadata
+    pub fn modified_at(&self) -> Option<DateTime<Utc>> {
+        to_date(self.modify_time)
+    }
+}
+
+impl Variable {
+    /// Create a new variable which has not been written yet
+    ///
+    /// # Arguments
+    ///
+    /// * `namespace` - &str
+    /// * `path` - &str
+    /// * `items` - BTreeMap<String, String>
+    pub fn new(namespace: &str, path: &str, items: BTreeMap<String, String>) -> Self {
+        Variable {
+            namespace: namespace.to_string(),
+            path: path.to_string(),
+            items,
+            ..Default::default()
+        }
+    }
+
+    /// Return the value of an item of the variable. The field can be omitted when the variable has a single item
+    ///
+    /// # Arguments
+    ///
+    /// * `&self` - Variable
+    /// * `field` - Option<&str>
+    pub fn item(&self, field: Option<&str>) -> Result<&String, Error> {
+        let invalid = |reason: String| Error::Variable(format!("{}: {reason}", self.path));
+
+        match field {
+            Some(field) => self.items
+                .get(field)
+                .ok_or_else(|| invalid(format!("{} {field}", error::VAR_FIELD_ERR))),
+            None => match self.items.len() {
+                1 => self.items.values().next().ok_or_else(|| invalid(error::VAR_AMBIGUOUS_ERR.to_string())),
+                _ => Err(invalid(error::VAR_AMBIGUOUS_ERR.to_string()))
+            }
+        }
+    }
+}
+
+impl std::fmt::Debug for Variable {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Variable")
+            .field("namespace", &self.namespace)
+            .field("path", &self.path)
+            .field("items", &self.items.keys().collect::<Vec<_>>())
+            .field("modify_index", &self.modify_index)
+            .finish()
+    }
+}
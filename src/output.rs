@@ -0,0 +1,502 @@
+use std::collections::{BTreeMap, HashMap};
+use std::io::stdout;
+use std::path::Path;
+use chrono::Local;
+use clap::ValueEnum;
+use crossterm::cursor::MoveTo;
+use crossterm::execute;
+use crossterm::style::Stylize;
+use crossterm::terminal::{Clear, ClearType};
+use serde::Serialize;
+use noumead::Error;
+use noumead::nomad::dispatch::DispatchRes;
+use noumead::nomad::acl::AclToken;
+use noumead::nomad::job::JobStatus;
+use noumead::nomad::meta::REDACTED;
+use noumead::nomad::spec::Spec;
+use noumead::nomad::stream::{LogEvent, StdKind};
+use noumead::nomad::summary::{DispatchState, DispatchStatus, ParentStatus};
+use noumead::nomad::variable::{Variable, VariableMetadata};
+use noumead::schema::Drift;
+use crate::log::Logger;
+
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum OutputFormat {
+    #[default]
+    Text,
+    Json,
+    Yaml
+}
+
+/// Item which can be rendered by the formatter
+pub trait Output: Serialize {
+    /// Render the item as a human readable text
+    ///
+    /// # Arguments
+    ///
+    /// * `&self` - Self
+    fn text(&self);
+}
+
+/// Render the output of the commands in the format selected by the user. Json items are
+/// written on a single line so that a sequence of item is a valid NDJSON stream
+#[derive(Debug, Clone, Copy)]
+pub struct Formatter {
+    format: OutputFormat
+}
+
+/// Result of a dispatch
+#[derive(Debug, Serialize)]
+pub struct DispatchOutput<'a> {
+    pub job: &'a str,
+    pub namespace: &'a str,
+    pub metas: &'a HashMap<String, String>,
+    /// The job has already been dispatched with the same idempotency token
+    pub existing: bool,
+    #[serde(flatten)]
+    pub res: &'a DispatchRes
+}
+
+/// Request which would be sent by a dispatch
+#[derive(Debug, Serialize)]
+pub struct DryRunOutput {
+    pub method: &'static str,
+    pub url: String,
+    pub job: String,
+    pub namespace: String,
+    pub metas: HashMap<String, String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub idempotency_token: Option<String>,
+    /// Decoded payload truncated to a readable size
+    pub payload: String,
+    pub payload_size: usize,
+    pub nomad: String,
+    pub curl: String
+}
+
+/// Summary of a batch dispatch
+#[derive(Debug, Serialize)]
+pub struct BatchOutput<'a> {
+    pub job: &'a str,
+    pub results: &'a Path,
+    pub dispatched: usize,
+    pub failed: usize,
+    pub skipped: usize
+}
+
+/// Error which stopped a command, written on stderr with the machine readable formats
+#[derive(Debug, Serialize)]
+pub struct ErrorOutput {
+    pub error: String
+}
+
+/// Result of the stop of a job
+#[derive(Debug, Serialize)]
+pub struct StopOutput<'a> {
+    pub job: &'a str,
+    pub namespace: &'a str,
+    pub stopped: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>
+}
+
+/// Variable along with its items. The values of the items are masked unless they are revealed
+#[derive(Debug, Serialize)]
+pub struct VariableOutput<'a> {
+    pub namespace: &'a str,
+    pub path: &'a str,
+    pub items: BTreeMap<&'a str, &'a str>,
+    pub modify_index: u64
+}
+
+/// Result of the deletion of a variable
+#[derive(Debug, Serialize)]
+pub struct VariableDeleteOutput<'a> {
+    pub namespace: &'a str,
+    pub path: &'a str,
+    pub deleted: bool
+}
+
+impl Formatter {
+    /// Create a new formatter
+    ///
+    /// # Arguments
+    ///
+    /// * `format` - OutputFormat
+    pub fn new(format: OutputFormat) -> Self {
+        Formatter { format }
+    }
+
+    /// Render an item
+    ///
+    /// # Arguments
+    ///
+    /// * `&self` - Formatter
+    /// * `item` - &T
+    pub fn emit<T: Output>(&self, item: &T) -> Result<(), Error> {
+        match self.format {
+            OutputFormat::Text => item.text(),
+            OutputFormat::Json => {
+                let res = serde_json::to_string(item)
+                    .map_err(|err| Error::Serialize(err.to_string()))?;
+
+                println!("{res}");
+            },
+            OutputFormat::Yaml => {
+                let res = serde_yaml::to_string(item)
+                    .map_err(|err| Error::Serialize(err.to_string()))?;
+
+                print!("---\n{res}");
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Show an informational message. Messages are only shown with the text format
+    /// in order to keep the machine readable output parsable
+    ///
+    /// # Arguments
+    ///
+    /// * `&self` - Formatter
+    /// * `msg` - &str
+    pub fn info(&self, msg: &str) {
+        if self.format == OutputFormat::Text {
+            Logger::info(msg);
+        }
+    }
+
+    /// Show a warning. Warnings are shown with every format as they are written on stderr
+    ///
+    /// # Arguments
+    ///
+    /// * `&self` - Formatter
+    /// * `msg` - &str
+    pub fn warn(&self, msg: &str) {
+        Logger::warn(msg);
+    }
+
+    /// Show the error which stopped a command. With the machine readable formats, the error is written on stderr
+    /// as an object so that the output on stdout stays parsable
+    ///
+    /// # Arguments
+    ///
+    /// * `&self` - Formatter
+    /// * `msg` - &str
+    /// * `err` - T
+    pub fn error<T: ToString>(&self, msg: &str, err: T) {
+        let output = ErrorOutput { error: err.to_string() };
+
+        match self.format {
+            OutputFormat::Text => Logger::error(msg, output.error),
+            OutputFormat::Json => eprintln!("{}", serde_json::to_string(&output).unwrap_or_default()),
+            OutputFormat::Yaml => eprint!("---\n{}", serde_yaml::to_string(&output).unwrap_or_default())
+        }
+    }
+
+    /// Clear the terminal before rendering a new version of the output. The terminal is only
+    /// cleared with the text format, other formats append a new document instead
+    ///
+    /// # Arguments
+    ///
+    /// * `&self` - Formatter
+    pub fn clear(&self) {
+        if self.format == OutputFormat::Text {
+            let _ = execute!(stdout(), Clear(ClearType::All), MoveTo(0, 0));
+        }
+    }
+}
+
+impl Output for DispatchOutput<'_> {
+    fn text(&self) {
+        match self.existing {
+            true => Logger::info(&format!("Job already dispatched as: {}", self.res.dispatch_id)),
+            false => Logger::info(&format!(
+                "Job with id: {} has been dispatched with the evaluation: {}",
+                self.res.dispatch_id,
+                self.res.eval_id
+            ))
+        }
+    }
+}
+
+impl Output for DryRunOutput {
+    fn text(&self) {
+        println!("{} {}", self.method.bold(), self.url);
+        println!("  job: {} ({})", self.job, self.namespace);
+        println!("  metas: {}", format_metas(&self.metas));
+        if let Some(token) = &self.idempotency_token {
+            println!("  idempotency token: {token}");
+        }
+        println!("  payload ({} bytes):", self.payload_size);
+        for line in self.payload.lines() {
+            println!("    {line}");
+        }
+
+        println!("{}", "nomad".bold());
+        println!("  {}", self.nomad);
+        println!("{}", "curl".bold());
+        println!("  {}", self.curl);
+    }
+}
+
+impl Output for BatchOutput<'_> {
+    fn text(&self) {
+        let summary = format!(
+            "{} rows of {} dispatched, {} failed, {} skipped. Results written in {}",
+            self.dispatched,
+            self.job,
+            self.failed,
+            self.skipped,
+            self.results.display()
+        );
+
+        match self.failed {
+            0 => Logger::info(&summary),
+            _ => Logger::error("Batch completed with failures:", summary)
+        }
+    }
+}
+
+impl<'a> VariableOutput<'a> {
+    /// Create the output of a variable
+    ///
+    /// # Arguments
+    ///
+    /// * `variable` - &Variable
+    /// * `reveal` - bool
+    pub fn new(variable: &'a Variable, reveal: bool) -> Self {
+        let items = variable.items.iter()
+            .map(|(key, value)| match reveal {
+                true => (key.as_str(), value.as_str()),
+                false => (key.as_str(), REDACTED)
+            })
+            .collect();
+
+        VariableOutput {
+            namespace: &variable.namespace,
+            path: &variable.path,
+            items,
+            modify_index: variable.modify_index
+        }
+    }
+}
+
+impl Output for VariableOutput<'_> {
+    fn text(&self) {
+        println!("{} ({}) index: {}", self.path.bold(), self.namespace, self.modify_index);
+        for (key, value) in &self.items {
+            println!("  {key}: {value}");
+        }
+    }
+}
+
+impl Output for VariableDeleteOutput<'_> {
+    fn text(&self) {
+        Logger::info(&format!("The variable {} has been deleted", self.path));
+    }
+}
+
+impl Output for Vec<VariableMetadata> {
+    fn text(&self) {
+        if self.is_empty() {
+            Logger::info("No variable found");
+        }
+
+        for variable in self {
+            let modified = variable.modified_at()
+                .map(|d| d.with_timezone(&Local).format("%Y-%m-%d %H:%M:%S").to_string())
+                .unwrap_or_else(|| "-".to_string());
+
+            println!(
+                "{} ({}) modified: {} index: {}",
+                variable.path.as_str().bold(),
+                variable.namespace,
+                modified,
+                variable.modify_index
+            );
+        }
+    }
+}
+
+impl Output for AclToken {
+    fn text(&self) {
+        let or_dash = |values: Vec<&str>| match values.is_empty() {
+            true => "-".to_string(),
+            false => values.join(", ")
+        };
+
+        let expiry = self.expiration_time
+            .map(|d| d.with_timezone(&Local).format("%Y-%m-%d %H:%M:%S").to_string())
+            .unwrap_or_else(|| "never".to_string());
+
+        println!("{} ({})", self.name.as_str().bold(), self.kind);
+        println!("  accessor: {}", self.accessor_id);
+        println!("  policies: {}", or_dash(self.policies.iter().map(String::as_str).collect()));
+        println!("  roles: {}", or_dash(self.roles.iter().map(|r| r.name.as_str()).collect()));
+        println!("  global: {}", self.global);
+        println!("  expires: {expiry}");
+    }
+}
+
+impl Output for Vec<StopOutput<'_>> {
+    fn text(&self) {
+        for output in self {
+            match &output.error {
+                Some(err) => Logger::error(&format!("Unable to delete the job {} due to", output.job), err),
+                None => Logger::info(&format!("The job {} has been deleted", output.job))
+            }
+        }
+    }
+}
+
+impl Output for LogEvent {
+    fn text(&self) {
+        match self.stream {
+            StdKind::Stdout => Logger::notice(&self.line),
+            StdKind::Stderr => Logger::error("", &self.line)
+        }
+    }
+}
+
+impl Output for JobStatus {
+    fn text(&self) {
+        Logger::info(&format!("Job {} has finished with the status: {}", self.id, self.status));
+    }
+}
+
+impl Output for Vec<ParentStatus> {
+    fn text(&self) {
+        if self.is_empty() {
+            Logger::info("No dispatched job found");
+        }
+
+        for parent in self {
+            println!(
+                "{} ({}) pending: {} running: {} dead: {}",
+                parent.job.as_str().bold(),
+                parent.namespace,
+                parent.children.pending,
+                parent.children.running,
+                parent.children.dead
+            );
+
+            for job in &parent.dispatched {
+                println!("  {}", render_dispatch_status(job));
+            }
+        }
+    }
+}
+
+impl Output for Spec {
+    fn text(&self) {
+        let or_dash = |value: String| match value.is_empty() {
+            true => "-".to_string(),
+            false => value
+        };
+
+        println!("{} ({}) {}", self.id.as_str().bold(), self.namespace, self.kind);
+        println!("  datacenters: {}", or_dash(self.datacenters.join(", ")));
+        println!("  payload: {}", or_dash(self.parameterized.payload.clone()));
+        println!("  meta required: {}", or_dash(self.parameterized.meta_required.clone().unwrap_or_default().join(", ")));
+        println!("  meta optional: {}", or_dash(self.parameterized.meta_optional.clone().unwrap_or_default().join(", ")));
+        println!("  meta defaults: {}", or_dash(format_metas(&self.metas)));
+
+        for group in &self.task_groups {
+            println!("{} (count: {})", format!("group {}", group.name).bold(), group.count);
+            if !group.metas.is_empty() {
+                println!("  meta defaults: {}", format_metas(&group.metas));
+            }
+
+            for task in &group.tasks {
+                println!("  task {} driver: {} image: {}", task.name.as_str().blue(), task.driver, task.image().unwrap_or("-"));
+                if let Some(payload) = &task.dispatch_payload {
+                    println!("    payload file: {}", payload.file);
+                }
+
+                if !task.metas.is_empty() {
+                    println!("    meta defaults: {}", format_metas(&task.metas));
+                }
+            }
+        }
+    }
+}
+
+impl Output for Vec<Drift> {
+    fn text(&self) {
+        if self.is_empty() {
+            Logger::info("The schema match the jobs");
+        }
+
+        for drift in self {
+            match drift {
+                Drift::JobNotFound { job } => Logger::error("The job of the schema does not exist", job),
+                Drift::MissingKey { job, key } => Logger::error(&format!("The required meta of {job} is not described"), key),
+                Drift::RemovedKey { job, key } => Logger::error(&format!("The described meta is not accepted by {job}"), key)
+            }
+        }
+    }
+}
+
+/// Render a dispatched job on a single line
+///
+/// # Arguments
+///
+/// * `job` - &DispatchStatus
+fn render_dispatch_status(job: &DispatchStatus) -> String {
+    let state = format!("{:<8}", job.state.to_string());
+    let state = match job.state {
+        DispatchState::Queued => state.yellow(),
+        DispatchState::Running => state.blue(),
+        DispatchState::Complete => state.green(),
+        DispatchState::Failed => state.red()
+    };
+
+    let submitted = job.submitted_at
+        .map(|d| d.with_timezone(&Local).format("%Y-%m-%d %H:%M:%S").to_string())
+        .unwrap_or_else(|| "-".to_string());
+
+    let duration = format_duration(job);
+    let metas = format_metas(&job.metas);
+
+    format!(
+        "{} {} {} {:>10} queued: {} running: {} complete: {} failed: {} {}",
+        job.id,
+        state,
+        submitted,
+        duration,
+        job.summary.queued,
+        job.summary.running,
+        job.summary.complete,
+        job.summary.failed,
+        metas
+    )
+}
+
+/// Render the duration of a dispatched job
+///
+/// # Arguments
+///
+/// * `job` - &DispatchStatus
+pub fn format_duration(job: &DispatchStatus) -> String {
+    job.duration()
+        .map(|d| {
+            let secs = d.num_seconds().max(0);
+            format!("{}h{:02}m{:02}s", secs / 3600, secs % 3600 / 60, secs % 60)
+        })
+        .unwrap_or_else(|| "-".to_string())
+}
+
+/// Render the metas as a sorted list of key=value
+///
+/// # Arguments
+///
+/// * `metas` - &HashMap<String, String>
+pub fn format_metas(metas: &HashMap<String, String>) -> String {
+    let mut metas: Vec<String> = metas.iter()
+        .map(|(k, v)| format!("{k}={v}"))
+        .collect();
+    metas.sort();
+
+    metas.join(" ")
+}
@@ -1,39 +1,178 @@
-use reqwest::{Client, RequestBuilder};
+use std::path::PathBuf;
+use std::sync::Arc;
+use reqwest::{Client, Method, NoProxy, Proxy, Request, RequestBuilder, StatusCode, Url};
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
 use tokio::time::{sleep, Duration};
 use serde::{de::DeserializeOwned, Serialize};
+use serde_json::Value;
 use crate::error::{Error, self};
+use crate::nomad::meta::{SecretKeys, REDACTED};
+use crate::nomad::spec;
 
 // Constant
 const RETRY_LINEAR_SLEEP: u64 = 1000;
 const MAX_RETRY: usize = 8;
+const TOKEN_HEADER: &str = "X-Nomad-Token";
+const INDEX_HEADER: &str = "X-Nomad-Index";
+const PAYLOAD_FIELD: &str = "Payload";
+const META_FIELD: &str = "Meta";
+/// Fields of the bodies which hold a secret: the items of the variables and the tokens of the auth methods
+const SECRET_FIELDS: [&str; 4] = ["Items", "SecretID", "LoginToken", "Code"];
+
+const UNIX_SCHEME: &str = "unix://";
+/// Host of the urls sent through a unix socket, only the path of the url is used
+const UNIX_BASE_URL: &str = "http://localhost/";
 
 #[derive(Debug, Default)]
 pub struct RestHandler {
-    base_url: String,
+    base_url: Option<Url>,
     token: Option<String>,
     client: Option<Client>,
+    /// Client sending the requests through the unix socket of the Nomad agent, if any
+    #[cfg(unix)]
+    unix: Option<(hyper::Client<hyperlocal::UnixConnector>, PathBuf)>,
+    /// Headers added to every request
+    headers: HeaderMap,
+    request_timeout: Option<Duration>,
+    log_poll_timeout: Option<Duration>,
+    debug: Option<DebugHook>,
+    secrets: SecretKeys
+}
+
+/// Request or response exchanged with Nomad, with the token and the secrets redacted
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DebugRecord {
+    /// Method and url of a request, e.g. `>>> GET http://nomad:4646/v1/jobs`, or status and endpoint of a response
+    pub title: String,
+    /// Headers and body of the request or the response
+    pub details: String
+}
+
+/// Callback receiving the requests and the responses exchanged with Nomad, e.g. to print them
+#[derive(Clone)]
+pub struct DebugHook(Arc<dyn Fn(DebugRecord) + Send + Sync>);
+
+impl DebugHook {
+    /// Create a hook from a callback
+    ///
+    /// # Arguments
+    ///
+    /// * `hook` - F
+    pub fn new<F: Fn(DebugRecord) + Send + Sync + 'static>(hook: F) -> Self {
+        DebugHook(Arc::new(hook))
+    }
+}
+
+impl std::fmt::Debug for DebugHook {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "DebugHook")
+    }
+}
+
+/// Options of the connection to Nomad
+#[derive(Debug, Default, Clone)]
+pub struct RestOptions {
+    /// Proxy used to reach Nomad, e.g. `http://proxy:3128` or `socks5://localhost:1080`. By default the proxy
+    /// is read from the `HTTP_PROXY`, `HTTPS_PROXY` and `ALL_PROXY` environment variables
+    pub proxy: Option<String>,
+
+    /// Headers added to every request, e.g. the key expected by an auth gateway in front of Nomad
+    pub headers: Vec<(String, String)>,
+
+    /// Maximum duration to establish a connection with Nomad
+    pub connect_timeout: Option<Duration>,
+
+    /// Maximum duration of a request. Blocking queries are given their wait on top of it
+    pub request_timeout: Option<Duration>,
+
+    /// Maximum duration of each poll reading the logs of a task, used instead of the request timeout
+    pub log_poll_timeout: Option<Duration>,
+
+    /// Receive the requests and the responses exchanged with Nomad
+    pub debug: Option<DebugHook>,
+
+    /// Metas whose value is redacted from the requests and the responses given to the debug hook
+    pub secrets: SecretKeys
+}
+
+/// Raw response returned by Nomad
+struct RawResponse {
+    body: String,
+    index: u64
+}
+
+/// Response of Nomad before its status is checked
+struct HttpResponse {
+    status: StatusCode,
+    headers: HeaderMap,
+    body: String
 }
 
 impl RestHandler {
-    /// Create a new connection options
+    /// Create a new connection options. The address is either the url of Nomad, which may have a path prefix
+    /// when Nomad is behind a reverse proxy, or the unix socket of the agent such as `unix:///var/run/nomad.sock`
     ///
     /// # Arguments
     ///
-    /// * `base_url` - String
+    /// * `base_url` - Option<String>
     /// * `token` - Option<String>
-    pub fn new(base_url: Option<String>, token: Option<String>) -> Result<RestHandler, Error> {
-        let Some(url) = base_url else {
+    /// * `options` - RestOptions
+    pub fn new(base_url: Option<String>, token: Option<String>, options: RestOptions) -> Result<RestHandler, Error> {
+        let Some(address) = base_url else {
             return Err(Error::MissingEnv(error::MISSING_BASE_URL_ERR.to_string()))
         };
 
-        let client = Client::builder()
-            .danger_accept_invalid_certs(true)
-            .build()?;
+        let socket = address.strip_prefix(UNIX_SCHEME).map(PathBuf::from);
+        let base_url = match socket {
+            Some(_) => Url::parse(UNIX_BASE_URL),
+            None => Url::parse(&address)
+        };
+
+        let mut base_url = base_url
+            .map_err(|err| Error::NomadReqErr(format!("{} {address}: {err}", error::INVALID_ADDRESS_ERR)))?;
+        if !matches!(base_url.scheme(), "http" | "https") {
+            return Err(Error::NomadReqErr(format!("{} {address}", error::INVALID_ADDRESS_ERR)));
+        }
+
+        // the endpoints are joined to the address, which would replace the last segment of the path
+        // of the address without the trailing slash
+        if !base_url.path().ends_with('/') {
+            base_url.set_path(&format!("{}/", base_url.path()));
+        }
+
+        let mut headers = HeaderMap::new();
+        for (name, value) in &options.headers {
+            let to_err = || Error::NomadReqErr(format!("{} {name}", error::INVALID_HEADER_ERR));
+            let name = HeaderName::from_bytes(name.as_bytes()).map_err(|_| to_err())?;
+            let value = HeaderValue::from_str(value).map_err(|_| to_err())?;
+            headers.append(name, value);
+        }
+
+        let mut builder = Client::builder().danger_accept_invalid_certs(true);
+        if let Some(timeout) = options.connect_timeout {
+            builder = builder.connect_timeout(timeout);
+        }
+        if let Some(proxy) = options.proxy.as_deref() {
+            let proxy = Proxy::all(proxy)?.no_proxy(NoProxy::from_env());
+            builder = builder.proxy(proxy);
+        }
+
+        #[cfg(not(unix))]
+        if socket.is_some() {
+            return Err(Error::NomadReqErr(format!("{} {address}", error::UNIX_SOCKET_ERR)));
+        }
 
         Ok(RestHandler {
-            base_url: url,
+            base_url: Some(base_url),
             token,
-            client: Some(client)
+            client: Some(builder.build()?),
+            #[cfg(unix)]
+            unix: socket.map(|path| (hyper::Client::builder().build(hyperlocal::UnixConnector), path)),
+            headers,
+            request_timeout: options.request_timeout,
+            log_poll_timeout: options.log_poll_timeout,
+            debug: options.debug,
+            secrets: options.secrets
         })
     }
 
@@ -48,48 +187,101 @@ impl RestHandler {
             T: DeserializeOwned,
             S: AsRef<str> + std::fmt::Display
     {
-        let Some(client) = self.client.as_ref() else {
-            return Err(Error::NomadReqErr(error::REQ_BUILD_ERR.to_string()))
-        };
-
-        let url = format!("{}/{}", self.base_url, endpoint);
+        let req = self.request(Method::GET, endpoint, self.request_timeout)?;
+        let res = retry(self, req, MAX_RETRY).await?;
 
-        let mut req = client.get(url);
-        if let Some(token) = self.token.as_ref() {
-            req = req.header("X-Nomad-Token", token);
-        }
+        Ok(res)
+    }
 
-        let res = retry(req, MAX_RETRY).await?;
+    /// Prepare and send a get request reading the logs of a task. The logs are polled while they are followed,
+    /// so each poll is bounded by its own timeout rather than by the request timeout
+    ///
+    /// # Arguments
+    ///
+    /// * `&self` - RestHandler
+    /// * `endpoint` - S
+    pub async fn get_logs<T, S>(&self, endpoint: S) -> Result<T, Error>
+        where
+            T: DeserializeOwned,
+            S: AsRef<str> + std::fmt::Display
+    {
+        let req = self.request(Method::GET, endpoint, self.log_poll_timeout)?;
+        let res = retry(self, req, MAX_RETRY).await?;
 
         Ok(res)
     }
 
-    /// Send a delete request to the targeted endpoint
+    /// Send a blocking query to the nomad api. Nomad holds the request until the data behind the endpoint
+    /// change past the provided index or until the wait duration expire. The index of the response
+    /// is returned alongside the body so that it can be used for the next blocking query.
+    /// Passing an index of 0 returns the data immediately.
     ///
     /// # Arguments
     ///
     /// * `&self` - RestHandler
     /// * `endpoint` - S
-    pub async fn delete<S>(&self, endpoint: S) -> Result<(), Error>
+    /// * `index` - u64
+    /// * `wait` - Duration
+    pub async fn get_blocking<T, S>(&self, endpoint: S, index: u64, wait: Duration) -> Result<(T, u64), Error>
         where
+            T: DeserializeOwned,
             S: AsRef<str> + std::fmt::Display
     {
-        let Some(client) = self.client.as_ref() else {
-            return Err(Error::NomadReqErr(error::REQ_BUILD_ERR.to_string()))
+        let separator = match endpoint.as_ref().contains('?') {
+            true => '&',
+            false => '?'
         };
 
-        let url = format!("{}/{}", self.base_url, endpoint);
+        let endpoint = format!("{endpoint}{separator}index={index}&wait={}ms", wait.as_millis());
+        // Nomad holds the query for the wait duration, which must not be counted as the request being stuck
+        let req = self.request(Method::GET, endpoint, self.request_timeout.map(|timeout| timeout + wait))?;
 
-        let mut req = client.delete(url);
-        if let Some(token) = self.token.as_ref() {
-            req = req.header("X-Nomad-Token", token);
-        }
+        let res = self.send(req).await?;
+        let output = serde_json::from_str::<T>(&res.body)
+            .map_err(|err| Error::Serialize(err.to_string()))?;
 
-        req.send().await?;
+        Ok((output, res.index))
+    }
+
+    /// Send a delete request to the targeted endpoint
+    ///
+    /// # Arguments
+    ///
+    /// * `&self` - RestHandler
+    /// * `endpoint` - S
+    pub async fn delete<S>(&self, endpoint: S) -> Result<(), Error>
+        where
+            S: AsRef<str> + std::fmt::Display
+    {
+        let req = self.request(Method::DELETE, endpoint, self.request_timeout)?;
+        self.send(req).await?;
 
         Ok(())
     }
 
+    /// Prepare and send a put request to the nomad api
+    ///
+    /// # Arguments
+    ///
+    /// * `&self` - RestHandler
+    /// * `endpoint` - S
+    /// * `payload` - T
+    pub async fn put<T, O, S>(&self, endpoint: S, payload: T) -> Result<O, Error>
+        where
+            T: Serialize,
+            O: DeserializeOwned,
+            S: AsRef<str> + std::fmt::Display
+    {
+        let req = self.request(Method::PUT, endpoint, self.request_timeout)?
+            .json(&payload);
+
+        let res = self.send(req).await?;
+        let output = serde_json::from_str::<O>(&res.body)
+            .map_err(|err| Error::Serialize(err.to_string()))?;
+
+        Ok(output)
+    }
+
     /// Prepare and send a post request to the nomad api
     ///
     /// # Arguments
@@ -103,69 +295,317 @@ impl RestHandler {
             O: DeserializeOwned,
             S: AsRef<str> + std::fmt::Display
     {
+        let req = self.request(Method::POST, endpoint, self.request_timeout)?
+            .json(&payload);
+
+        let res = self.send(req).await?;
+        let output = serde_json::from_str::<O>(&res.body)
+            .map_err(|err| Error::Serialize(err.to_string()))?;
+
+        Ok(output)
+    }
+
+    /// Create a request builder for the targeted endpoint with the token of the user and the custom headers
+    ///
+    /// # Arguments
+    ///
+    /// * `&self` - RestHandler
+    /// * `method` - Method
+    /// * `endpoint` - S
+    /// * `timeout` - Option<Duration>
+    fn request<S: std::fmt::Display>(&self, method: Method, endpoint: S, timeout: Option<Duration>) -> Result<RequestBuilder, Error> {
         let Some(client) = self.client.as_ref() else {
             return Err(Error::NomadReqErr(error::REQ_BUILD_ERR.to_string()))
         };
 
-        let url = format!("{}/{}", self.base_url, endpoint);
+        let Some(base_url) = self.base_url.as_ref() else {
+            return Err(Error::NomadReqErr(error::REQ_BUILD_ERR.to_string()))
+        };
+
+        let endpoint = endpoint.to_string();
+        let url = base_url.join(endpoint.trim_start_matches('/'))
+            .map_err(|err| Error::NomadReqErr(format!("{endpoint}: {err}")))?;
 
-        let mut req = client.post(url);
+        let mut req = client.request(method, url).headers(self.headers.clone());
         if let Some(token) = self.token.as_ref() {
-            req = req.header("X-Nomad-Token", token);
+            req = req.header(TOKEN_HEADER, token);
+        }
+
+        if let Some(timeout) = timeout {
+            req = req.timeout(timeout);
+        }
+
+        Ok(req)
+    }
+
+    /// Send a request and return the body of the response with its index. Any response which does not have
+    /// a success status code is returned as an Error::Api with the body returned by Nomad
+    ///
+    /// # Arguments
+    ///
+    /// * `&self` - RestHandler
+    /// * `req` - RequestBuilder
+    async fn send(&self, req: RequestBuilder) -> Result<RawResponse, Error> {
+        let req = req.build()?;
+        if let Some(DebugHook(hook)) = &self.debug {
+            hook(dump_request(&req, &self.headers, &self.secrets));
         }
 
-        let res = req
-            .json(&payload)
-            .send()
-            .await?;
+        let method = req.method().to_string();
+        let endpoint = match req.url().query() {
+            Some(query) => format!("{}?{}", req.url().path(), query),
+            None => req.url().path().to_string()
+        };
+
+        let HttpResponse { status, headers, body } = self.execute(req).await?;
+        let index = headers
+            .get(INDEX_HEADER)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.parse::<u64>().ok())
+            .unwrap_or_default();
+        let headers = format!("{headers:?}");
 
-        if res.status() != 200 {
-            return Err(Error::Dispatch);
+        if let Some(DebugHook(hook)) = &self.debug {
+            hook(DebugRecord {
+                title: format!("<<< {status} {endpoint}"),
+                details: format!("headers: {headers}\n{}", redact_body(body.trim(), &self.secrets))
+            });
         }
 
-        let output = res.json::<O>().await?;
+        if !status.is_success() {
+            return Err(Error::Api {
+                status: status.as_u16(),
+                method,
+                endpoint,
+                // a check-and-set conflict returns the current variable
+                body: redact_body(&body, &self.secrets)
+            });
+        }
 
-        Ok(output)
+        Ok(RawResponse { body, index })
+    }
+
+    /// Execute a request over http or through the unix socket of the Nomad agent
+    ///
+    /// # Arguments
+    ///
+    /// * `&self` - RestHandler
+    /// * `req` - Request
+    async fn execute(&self, req: Request) -> Result<HttpResponse, Error> {
+        #[cfg(unix)]
+        if let Some((client, socket)) = self.unix.as_ref() {
+            return execute_unix(client, socket, req).await;
+        }
+
+        let Some(client) = self.client.as_ref() else {
+            return Err(Error::NomadReqErr(error::REQ_BUILD_ERR.to_string()))
+        };
+
+        let res = client.execute(req).await.map_err(|err| match err.is_timeout() {
+            true => Error::Timeout(err.to_string()),
+            false => Error::from(err)
+        })?;
+        let status = res.status();
+        let headers = res.headers().clone();
+        let body = res.text().await?;
+
+        Ok(HttpResponse { status, headers, body })
+    }
+}
+
+/// Send a request through a unix socket. The request built for http is converted into a request of hyper
+/// targeting the socket with the same path, headers and body
+///
+/// # Arguments
+///
+/// * `client` - &hyper::Client<hyperlocal::UnixConnector>
+/// * `socket` - &PathBuf
+/// * `req` - Request
+#[cfg(unix)]
+async fn execute_unix(client: &hyper::Client<hyperlocal::UnixConnector>, socket: &PathBuf, req: Request) -> Result<HttpResponse, Error> {
+    let path = match req.url().query() {
+        Some(query) => format!("{}?{}", req.url().path(), query),
+        None => req.url().path().to_string()
+    };
+
+    let body = req.body()
+        .and_then(|b| b.as_bytes())
+        .map(|b| b.to_vec())
+        .unwrap_or_default();
+
+    let mut builder = hyper::Request::builder()
+        .method(req.method().clone())
+        .uri(hyper::Uri::from(hyperlocal::Uri::new(socket, &path)));
+    for (name, value) in req.headers() {
+        builder = builder.header(name, value);
+    }
+
+    let to_err = |err: String| Error::NomadReqErr(format!("{}: {err}", socket.display()));
+    let timeout = req.timeout().copied();
+    let req = builder.body(hyper::Body::from(body)).map_err(|err| to_err(err.to_string()))?;
+
+    // hyper has no timeout of its own, the one of the request is applied to the whole exchange
+    let exchange = async {
+        let res = client.request(req).await.map_err(|err| to_err(err.to_string()))?;
+        let status = res.status();
+        let headers = res.headers().clone();
+        let bytes = hyper::body::to_bytes(res.into_body()).await.map_err(|err| to_err(err.to_string()))?;
+
+        Ok::<_, Error>((status, headers, bytes))
+    };
+
+    let (status, headers, bytes) = match timeout {
+        Some(timeout) => tokio::time::timeout(timeout, exchange).await
+            .map_err(|_| Error::Timeout(format!("{} {path}", socket.display())))??,
+        None => exchange.await?
+    };
+
+    Ok(HttpResponse {
+        status,
+        headers,
+        body: String::from_utf8_lossy(&bytes).to_string()
+    })
+}
+
+/// Dump a request into a debug record. The token of the user and the secrets of the body are redacted
+///
+/// # Arguments
+///
+/// * `req` - &Request
+/// * `custom` - &HeaderMap
+/// * `secrets` - &SecretKeys
+fn dump_request(req: &Request, custom: &HeaderMap, secrets: &SecretKeys) -> DebugRecord {
+    // the custom headers may hold the credentials of a gateway in front of Nomad
+    let headers: Vec<String> = req.headers()
+        .iter()
+        .map(|(name, value)| match name.as_str().eq_ignore_ascii_case(TOKEN_HEADER) || custom.contains_key(name) {
+            true => format!("{name}: {REDACTED}"),
+            false => format!("{name}: {}", value.to_str().unwrap_or_default())
+        })
+        .collect();
+
+    let body = req.body()
+        .and_then(|b| b.as_bytes())
+        .map(|b| String::from_utf8_lossy(b).to_string())
+        .unwrap_or_default();
+
+    DebugRecord {
+        title: format!(">>> {} {}", req.method(), req.url()),
+        details: format!("headers: [{}]\n{}", headers.join(", "), redact_body(body.trim(), secrets))
+    }
+}
+
+/// Redact the secrets of a json body, which is returned as is when there is nothing to redact
+///
+/// # Arguments
+///
+/// * `body` - &str
+/// * `secrets` - &SecretKeys
+fn redact_body(body: &str, secrets: &SecretKeys) -> String {
+    let Ok(mut value) = serde_json::from_str::<Value>(body) else {
+        return body.to_string();
+    };
+
+    match redact_value(&mut value, secrets) {
+        true => value.to_string(),
+        false => body.to_string()
+    }
+}
+
+/// Redact the items of the variables, the tokens exchanged with Nomad and the value of the secret metas,
+/// which are known by the handler or marked by the convention of the job. The payload next to a secret meta
+/// is hidden as well as it may contain the secret encoded in base64. Return whether a value has been redacted
+///
+/// # Arguments
+///
+/// * `value` - &mut Value
+/// * `secrets` - &SecretKeys
+fn redact_value(value: &mut Value, secrets: &SecretKeys) -> bool {
+    let redact = |value: &mut Value| *value = Value::String(REDACTED.to_string());
+
+    match value {
+        // every item is visited, a secret may be held by any of them
+        Value::Array(items) => items.iter_mut().map(|item| redact_value(item, secrets)).filter(|redacted| *redacted).count() > 0,
+        Value::Object(object) => {
+            let mut redacted = false;
+            for field in SECRET_FIELDS {
+                if let Some(value) = object.get_mut(field) {
+                    redact(value);
+                    redacted = true;
+                }
+            }
+
+            if let Some(Value::Object(metas)) = object.get_mut(META_FIELD) {
+                let strings = metas.iter()
+                    .filter_map(|(key, value)| value.as_str().map(|v| (key.clone(), v.to_string())))
+                    .collect();
+                let marked = spec::secret_keys(&strings);
+
+                let mut secret_metas = false;
+                for (key, value) in metas.iter_mut() {
+                    if secrets.contains(key) || marked.contains(key) {
+                        redact(value);
+                        secret_metas = true;
+                    }
+                }
+
+                if let Some(payload) = object.get_mut(PAYLOAD_FIELD).filter(|_| secret_metas) {
+                    redact(payload);
+                }
+                redacted |= secret_metas;
+            }
+
+            let nested = object.values_mut().map(|value| redact_value(value, secrets)).filter(|redacted| *redacted).count();
+            redacted || nested > 0
+        },
+        _ => false
     }
 }
 
 /// Retry an http request. Due to the fact that the nomad endpoint might returns nothing
 /// or a null json value as something might not be available yet, we need to retry some request for some time.
 /// So far, the implementation is based on a linear retry. Should it be not enough it'd be better to implement
-/// an exponential backoff
+/// an exponential backoff. Client errors returned by Nomad (4xx) and timeouts are not retried.
 ///
 /// # Arguments
 ///
+/// * `handler` - &RestHandler
 /// * `req` - ReqBuilder
 /// * `max_retry` - usize
-async fn retry<T: DeserializeOwned>(req: RequestBuilder, max_retry: usize) -> Result<T, Error> {
+async fn retry<T: DeserializeOwned>(handler: &RestHandler, req: RequestBuilder, max_retry: usize) -> Result<T, Error> {
     for idx in 1..max_retry {
         let Some(req) = req.try_clone() else {
             return Err(Error::NomadReqErr(error::REQ_BUILD_FAIL_ERR.to_string()));
         };
 
-        let res = match req.send().await {
-            Ok(r) => r,
+        let body = match handler.send(req).await {
+            Ok(res) => res.body,
+            Err(Error::Api { status, .. }) if StatusCode::from_u16(status).is_ok_and(|s| s.is_server_error()) && idx < max_retry - 1 => {
+                sleep(Duration::from_millis(RETRY_LINEAR_SLEEP)).await;
+                continue
+            },
+            // retrying a stuck request would multiply the timeout
+            Err(err @ (Error::Api { .. } | Error::Timeout(_))) => return Err(err),
             Err(err) => {
-                if idx < MAX_RETRY - 1 {
+                if idx < max_retry - 1 {
                     sleep(Duration::from_millis(RETRY_LINEAR_SLEEP)).await;
                     continue
                 }
 
-                return Err(Error::from(err));
+                return Err(err);
             }
         };
 
         // try to parse the output data
-        match res.json::<T>().await {
+        match serde_json::from_str::<T>(&body) {
             Ok(res) => return Ok(res),
             Err(err) => {
-                if idx < MAX_RETRY - 1 {
+                if idx < max_retry - 1 {
                     sleep(Duration::from_millis(RETRY_LINEAR_SLEEP)).await;
                     continue
                 }
 
-                return Err(Error::from(err));
+                return Err(Error::Serialize(err.to_string()));
             }
         }
     }
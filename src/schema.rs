@@ -0,0 +1,201 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use serde::{Deserialize, Serialize};
+use crate::error::{Error, self};
+use crate::nomad::meta::MetaType;
+use crate::nomad::spec::Spec;
+
+// Constant
+const SCHEMA_FILES: [&str; 3] = ["noumead.schema.toml", "noumead.schema.yaml", "noumead.schema.yml"];
+const CONFIG_DIR: &str = "noumead";
+const REPO_MARKER: &str = ".git";
+const META_PREFIX: &str = "noumead";
+
+/// Local description of the inputs of the parameterized jobs. It's used when the convention
+/// `noumead.<meta>.<attribute>` can't be added to the meta of the job
+#[derive(Debug, Deserialize, Serialize, Default, Clone)]
+pub struct Schema {
+    /// Schema of the jobs, keyed by the id of the job
+    #[serde(default)]
+    pub jobs: BTreeMap<String, JobSchema>
+}
+
+#[derive(Debug, Deserialize, Serialize, Default, Clone)]
+pub struct JobSchema {
+    /// Template of the payload where `{{meta}}` is replaced by the value of the meta
+    #[serde(default)]
+    pub payload: Option<String>,
+
+    #[serde(default)]
+    pub metas: BTreeMap<String, MetaSchema>
+}
+
+#[derive(Debug, Deserialize, Serialize, Default, Clone)]
+pub struct MetaSchema {
+    #[serde(default)]
+    pub description: Option<String>,
+
+    #[serde(rename = "type", default)]
+    pub kind: Option<MetaType>,
+
+    #[serde(default)]
+    pub default: Option<String>,
+
+    #[serde(default)]
+    pub pattern: Option<String>,
+
+    #[serde(default)]
+    pub choices: Vec<String>,
+
+    #[serde(default)]
+    pub secret: bool
+}
+
+/// Difference between the schema of a job and its live spec
+#[derive(Debug, Serialize, Clone, PartialEq, Eq)]
+#[serde(rename_all = "snake_case", tag = "kind")]
+pub enum Drift {
+    /// The job does not exist anymore
+    JobNotFound { job: String },
+    /// A required meta of the job is not described by the schema
+    MissingKey { job: String, key: String },
+    /// The schema describes a meta which is not accepted by the job anymore
+    RemovedKey { job: String, key: String }
+}
+
+impl Schema {
+    /// Load a schema from a toml or a yaml file
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - &Path
+    pub fn load(path: &Path) -> Result<Schema, Error> {
+        let to_err = |msg: String| Error::Schema(format!("{}: {msg}", path.display()));
+        let content = fs::read_to_string(path).map_err(|err| to_err(err.to_string()))?;
+
+        match path.extension().and_then(|e| e.to_str()) {
+            Some("toml") => toml::from_str(&content).map_err(|err| to_err(err.to_string())),
+            Some("yaml" | "yml") => serde_yaml::from_str(&content).map_err(|err| to_err(err.to_string())),
+            _ => Err(to_err(error::SCHEMA_FORMAT_ERR.to_string()))
+        }
+    }
+
+    /// Look for a schema file in the current directory and its parents up to the root of the repository,
+    /// then in the config directory of the user
+    pub fn find() -> Result<Option<(PathBuf, Schema)>, Error> {
+        let mut dirs = Vec::new();
+        if let Ok(cwd) = env::current_dir() {
+            for dir in cwd.ancestors() {
+                dirs.push(dir.to_path_buf());
+                if dir.join(REPO_MARKER).exists() {
+                    break;
+                }
+            }
+        }
+
+        if let Some(config) = dirs::config_dir() {
+            dirs.push(config.join(CONFIG_DIR));
+        }
+
+        let path = dirs.iter()
+            .flat_map(|dir| SCHEMA_FILES.iter().map(move |f| dir.join(f)))
+            .find(|path| path.is_file());
+
+        match path {
+            Some(path) => Schema::load(&path).map(|schema| Some((path, schema))),
+            None => Ok(None)
+        }
+    }
+
+    /// Return the schema of a job from the id of the job
+    ///
+    /// # Arguments
+    ///
+    /// * `&self` - Schema
+    /// * `job_id` - &str
+    pub fn job(&self, job_id: &str) -> Option<&JobSchema> {
+        self.jobs.get(job_id)
+    }
+}
+
+impl JobSchema {
+    /// Apply the schema to the spec of the job. The attributes of the schema are written in the meta of the spec
+    /// and take precedence over the attributes defined in the job
+    ///
+    /// # Arguments
+    ///
+    /// * `&self` - JobSchema
+    /// * `spec` - Spec
+    pub fn apply(&self, mut spec: Spec) -> Spec {
+        for (key, meta) in &self.metas {
+            let choices = (!meta.choices.is_empty()).then(|| meta.choices.join(","));
+            let secret = meta.secret.then(|| true.to_string());
+            let attributes = [
+                ("description", meta.description.clone()),
+                ("type", meta.kind.map(|k| k.to_string())),
+                ("default", meta.default.clone()),
+                ("pattern", meta.pattern.clone()),
+                ("choices", choices),
+                ("secret", secret)
+            ];
+
+            for (attr, value) in attributes {
+                if let Some(value) = value {
+                    spec.metas.insert(format!("{META_PREFIX}.{key}.{attr}"), value);
+                }
+            }
+        }
+
+        spec
+    }
+
+    /// Return the metas marked as secret by the schema
+    ///
+    /// # Arguments
+    ///
+    /// * `&self` - JobSchema
+    pub fn secret_keys(&self) -> HashSet<String> {
+        self.metas.iter()
+            .filter(|(_, meta)| meta.secret)
+            .map(|(key, _)| key.clone())
+            .collect()
+    }
+
+    /// Render the payload template with the value of the metas
+    ///
+    /// # Arguments
+    ///
+    /// * `&self` - JobSchema
+    /// * `metas` - &HashMap<String, String>
+    pub fn render_payload(&self, metas: &HashMap<String, String>) -> Option<String> {
+        self.payload.as_ref().map(|template| {
+            metas.iter().fold(template.clone(), |payload, (key, value)| {
+                payload.replace(&format!("{{{{{key}}}}}"), value)
+            })
+        })
+    }
+
+    /// Compare the schema with the live spec of the job
+    ///
+    /// # Arguments
+    ///
+    /// * `&self` - JobSchema
+    /// * `job` - &str
+    /// * `spec` - &Spec
+    pub fn check(&self, job: &str, spec: &Spec) -> Vec<Drift> {
+        let required = spec.parameterized.meta_required.clone().unwrap_or_default();
+        let optional = spec.parameterized.meta_optional.clone().unwrap_or_default();
+
+        let missing = required.iter()
+            .filter(|key| !self.metas.contains_key(*key))
+            .map(|key| Drift::MissingKey { job: job.to_string(), key: key.clone() });
+
+        let removed = self.metas.keys()
+            .filter(|key| !required.contains(key) && !optional.contains(key))
+            .map(|key| Drift::RemovedKey { job: job.to_string(), key: key.clone() });
+
+        missing.chain(removed).collect()
+    }
+}
@@ -0,0 +1,636 @@
+use std::collections::HashMap;
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use futures::StreamExt;
+use tokio::sync::mpsc::UnboundedSender;
+use tokio::task::JoinHandle;
+use tokio::time::{sleep, Duration};
+use noumead::{Client, Error};
+use noumead::error;
+use noumead::nomad::alloc::Allocation;
+use noumead::nomad::job::Job;
+use noumead::nomad::spec::Spec;
+use noumead::nomad::stream::LogEvent;
+use noumead::nomad::summary::{ChildrenSummary, DispatchStatus, ParentStatus};
+use noumead::schema::Schema;
+use super::form::{Form, FormEvent};
+
+// constant
+const STATUS_WAIT: u64 = 30000;
+const RETRY_SLEEP: u64 = 2000;
+const PAGE_SIZE: usize = 10;
+
+/// Pane which receive the navigation keys
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Pane {
+    Jobs,
+    Children,
+    Logs
+}
+
+/// Action on a dispatched job waiting for a confirmation
+#[derive(Debug, Clone)]
+pub enum Action {
+    Stop(DispatchStatus),
+    Purge(DispatchStatus)
+}
+
+/// Current interaction of the user
+pub enum Mode {
+    Normal,
+    Search(String),
+    Form(Form),
+    Confirm(Action)
+}
+
+/// Message sent by the background tasks which watch Nomad
+pub enum Msg {
+    Status(String, String, Option<ParentStatus>),
+    Alloc(String, Allocation),
+    Log(LogEvent),
+    Error(String)
+}
+
+/// State of the terminal interface
+pub struct App {
+    client: Client,
+    schema: Option<Schema>,
+    tx: UnboundedSender<Msg>,
+    pub jobs: Vec<Job>,
+    pub job_cursor: usize,
+    pub children: Vec<DispatchStatus>,
+    pub summary: ChildrenSummary,
+    pub child_cursor: usize,
+    pub alloc: Option<Allocation>,
+    pub tasks: Vec<String>,
+    pub task_cursor: usize,
+    pub logs: Vec<LogEvent>,
+    pub scroll: usize,
+    pub search: Option<String>,
+    pub focus: Pane,
+    pub mode: Mode,
+    pub message: Option<(String, bool)>,
+    pub quit: bool,
+    followed: Option<String>,
+    status_watch: Option<JoinHandle<()>>,
+    log_follow: Option<JoinHandle<()>>
+}
+
+impl App {
+    /// Create the application with the parameterized jobs of every namespaces
+    ///
+    /// # Arguments
+    ///
+    /// * `client` - Client
+    /// * `schema` - Option<Schema>
+    /// * `tx` - UnboundedSender<Msg>
+    pub async fn new(client: Client, schema: Option<Schema>, tx: UnboundedSender<Msg>) -> Result<App, Error> {
+        let mut jobs = client.parameterized_jobs().await?;
+        jobs.sort_by(|a, b| (&a.namespace, &a.name).cmp(&(&b.namespace, &b.name)));
+
+        let mut app = App {
+            client,
+            schema,
+            tx,
+            jobs,
+            job_cursor: 0,
+            children: Vec::new(),
+            summary: ChildrenSummary::default(),
+            child_cursor: 0,
+            alloc: None,
+            tasks: Vec::new(),
+            task_cursor: 0,
+            logs: Vec::new(),
+            scroll: 0,
+            search: None,
+            focus: Pane::Jobs,
+            mode: Mode::Normal,
+            message: None,
+            quit: false,
+            followed: None,
+            status_watch: None,
+            log_follow: None
+        };
+        app.watch_job();
+
+        Ok(app)
+    }
+
+    /// Return the selected parameterized job
+    ///
+    /// # Arguments
+    ///
+    /// * `&self` - App
+    pub fn job(&self) -> Option<&Job> {
+        self.jobs.get(self.job_cursor)
+    }
+
+    /// Return the selected dispatched job
+    ///
+    /// # Arguments
+    ///
+    /// * `&self` - App
+    pub fn child(&self) -> Option<&DispatchStatus> {
+        self.children.get(self.child_cursor)
+    }
+
+    /// Return the task whose logs are shown
+    ///
+    /// # Arguments
+    ///
+    /// * `&self` - App
+    pub fn task(&self) -> Option<&String> {
+        self.tasks.get(self.task_cursor)
+    }
+
+    /// Check whether a log line match the search
+    ///
+    /// # Arguments
+    ///
+    /// * `&self` - App
+    /// * `event` - &LogEvent
+    pub fn is_match(&self, event: &LogEvent) -> bool {
+        self.search
+            .as_ref()
+            .map(|q| event.line.contains(q.as_str()))
+            .unwrap_or_default()
+    }
+
+    /// Update the state with a message sent by a background task
+    ///
+    /// # Arguments
+    ///
+    /// * `&mut self` - App
+    /// * `msg` - Msg
+    pub fn handle_msg(&mut self, msg: Msg) {
+        match msg {
+            Msg::Status(namespace, job_id, parent) => {
+                if self.job().map(|j| (&j.namespace, &j.id)) != Some((&namespace, &job_id)) {
+                    return;
+                }
+
+                let parent = parent.unwrap_or_else(|| ParentStatus {
+                    job: job_id,
+                    namespace,
+                    children: ChildrenSummary::default(),
+                    dispatched: Vec::new()
+                });
+
+                // keep the selection on the same dispatched job when new jobs are dispatched
+                let selected = self.child().map(|c| c.id.clone());
+                self.children = parent.dispatched;
+                self.summary = parent.children;
+                self.child_cursor = selected
+                    .and_then(|id| self.children.iter().position(|c| c.id == id))
+                    .unwrap_or(0);
+
+                self.follow_child();
+            },
+            Msg::Alloc(job_id, alloc) => {
+                if self.followed.as_ref() != Some(&job_id) {
+                    return;
+                }
+
+                let mut tasks: Vec<String> = alloc.get_tasks_name()
+                    .into_iter()
+                    .cloned()
+                    .collect();
+                tasks.sort();
+
+                self.tasks = tasks;
+                self.task_cursor = 0;
+                self.alloc = Some(alloc);
+                self.follow_task();
+            },
+            Msg::Log(event) => {
+                let alloc_id = self.alloc.as_ref().map(|a| &a.alloc_id);
+                if alloc_id != Some(&event.alloc) || self.task() != Some(&event.task) {
+                    return;
+                }
+
+                // keep the view still when the user has scrolled up
+                if self.scroll > 0 {
+                    self.scroll += 1;
+                }
+
+                self.logs.push(event);
+            },
+            Msg::Error(err) => self.message = Some((err, true))
+        }
+    }
+
+    /// Update the state with the key pressed by the user
+    ///
+    /// # Arguments
+    ///
+    /// * `&mut self` - App
+    /// * `key` - KeyEvent
+    pub async fn handle_key(&mut self, key: KeyEvent) {
+        if key.modifiers.contains(KeyModifiers::CONTROL) && key.code == KeyCode::Char('c') {
+            self.quit = true;
+            return;
+        }
+
+        let mode = std::mem::replace(&mut self.mode, Mode::Normal);
+        match mode {
+            Mode::Normal => self.handle_normal_key(key).await,
+            Mode::Search(query) => self.handle_search_key(key, query),
+            Mode::Form(form) => self.handle_form_key(key, form).await,
+            Mode::Confirm(action) => {
+                if key.code == KeyCode::Char('y') {
+                    self.confirm(action).await;
+                }
+            }
+        }
+    }
+
+    /// Handle the keys of the navigation mode
+    ///
+    /// # Arguments
+    ///
+    /// * `&mut self` - App
+    /// * `key` - KeyEvent
+    async fn handle_normal_key(&mut self, key: KeyEvent) {
+        self.message = None;
+
+        match key.code {
+            KeyCode::Char('q') => self.quit = true,
+            KeyCode::Esc => self.search = None,
+            KeyCode::Tab | KeyCode::Right | KeyCode::Enter => self.focus = match self.focus {
+                Pane::Jobs => Pane::Children,
+                _ => Pane::Logs
+            },
+            KeyCode::BackTab | KeyCode::Left => self.focus = match self.focus {
+                Pane::Logs => Pane::Children,
+                _ => Pane::Jobs
+            },
+            KeyCode::Down | KeyCode::Char('j') => self.move_cursor(1),
+            KeyCode::Up | KeyCode::Char('k') => self.move_cursor(-1),
+            KeyCode::PageDown => self.scroll_logs(-(PAGE_SIZE as i64)),
+            KeyCode::PageUp => self.scroll_logs(PAGE_SIZE as i64),
+            KeyCode::Char('g') => self.scroll = self.logs.len().saturating_sub(1),
+            KeyCode::Char('G') => self.scroll = 0,
+            KeyCode::Char('/') => self.mode = Mode::Search(String::new()),
+            KeyCode::Char('n') => self.jump_to_match(true),
+            KeyCode::Char('N') => self.jump_to_match(false),
+            KeyCode::Char('t') if !self.tasks.is_empty() => {
+                self.task_cursor = (self.task_cursor + 1) % self.tasks.len();
+                self.follow_task();
+            },
+            KeyCode::Char('d') => self.open_form().await,
+            KeyCode::Char('r') => self.redispatch().await,
+            KeyCode::Char('s') => self.ask_confirmation(Action::Stop),
+            KeyCode::Char('p') => self.ask_confirmation(Action::Purge),
+            _ => {}
+        }
+    }
+
+    /// Handle the keys typed while searching the logs
+    ///
+    /// # Arguments
+    ///
+    /// * `&mut self` - App
+    /// * `key` - KeyEvent
+    /// * `query` - String
+    fn handle_search_key(&mut self, key: KeyEvent, mut query: String) {
+        match key.code {
+            KeyCode::Esc => {},
+            KeyCode::Enter => {
+                self.search = (!query.is_empty()).then_some(query);
+                self.scroll = 0;
+                self.jump_to_match(false);
+            },
+            KeyCode::Backspace => {
+                query.pop();
+                self.mode = Mode::Search(query);
+            },
+            KeyCode::Char(c) => {
+                query.push(c);
+                self.mode = Mode::Search(query);
+            },
+            _ => self.mode = Mode::Search(query)
+        }
+    }
+
+    /// Handle the keys typed in the dispatch form
+    ///
+    /// # Arguments
+    ///
+    /// * `&mut self` - App
+    /// * `key` - KeyEvent
+    /// * `form` - Form
+    async fn handle_form_key(&mut self, key: KeyEvent, mut form: Form) {
+        match form.handle(key) {
+            FormEvent::Pending => self.mode = Mode::Form(form),
+            FormEvent::Cancel => {},
+            FormEvent::Submit(metas) => self.dispatch(&form.job, metas).await
+        }
+    }
+
+    /// Move the cursor of the focused pane
+    ///
+    /// # Arguments
+    ///
+    /// * `&mut self` - App
+    /// * `delta` - i64
+    fn move_cursor(&mut self, delta: i64) {
+        let shift = |cursor: usize, len: usize| (cursor as i64 + delta).clamp(0, len.saturating_sub(1) as i64) as usize;
+
+        match self.focus {
+            Pane::Jobs => {
+                let cursor = shift(self.job_cursor, self.jobs.len());
+                if cursor != self.job_cursor {
+                    self.job_cursor = cursor;
+                    self.watch_job();
+                }
+            },
+            Pane::Children => {
+                self.child_cursor = shift(self.child_cursor, self.children.len());
+                self.follow_child();
+            },
+            Pane::Logs => self.scroll_logs(-delta)
+        }
+    }
+
+    /// Scroll the logs. A positive delta scroll toward the oldest lines
+    ///
+    /// # Arguments
+    ///
+    /// * `&mut self` - App
+    /// * `delta` - i64
+    fn scroll_logs(&mut self, delta: i64) {
+        let max = self.logs.len().saturating_sub(1) as i64;
+        self.scroll = (self.scroll as i64 + delta).clamp(0, max) as usize;
+    }
+
+    /// Scroll the logs to the next or the previous line which match the search
+    ///
+    /// # Arguments
+    ///
+    /// * `&mut self` - App
+    /// * `forward` - bool
+    fn jump_to_match(&mut self, forward: bool) {
+        let len = self.logs.len();
+        if self.search.is_none() || len == 0 {
+            return;
+        }
+
+        // the line shown at the bottom of the view
+        let anchor = len - 1 - self.scroll.min(len - 1);
+        let matches: Vec<usize> = self.logs.iter()
+            .enumerate()
+            .filter(|(_, e)| self.is_match(e))
+            .map(|(i, _)| i)
+            .collect();
+
+        let target = match forward {
+            true => matches.iter().find(|i| **i > anchor).or(matches.first()),
+            false => matches.iter().rev().find(|i| **i < anchor).or(matches.last())
+        };
+
+        match target {
+            Some(i) => self.scroll = len - 1 - i,
+            None => self.message = Some((format!("No line match {}", self.search.clone().unwrap_or_default()), true))
+        }
+    }
+
+    /// Watch the dispatched jobs of the selected parameterized job
+    ///
+    /// # Arguments
+    ///
+    /// * `&mut self` - App
+    fn watch_job(&mut self) {
+        if let Some(handle) = self.status_watch.take() {
+            handle.abort();
+        }
+
+        self.children.clear();
+        self.summary = ChildrenSummary::default();
+        self.child_cursor = 0;
+        self.follow_child();
+
+        let Some(job) = self.job() else {
+            return;
+        };
+
+        let (client, tx, schema) = (self.client.clone(), self.tx.clone(), self.schema.clone());
+        let (namespace, job_id) = (job.namespace.clone(), job.id.clone());
+        self.status_watch = Some(tokio::spawn(async move {
+            let mut index = 0;
+            loop {
+                match client.dispatch_status(Some(&job_id), schema.as_ref(), index, Duration::from_millis(STATUS_WAIT)).await {
+                    Ok((status, last_index)) => {
+                        // jobs of several namespaces can share the id
+                        let parent = status.into_iter().find(|p| p.namespace == namespace && p.job == job_id);
+                        if tx.send(Msg::Status(namespace.clone(), job_id.clone(), parent)).is_err() {
+                            return;
+                        }
+
+                        index = last_index.max(1);
+                    },
+                    Err(err) => {
+                        let _ = tx.send(Msg::Error(err.to_string()));
+                        sleep(Duration::from_millis(RETRY_SLEEP)).await;
+                    }
+                }
+            }
+        }));
+    }
+
+    /// Fetch the allocation of the selected dispatched job in order to follow its logs
+    ///
+    /// # Arguments
+    ///
+    /// * `&mut self` - App
+    fn follow_child(&mut self) {
+        let selected = self.child().map(|c| c.id.clone());
+        if selected.is_some() && selected == self.followed {
+            return;
+        }
+
+        if let Some(handle) = self.log_follow.take() {
+            handle.abort();
+        }
+
+        self.followed = selected.clone();
+        self.alloc = None;
+        self.tasks.clear();
+        self.logs.clear();
+        self.scroll = 0;
+
+        let Some(job_id) = selected else {
+            return;
+        };
+
+        // the children are dispatched in the namespace of their parent
+        let namespace = self.job().map(|j| j.namespace.clone()).unwrap_or_default();
+        let (client, tx) = (self.client.clone(), self.tx.clone());
+        self.log_follow = Some(tokio::spawn(async move {
+            let msg = match client.allocation(&namespace, &job_id).await {
+                Ok(alloc) => Msg::Alloc(job_id, alloc),
+                Err(err) => Msg::Error(err.to_string())
+            };
+
+            let _ = tx.send(msg);
+        }));
+    }
+
+    /// Follow the logs of the selected task
+    ///
+    /// # Arguments
+    ///
+    /// * `&mut self` - App
+    fn follow_task(&mut self) {
+        if let Some(handle) = self.log_follow.take() {
+            handle.abort();
+        }
+
+        self.logs.clear();
+        self.scroll = 0;
+
+        let (Some(alloc), Some(task)) = (self.alloc.as_ref(), self.task()) else {
+            return;
+        };
+
+        let mut logs = self.client.follow_logs(alloc, task);
+        let tx = self.tx.clone();
+        self.log_follow = Some(tokio::spawn(async move {
+            while let Some(event) = logs.next().await {
+                let msg = match event {
+                    Ok(event) => Msg::Log(event),
+                    Err(err) => Msg::Error(err.to_string())
+                };
+
+                if tx.send(msg).is_err() {
+                    return;
+                }
+            }
+        }));
+    }
+
+    /// Open the dispatch form of the selected parameterized job
+    ///
+    /// # Arguments
+    ///
+    /// * `&mut self` - App
+    async fn open_form(&mut self) {
+        let Some(job) = self.job().cloned() else {
+            return;
+        };
+
+        let form = self.spec(&job)
+            .await
+            .and_then(|spec| Form::new(job, &spec));
+
+        match form {
+            Ok(form) => self.mode = Mode::Form(form),
+            Err(err) => self.message = Some((err.to_string(), true))
+        }
+    }
+
+    /// Dispatch the selected parameterized job with the metas of the selected dispatched job
+    ///
+    /// # Arguments
+    ///
+    /// * `&mut self` - App
+    async fn redispatch(&mut self) {
+        let (Some(job), Some(child)) = (self.job().cloned(), self.child().cloned()) else {
+            self.message = Some((error::NO_DISPATCHED_JOB_ERR.to_string(), true));
+            return;
+        };
+
+        // only the parameterized metas are sent again, the others are inherited from the meta of the job
+        let metas = self.spec(&job)
+            .await
+            .and_then(|spec| spec.dispatch_metas(&child.metas));
+
+        match metas {
+            Ok(metas) => self.dispatch(&job, metas).await,
+            Err(err) => self.message = Some((err.to_string(), true))
+        }
+    }
+
+    /// Get the spec of a parameterized job with its schema applied
+    ///
+    /// # Arguments
+    ///
+    /// * `&self` - App
+    /// * `job` - &Job
+    async fn spec(&self, job: &Job) -> Result<Spec, Error> {
+        let spec = self.client.spec(job).await?;
+
+        Ok(match self.schema.as_ref().and_then(|s| s.job(&job.id)) {
+            Some(job_schema) => job_schema.apply(spec),
+            None => spec
+        })
+    }
+
+    /// Dispatch a parameterized job
+    ///
+    /// # Arguments
+    ///
+    /// * `&mut self` - App
+    /// * `job` - &Job
+    /// * `metas` - HashMap<String, String>
+    async fn dispatch(&mut self, job: &Job, metas: HashMap<String, String>) {
+        let payload = self.schema.as_ref()
+            .and_then(|s| s.job(&job.id))
+            .and_then(|s| s.render_payload(&metas));
+
+        let res = match payload {
+            Some(payload) => self.client.dispatch_with_payload(job, metas, payload).await,
+            None => self.client.dispatch(job, metas).await
+        };
+
+        self.message = Some(match res {
+            Ok(res) => (format!("Job {} has been dispatched", res.dispatch_id), false),
+            Err(err) => (err.to_string(), true)
+        });
+    }
+
+    /// Ask the user to confirm an action on the selected dispatched job
+    ///
+    /// # Arguments
+    ///
+    /// * `&mut self` - App
+    /// * `action` - fn(DispatchStatus) -> Action
+    fn ask_confirmation(&mut self, action: fn(DispatchStatus) -> Action) {
+        match self.child().cloned() {
+            Some(child) => self.mode = Mode::Confirm(action(child)),
+            None => self.message = Some((error::NO_DISPATCHED_JOB_ERR.to_string(), true))
+        }
+    }
+
+    /// Run an action confirmed by the user
+    ///
+    /// # Arguments
+    ///
+    /// * `&mut self` - App
+    /// * `action` - Action
+    async fn confirm(&mut self, action: Action) {
+        let namespace = self.job().map(|j| j.namespace.clone()).unwrap_or_default();
+        let to_job = |child: &DispatchStatus| Job {
+            id: child.id.clone(),
+            name: child.id.clone(),
+            namespace,
+            ..Job::default()
+        };
+
+        let (res, verb) = match &action {
+            Action::Stop(child) => (self.client.stop(&[to_job(child)]).await, "stopped"),
+            Action::Purge(child) => (self.client.purge(&[to_job(child)]).await, "purged")
+        };
+
+        self.message = Some(match res.into_iter().next() {
+            Some(Err(err)) => (err.to_string(), true),
+            _ => (format!("Job has been {verb}"), false)
+        });
+    }
+}
+
+impl Drop for App {
+    fn drop(&mut self) {
+        for handle in [self.status_watch.take(), self.log_follow.take()].into_iter().flatten() {
+            handle.abort();
+        }
+    }
+}
@@ -0,0 +1,269 @@
+use tui::backend::Backend;
+use tui::layout::{Constraint, Direction, Layout, Rect};
+use tui::style::{Color, Modifier, Style};
+use tui::text::{Span, Spans};
+use tui::widgets::{Block, Borders, Clear, List, ListItem, ListState, Paragraph};
+use tui::Frame;
+use noumead::nomad::stream::StdKind;
+use noumead::nomad::summary::DispatchState;
+use crate::output::{format_duration, format_metas};
+use super::app::{Action, App, Mode, Pane};
+use super::form::Form;
+
+// constant
+const SECRET_MASK: &str = "*";
+const HELP: &str = "q quit · tab switch pane · d dispatch · r re-dispatch · s stop · p purge · t task · / search";
+
+/// Draw the whole interface
+///
+/// # Arguments
+///
+/// * `f` - &mut Frame<B>
+/// * `app` - &App
+pub fn draw<B: Backend>(f: &mut Frame<B>, app: &App) {
+    let rows = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Min(0), Constraint::Length(1)])
+        .split(f.size());
+
+    let panes = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(25), Constraint::Percentage(35), Constraint::Percentage(40)])
+        .split(rows[0]);
+
+    draw_jobs(f, app, panes[0]);
+    draw_children(f, app, panes[1]);
+    draw_logs(f, app, panes[2]);
+    draw_status_bar(f, app, rows[1]);
+
+    if let Mode::Form(form) = &app.mode {
+        draw_form(f, form);
+    }
+}
+
+/// Create the block surrounding a pane. The focused pane is highlighted
+///
+/// # Arguments
+///
+/// * `app` - &App
+/// * `pane` - Pane
+/// * `title` - String
+fn pane_block(app: &App, pane: Pane, title: String) -> Block<'static> {
+    let style = match app.focus == pane {
+        true => Style::default().fg(Color::Yellow),
+        false => Style::default()
+    };
+
+    Block::default()
+        .borders(Borders::ALL)
+        .border_style(style)
+        .title(title)
+}
+
+/// Draw the parameterized jobs grouped by namespace
+///
+/// # Arguments
+///
+/// * `f` - &mut Frame<B>
+/// * `app` - &App
+/// * `area` - Rect
+fn draw_jobs<B: Backend>(f: &mut Frame<B>, app: &App, area: Rect) {
+    let mut items = Vec::new();
+    let mut selected = None;
+    let mut namespace = None;
+
+    for (idx, job) in app.jobs.iter().enumerate() {
+        if namespace != Some(&job.namespace) {
+            namespace = Some(&job.namespace);
+            items.push(ListItem::new(Span::styled(
+                job.namespace.clone(),
+                Style::default().fg(Color::DarkGray).add_modifier(Modifier::BOLD)
+            )));
+        }
+
+        if idx == app.job_cursor {
+            selected = Some(items.len());
+        }
+
+        items.push(ListItem::new(format!("  {}", job.name)));
+    }
+
+    let list = List::new(items)
+        .block(pane_block(app, Pane::Jobs, "Jobs".to_string()))
+        .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+
+    let mut state = ListState::default();
+    state.select(selected);
+
+    f.render_stateful_widget(list, area, &mut state);
+}
+
+/// Draw the jobs dispatched from the selected parameterized job
+///
+/// # Arguments
+///
+/// * `f` - &mut Frame<B>
+/// * `app` - &App
+/// * `area` - Rect
+fn draw_children<B: Backend>(f: &mut Frame<B>, app: &App, area: Rect) {
+    let items: Vec<ListItem> = app.children.iter()
+        .map(|child| {
+            let color = match child.state {
+                DispatchState::Queued => Color::Yellow,
+                DispatchState::Running => Color::Blue,
+                DispatchState::Complete => Color::Green,
+                DispatchState::Failed => Color::Red
+            };
+
+            let id = child.id.rsplit('/').next().unwrap_or(&child.id);
+
+            ListItem::new(Spans::from(vec![
+                Span::styled(format!("{:<9}", child.state.to_string()), Style::default().fg(color)),
+                Span::raw(format!("{id} {} ", format_duration(child))),
+                Span::styled(format_metas(&child.metas), Style::default().fg(Color::DarkGray))
+            ]))
+        })
+        .collect();
+
+    let title = format!(
+        "Dispatched (pending: {} running: {} dead: {})",
+        app.summary.pending,
+        app.summary.running,
+        app.summary.dead
+    );
+
+    let list = List::new(items)
+        .block(pane_block(app, Pane::Children, title))
+        .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+
+    let mut state = ListState::default();
+    state.select((!app.children.is_empty()).then_some(app.child_cursor));
+
+    f.render_stateful_widget(list, area, &mut state);
+}
+
+/// Draw the logs of the selected task. Only the lines which fit in the pane are rendered
+///
+/// # Arguments
+///
+/// * `f` - &mut Frame<B>
+/// * `app` - &App
+/// * `area` - Rect
+fn draw_logs<B: Backend>(f: &mut Frame<B>, app: &App, area: Rect) {
+    let height = area.height.saturating_sub(2) as usize;
+    let end = app.logs.len().saturating_sub(app.scroll);
+    let start = end.saturating_sub(height);
+
+    let lines: Vec<Spans> = app.logs[start..end].iter()
+        .map(|event| {
+            let style = match (app.is_match(event), &event.stream) {
+                (true, _) => Style::default().fg(Color::Black).bg(Color::Yellow),
+                (false, StdKind::Stderr) => Style::default().fg(Color::Red),
+                (false, StdKind::Stdout) => Style::default()
+            };
+
+            Spans::from(Span::styled(event.line.clone(), style))
+        })
+        .collect();
+
+    let mut title = match app.task() {
+        Some(task) => format!("Logs {task} ({}/{})", app.task_cursor + 1, app.tasks.len()),
+        None => "Logs".to_string()
+    };
+
+    if let Some(search) = &app.search {
+        title.push_str(&format!(" /{search}"));
+    }
+
+    if app.scroll > 0 {
+        title.push_str(&format!(" [-{}]", app.scroll));
+    }
+
+    let paragraph = Paragraph::new(lines)
+        .block(pane_block(app, Pane::Logs, title));
+
+    f.render_widget(paragraph, area);
+}
+
+/// Draw the status bar showing the prompt, the last message or the key bindings
+///
+/// # Arguments
+///
+/// * `f` - &mut Frame<B>
+/// * `app` - &App
+/// * `area` - Rect
+fn draw_status_bar<B: Backend>(f: &mut Frame<B>, app: &App, area: Rect) {
+    let line = match (&app.mode, &app.message) {
+        (Mode::Search(query), _) => Span::raw(format!("/{query}")),
+        (Mode::Confirm(Action::Stop(child)), _) => Span::raw(format!("Stop {}? (y/n)", child.id)),
+        (Mode::Confirm(Action::Purge(child)), _) => Span::raw(format!("Purge {}? (y/n)", child.id)),
+        (_, Some((msg, true))) => Span::styled(msg.clone(), Style::default().fg(Color::Red)),
+        (_, Some((msg, false))) => Span::styled(msg.clone(), Style::default().fg(Color::Green)),
+        _ => Span::styled(HELP, Style::default().fg(Color::DarkGray))
+    };
+
+    f.render_widget(Paragraph::new(Spans::from(line)), area);
+}
+
+/// Draw the dispatch form above the panes
+///
+/// # Arguments
+///
+/// * `f` - &mut Frame<B>
+/// * `form` - &Form
+fn draw_form<B: Backend>(f: &mut Frame<B>, form: &Form) {
+    let size = f.size();
+    let height = (form.fields.len() as u16 + 5).min(size.height);
+    let width = (size.width * 6 / 10).max(20).min(size.width);
+    let area = Rect::new((size.width - width) / 2, (size.height - height) / 2, width, height);
+
+    let mut lines: Vec<Spans> = form.fields.iter()
+        .enumerate()
+        .map(|(idx, field)| {
+            let label = format!("{}{}: ", field.meta.name, if field.meta.required { "*" } else { "" });
+            let style = match idx == form.cursor {
+                true => Style::default().add_modifier(Modifier::BOLD).fg(Color::Yellow),
+                false => Style::default()
+            };
+
+            // secret values are masked while typed
+            let value = match field.meta.secret {
+                true => SECRET_MASK.repeat(field.value.chars().count()),
+                false => field.value.clone()
+            };
+
+            Spans::from(vec![Span::styled(label, style), Span::raw(value)])
+        })
+        .collect();
+
+    // show the description and the choices of the selected meta
+    let help = form.fields.get(form.cursor)
+        .map(|f| {
+            let mut help: Vec<String> = f.meta.description.iter().cloned().collect();
+            help.push(format!("type: {}", f.meta.kind));
+            if !f.meta.choices.is_empty() {
+                help.push(format!("one of: {}", f.meta.choices.join(", ")));
+            }
+
+            help.join(" · ")
+        })
+        .unwrap_or_default();
+
+    lines.push(Spans::from(Span::styled(help, Style::default().fg(Color::DarkGray))));
+    lines.push(match &form.error {
+        Some(err) => Spans::from(Span::styled(err.clone(), Style::default().fg(Color::Red))),
+        None => Spans::from(Span::styled("enter dispatch · esc cancel", Style::default().fg(Color::DarkGray)))
+    });
+
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .title(format!("Dispatch {}", form.job.name));
+
+    f.render_widget(Clear, area);
+    f.render_widget(Paragraph::new(lines).block(block), area);
+
+    if let Some(field) = form.fields.get(form.cursor) {
+        let x = area.x + 1 + (field.meta.name.len() + field.meta.required as usize + 2 + field.value.chars().count()) as u16;
+        f.set_cursor(x.min(area.right().saturating_sub(2)), area.y + 1 + form.cursor as u16);
+    }
+}
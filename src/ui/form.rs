@@ -0,0 +1,108 @@
+use std::collections::HashMap;
+use crossterm::event::{KeyCode, KeyEvent};
+use noumead::Error;
+use noumead::nomad::job::Job;
+use noumead::nomad::meta::MetaField;
+use noumead::nomad::spec::Spec;
+
+/// Meta which can be filled in the form
+#[derive(Debug, Clone)]
+pub struct Field {
+    pub meta: MetaField,
+    pub value: String
+}
+
+/// Form generated from the parameterized spec of a job in order to dispatch it
+#[derive(Debug, Clone)]
+pub struct Form {
+    pub job: Job,
+    pub fields: Vec<Field>,
+    pub cursor: usize,
+    pub error: Option<String>
+}
+
+/// Outcome of a key pressed in the form
+pub enum FormEvent {
+    Pending,
+    Cancel,
+    Submit(HashMap<String, String>)
+}
+
+impl Form {
+    /// Create a new form with a field for each required and optional meta of the job.
+    /// The fields are filled with the default value of the metas
+    ///
+    /// # Arguments
+    ///
+    /// * `job` - Job
+    /// * `spec` - &Spec
+    pub fn new(job: Job, spec: &Spec) -> Result<Form, Error> {
+        let fields = spec.meta_fields()?
+            .into_iter()
+            .map(|meta| Field {
+                value: meta.default.clone().unwrap_or_default(),
+                meta
+            })
+            .collect();
+
+        Ok(Form {
+            job,
+            fields,
+            cursor: 0,
+            error: None
+        })
+    }
+
+    /// Update the form with the key pressed by the user
+    ///
+    /// # Arguments
+    ///
+    /// * `&mut self` - Form
+    /// * `key` - KeyEvent
+    pub fn handle(&mut self, key: KeyEvent) -> FormEvent {
+        let len = self.fields.len();
+
+        match key.code {
+            KeyCode::Esc => return FormEvent::Cancel,
+            KeyCode::Enter => return match self.metas() {
+                Ok(metas) => FormEvent::Submit(metas),
+                Err(err) => {
+                    self.error = Some(err);
+                    FormEvent::Pending
+                }
+            },
+            KeyCode::Down | KeyCode::Tab if len > 0 => self.cursor = (self.cursor + 1) % len,
+            KeyCode::Up | KeyCode::BackTab if len > 0 => self.cursor = (self.cursor + len - 1) % len,
+            KeyCode::Backspace => {
+                if let Some(field) = self.fields.get_mut(self.cursor) {
+                    field.value.pop();
+                }
+            },
+            KeyCode::Char(c) => {
+                if let Some(field) = self.fields.get_mut(self.cursor) {
+                    field.value.push(c);
+                }
+            },
+            _ => {}
+        }
+
+        FormEvent::Pending
+    }
+
+    /// Collect the normalized metas filled by the user. Optional metas left empty are not dispatched
+    ///
+    /// # Arguments
+    ///
+    /// * `&self` - Form
+    fn metas(&self) -> Result<HashMap<String, String>, String> {
+        let mut metas = HashMap::new();
+        for field in &self.fields {
+            let value = field.meta.normalize(&field.value).map_err(|err| err.to_string())?;
+            if !value.is_empty() {
+                metas.insert(field.meta.name.clone(), value);
+            }
+        }
+
+        Ok(metas)
+    }
+}
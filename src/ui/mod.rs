@@ -0,0 +1,92 @@
+use std::io::{stdout, Stdout};
+use crossterm::event::{Event, EventStream, KeyEventKind};
+use crossterm::execute;
+use crossterm::terminal::{self, EnterAlternateScreen, LeaveAlternateScreen};
+use futures::StreamExt;
+use tokio::sync::mpsc::{self, UnboundedReceiver};
+use tokio::time::{interval, Duration};
+use tui::backend::CrosstermBackend;
+use tui::Terminal;
+use noumead::{Client, Error};
+use noumead::schema::Schema;
+use app::{App, Msg};
+
+mod app;
+mod draw;
+mod form;
+
+// constant
+const REFRESH_INTERVAL: u64 = 1000;
+
+type Term = Terminal<CrosstermBackend<Stdout>>;
+
+/// Run the full screen interface until the user quit
+///
+/// # Arguments
+///
+/// * `client` - Client
+/// * `schema` - Option<Schema>
+pub async fn run(client: Client, schema: Option<Schema>) -> Result<(), Error> {
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    let mut app = App::new(client, schema, tx).await?;
+
+    let mut terminal = setup().map_err(|err| Error::Terminal(err.to_string()))?;
+    let res = event_loop(&mut terminal, &mut app, &mut rx).await;
+
+    // the terminal is restored even when the interface has failed
+    restore(&mut terminal).map_err(|err| Error::Terminal(err.to_string()))?;
+
+    res
+}
+
+/// Switch the terminal to the alternate screen in raw mode
+fn setup() -> Result<Term, std::io::Error> {
+    terminal::enable_raw_mode()?;
+    execute!(stdout(), EnterAlternateScreen)?;
+
+    Terminal::new(CrosstermBackend::new(stdout()))
+}
+
+/// Restore the terminal as it was before the interface started
+///
+/// # Arguments
+///
+/// * `terminal` - &mut Term
+fn restore(terminal: &mut Term) -> Result<(), std::io::Error> {
+    terminal::disable_raw_mode()?;
+    execute!(terminal.backend_mut(), LeaveAlternateScreen)?;
+
+    terminal.show_cursor()
+}
+
+/// Redraw the interface whenever a key is pressed, a background task send a message,
+/// or periodically in order to refresh the durations of the running jobs
+///
+/// # Arguments
+///
+/// * `terminal` - &mut Term
+/// * `app` - &mut App
+/// * `rx` - &mut UnboundedReceiver<Msg>
+async fn event_loop(terminal: &mut Term, app: &mut App, rx: &mut UnboundedReceiver<Msg>) -> Result<(), Error> {
+    let mut events = EventStream::new();
+    let mut refresh = interval(Duration::from_millis(REFRESH_INTERVAL));
+
+    while !app.quit {
+        terminal
+            .draw(|f| draw::draw(f, app))
+            .map_err(|err| Error::Terminal(err.to_string()))?;
+
+        tokio::select! {
+            Some(event) = events.next() => {
+                match event.map_err(|err| Error::Terminal(err.to_string()))? {
+                    Event::Key(key) if key.kind == KeyEventKind::Press => app.handle_key(key).await,
+                    _ => {}
+                }
+            },
+            Some(msg) = rx.recv() => app.handle_msg(msg),
+            _ = refresh.tick() => {}
+        }
+    }
+
+    Ok(())
+}
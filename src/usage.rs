@@ -0,0 +1,124 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use crate::error::Error;
+use crate::nomad::job::Job;
+
+// Constant
+const USAGE_DIR: &str = "noumead";
+const USAGE_FILE: &str = "usage.json";
+/// Weight of a dispatch depending on its age in days. Older dispatches weight less
+const RECENCY_WEIGHTS: [(i64, u64); 4] = [(4, 100), (14, 70), (31, 50), (90, 30)];
+const OLD_WEIGHT: u64 = 10;
+
+/// Local stats of the dispatched jobs used to show the recently or frequently dispatched jobs first
+#[derive(Debug, Deserialize, Serialize, Default, Clone)]
+pub struct Usage {
+    #[serde(default)]
+    pub jobs: HashMap<String, JobUsage>
+}
+
+/// Number of dispatches of a job and the date of the last one
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq, Eq)]
+pub struct JobUsage {
+    pub count: u64,
+    pub last: DateTime<Utc>
+}
+
+/// Return the path of the usage stats in the data directory of the user
+pub fn default_path() -> Option<PathBuf> {
+    dirs::data_local_dir().map(|dir| dir.join(USAGE_DIR).join(USAGE_FILE))
+}
+
+/// Return the key of a job in the usage stats. Jobs with the same id may exist in several namespaces
+///
+/// # Arguments
+///
+/// * `job` - &Job
+fn key(job: &Job) -> String {
+    format!("{}/{}", job.namespace, job.id)
+}
+
+impl Usage {
+    /// Load the usage stats. Missing stats are returned as empty stats
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - &Path
+    pub fn load(path: &Path) -> Result<Self, Error> {
+        if !path.exists() {
+            return Ok(Usage::default());
+        }
+
+        let content = fs::read_to_string(path).map_err(|err| Error::Usage(err.to_string()))?;
+        serde_json::from_str(&content).map_err(|err| Error::Usage(err.to_string()))
+    }
+
+    /// Save the usage stats, the parent directory is created if needed
+    ///
+    /// # Arguments
+    ///
+    /// * `&self` - Usage
+    /// * `path` - &Path
+    pub fn save(&self, path: &Path) -> Result<(), Error> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).map_err(|err| Error::Usage(err.to_string()))?;
+        }
+
+        let content = serde_json::to_string(self).map_err(|err| Error::Serialize(err.to_string()))?;
+        fs::write(path, content).map_err(|err| Error::Usage(err.to_string()))
+    }
+
+    /// Record a dispatch of a job
+    ///
+    /// # Arguments
+    ///
+    /// * `&mut self` - Usage
+    /// * `job` - &Job
+    /// * `at` - DateTime<Utc>
+    pub fn record(&mut self, job: &Job, at: DateTime<Utc>) {
+        self.jobs.entry(key(job))
+            .and_modify(|usage| {
+                usage.count += 1;
+                usage.last = usage.last.max(at);
+            })
+            .or_insert(JobUsage { count: 1, last: at });
+    }
+
+    /// Return the frecency of a job, a score combining the number of dispatches with the age of the last one
+    ///
+    /// # Arguments
+    ///
+    /// * `&self` - Usage
+    /// * `job` - &Job
+    /// * `now` - DateTime<Utc>
+    pub fn frecency(&self, job: &Job, now: DateTime<Utc>) -> u64 {
+        let Some(usage) = self.jobs.get(&key(job)) else {
+            return 0;
+        };
+
+        let days = (now - usage.last).num_days();
+        let weight = RECENCY_WEIGHTS.iter()
+            .find(|(max_days, _)| days <= *max_days)
+            .map(|(_, weight)| *weight)
+            .unwrap_or(OLD_WEIGHT);
+
+        usage.count * weight
+    }
+
+    /// Return the position of the jobs sorted by frecency. Jobs with the same frecency keep their order
+    ///
+    /// # Arguments
+    ///
+    /// * `&self` - Usage
+    /// * `jobs` - &[Job]
+    /// * `now` - DateTime<Utc>
+    pub fn rank(&self, jobs: &[Job], now: DateTime<Utc>) -> Vec<usize> {
+        let mut order: Vec<usize> = (0..jobs.len()).collect();
+        order.sort_by_key(|idx| std::cmp::Reverse(self.frecency(&jobs[*idx], now)));
+
+        order
+    }
+}
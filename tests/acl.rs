@@ -0,0 +1,79 @@
+mod common;
+
+use std::sync::Arc;
+use noumead::Error;
+use noumead::nomad::acl::{self, Grant, NamespaceRule};
+use noumead::nomad::fake::FakeNomad;
+
+const DEPLOY_POLICY: &str = r#"
+namespace "default" {
+  policy = "read"
+  capabilities = ["dispatch-job", "read-logs"]
+
+  variables {
+    path "deploy/*" {
+      capabilities = ["read"]
+    }
+  }
+}
+
+namespace "prod-*" {
+  policy = "write"
+}
+
+namespace "prod-eu" {
+  capabilities = ["deny"]
+}
+"#;
+
+const READ_POLICY: &str = r#"{"namespace": {"*": {"policy": "read"}}}"#;
+
+#[test]
+fn expect_to_parse_hcl_and_json_rules() {
+    let rules = acl::namespace_rules(DEPLOY_POLICY);
+    assert_eq!(rules.len(), 3);
+    assert_eq!(rules[0], NamespaceRule {
+        namespace: "default".to_string(),
+        policy: Some("read".to_string()),
+        capabilities: vec!["dispatch-job".to_string(), "read-logs".to_string()]
+    });
+    assert_eq!(rules[1].policy.as_deref(), Some("write"));
+
+    let rules = acl::namespace_rules(READ_POLICY);
+    assert_eq!(rules, vec![NamespaceRule { namespace: "*".to_string(), policy: Some("read".to_string()), capabilities: vec![] }]);
+}
+
+#[test]
+fn expect_exact_namespace_and_deny_to_take_precedence() {
+    let mut rules = acl::namespace_rules(DEPLOY_POLICY);
+    rules.extend(acl::namespace_rules(READ_POLICY));
+
+    assert_eq!(acl::grant(&rules, "default", "dispatch-job"), Grant::Allowed);
+    assert_eq!(acl::grant(&rules, "prod-us", "dispatch-job"), Grant::Allowed);
+    assert_eq!(acl::grant(&rules, "prod-eu", "dispatch-job"), Grant::Denied);
+    assert_eq!(acl::grant(&rules, "staging", "dispatch-job"), Grant::NotGranted);
+}
+
+#[tokio::test]
+async fn expect_to_check_the_grant_of_the_token() {
+    let fake = FakeNomad::new()
+        .with_acl_token("ci", "client", &["read"], &["deployer"])
+        .with_acl_policy("read", READ_POLICY)
+        .with_acl_policy("deploy", DEPLOY_POLICY)
+        .with_acl_role("deployer", &["deploy"]);
+    let client = common::serve(Arc::new(fake));
+
+    let token = client.token_self().await.unwrap();
+    assert_eq!(token.name, "ci");
+    assert_eq!(token.roles[0].name, "deployer");
+
+    assert_eq!(client.dispatch_grant("default").await.unwrap(), Grant::Allowed);
+    assert_eq!(client.dispatch_grant("staging").await.unwrap(), Grant::NotGranted);
+
+    let management = common::serve(Arc::new(FakeNomad::new().with_acl_token("root", "management", &[], &[])));
+    assert_eq!(management.dispatch_grant("staging").await.unwrap(), Grant::Allowed);
+
+    // the ACLs are disabled
+    let disabled = common::serve(Arc::new(FakeNomad::new()));
+    assert!(matches!(disabled.token_self().await, Err(Error::Api { status: 400, .. })));
+}
@@ -0,0 +1,183 @@
+mod common;
+
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::sync::{Arc, Mutex};
+use futures::StreamExt;
+use noumead::batch::{self, Batch, RowResult};
+use noumead::nomad::fake::FakeNomad;
+use noumead::nomad::meta::REDACTED;
+use noumead::rest::{DebugHook, DebugRecord, RestOptions};
+use common::fixture::{self, Fixtures};
+
+#[test]
+fn expect_to_read_csv_and_jsonl_rows() {
+    let csv = fixture::temp_file("rows.csv", "word,count,_payload\nfoo,1,\nbar,,payload.json\n");
+    let rows = batch::read_rows(&csv).unwrap();
+
+    assert_eq!(rows.len(), 2);
+    assert_eq!(rows[0].index, 1);
+    assert_eq!(rows[0].metas, HashMap::from([("word".to_string(), "foo".to_string()), ("count".to_string(), "1".to_string())]));
+    assert_eq!(rows[0].payload, None);
+    assert_eq!(rows[1].metas, HashMap::from([("word".to_string(), "bar".to_string())]));
+    assert_eq!(rows[1].payload, Some(csv.parent().unwrap().join("payload.json")));
+
+    let jsonl = fixture::temp_file("rows.jsonl", "{\"word\": \"foo\", \"count\": 2}\n\n{\"word\": \"bar\", \"count\": null}\n");
+    let rows = batch::read_rows(&jsonl).unwrap();
+
+    assert_eq!(rows.len(), 2);
+    assert_eq!(rows[0].metas.get("count"), Some(&"2".to_string()));
+    assert_eq!(rows[1].metas.get("count"), None);
+
+    assert!(batch::read_rows(&fixture::temp_file("rows.jsonl", "[1, 2]\n")).is_err());
+    assert!(batch::read_rows(&fixture::temp_file("rows.txt", "word\nfoo\n")).is_err());
+}
+
+#[tokio::test]
+async fn expect_to_dispatch_rows_and_report_errors() {
+    let fake = Arc::new(FakeNomad::new().with_busybox().with_job_meta("busybox", "noumead.bar.type", "int"));
+    let client = common::serve(fake.clone());
+    let jobs = client.parameterized_jobs().await.unwrap();
+    let spec = client.spec(&jobs[0]).await.unwrap();
+
+    let payload = fixture::temp_file("payload.json", "{\"size\": 10}");
+    let path = fixture::temp_file("dispatch.csv", "word,bar,_payload\nfoo,1,\nbar,two,\nbaz,,payload.json\n");
+    let rows = batch::read_rows(&path).unwrap();
+
+    let runner = Batch::new(&client, &jobs[0], &spec, None);
+    let mut results: Vec<RowResult> = runner.run(rows, 2).collect().await;
+    results.sort_by_key(|res| res.row);
+
+    assert!(results[0].dispatched_job_id.is_some());
+    assert!(results[1].dispatched_job_id.is_none());
+    assert!(results[1].error.as_ref().unwrap().contains("bar"));
+    assert!(results[2].dispatched_job_id.is_some());
+
+    let dispatched = fake.dispatched();
+    assert_eq!(dispatched.len(), 2);
+
+    let baz = dispatched.iter()
+        .find(|(id, _)| Some(id) == results[2].dispatched_job_id.as_ref())
+        .unwrap();
+    assert_eq!(baz.1.payload, base64::encode(fs::read_to_string(payload).unwrap()));
+}
+
+#[tokio::test]
+async fn expect_batch_to_resume_from_results() {
+    let fake = Arc::new(FakeNomad::new().with_busybox().with_job_meta("busybox", "noumead.bar.type", "int"));
+    let client = common::serve(fake.clone());
+    let jobs = client.parameterized_jobs().await.unwrap();
+    let spec = client.spec(&jobs[0]).await.unwrap();
+
+    let path = fixture::temp_file("resume.csv", "word,bar\nfoo,1\nbar,two\n");
+    let results_path = batch::results_path(&path);
+    let _ = fs::remove_file(&results_path);
+
+    // first run, the second row fails
+    let rows = batch::read_rows(&path).unwrap();
+    let pending = batch::pending_rows(rows, &batch::read_results(&results_path).unwrap());
+    assert_eq!(pending.len(), 2);
+
+    let runner = Batch::new(&client, &jobs[0], &spec, None);
+    let mut file = batch::open_results(&results_path).unwrap();
+    for res in runner.run(pending, 1).collect::<Vec<_>>().await {
+        batch::write_result(&mut file, &res).unwrap();
+    }
+
+    // second run after fixing the file, only the failed row is dispatched
+    fs::write(&path, "word,bar\nfoo,1\nbar,2\n").unwrap();
+    let rows = batch::read_rows(&path).unwrap();
+    let pending = batch::pending_rows(rows, &batch::read_results(&results_path).unwrap());
+    assert_eq!(pending.len(), 1);
+    assert_eq!(pending[0].index, 2);
+
+    for res in runner.run(pending, 1).collect::<Vec<_>>().await {
+        batch::write_result(&mut file, &res).unwrap();
+    }
+
+    let results = batch::read_results(&results_path).unwrap();
+    assert!(results.values().all(|res| res.dispatched_job_id.is_some()));
+    assert_eq!(fake.dispatched().len(), 2);
+}
+
+#[tokio::test]
+async fn expect_rows_whose_secrets_changed_to_be_dispatched_again() {
+    let fake = Arc::new(FakeNomad::new().with_parameterized_job("deploy", &["env", "api_key"], &[], &[("run", "done")]));
+    let client = common::serve(fake.clone());
+    let jobs = client.parameterized_jobs().await.unwrap();
+    let spec = client.spec(&jobs[0]).await.unwrap();
+
+    let path = fixture::temp_file("rotate.csv", "env,api_key\nprod,old\n");
+    let results_path = batch::results_path(&path);
+    let _ = fs::remove_file(&results_path);
+
+    let runner = Batch::new(&client, &jobs[0], &spec, None).with_secrets(HashSet::from(["api_key".to_string()]));
+    let mut file = batch::open_results(&results_path).unwrap();
+    for res in runner.run(batch::read_rows(&path).unwrap(), 1).collect::<Vec<_>>().await {
+        batch::write_result(&mut file, &res).unwrap();
+    }
+
+    // the secret is not written in the results
+    assert!(!fs::read_to_string(&results_path).unwrap().contains("old"));
+    let pending = batch::pending_rows(batch::read_rows(&path).unwrap(), &batch::read_results(&results_path).unwrap());
+    assert!(pending.is_empty());
+
+    // a new value of the secret is dispatched again
+    fs::write(&path, "env,api_key\nprod,new\n").unwrap();
+    let pending = batch::pending_rows(batch::read_rows(&path).unwrap(), &batch::read_results(&results_path).unwrap());
+    assert_eq!(pending.len(), 1);
+}
+
+#[tokio::test]
+async fn expect_binary_payloads_to_be_dispatched() {
+    let fake = Arc::new(FakeNomad::new().with_busybox());
+    let client = common::serve(fake.clone());
+    let jobs = client.parameterized_jobs().await.unwrap();
+    let spec = client.spec(&jobs[0]).await.unwrap();
+
+    let bytes = [0x1f, 0x8b, 0x08, 0x00, 0xff, 0xfe];
+    fixture::temp_file("payload.gz", bytes);
+    let path = fixture::temp_file("binary.csv", "word,_payload\nfoo,payload.gz\n");
+
+    let runner = Batch::new(&client, &jobs[0], &spec, None);
+    let results: Vec<RowResult> = runner.run(batch::read_rows(&path).unwrap(), 1).collect().await;
+    assert!(results[0].dispatched_job_id.is_some(), "{results:?}");
+
+    assert_eq!(fake.dispatched()[0].1.payload, base64::encode(bytes));
+}
+
+#[tokio::test]
+async fn expect_secrets_of_the_rows_to_be_redacted_from_the_debug_records() {
+    let fake = FakeNomad::new().with_parameterized_job("deploy", &["env", "api_key"], &[], &[("run", "done")]);
+    let records: Arc<Mutex<Vec<DebugRecord>>> = Arc::new(Mutex::new(Vec::new()));
+    let collected = records.clone();
+    let options = RestOptions {
+        debug: Some(DebugHook::new(move |record| collected.lock().unwrap().push(record))),
+        ..Default::default()
+    };
+
+    let client = common::serve_with(Arc::new(fake), options);
+    let jobs = client.parameterized_jobs().await.unwrap();
+    let spec = client.spec(&jobs[0]).await.unwrap();
+
+    // the secret has the same value as a meta which is not secret, only the secret is redacted
+    let payload = fixture::temp_file("secret-payload.txt", "s3cr3t");
+    let path = fixture::temp_file("secrets.csv", "env,api_key,_payload
+s3cr3t,s3cr3t,secret-payload.txt
+");
+    let runner = Batch::new(&client, &jobs[0], &spec, None).with_secrets(HashSet::from(["api_key".to_string()]));
+
+    let results: Vec<RowResult> = runner.run(batch::read_rows(&path).unwrap(), 1).collect().await;
+    assert!(results[0].dispatched_job_id.is_some(), "{results:?}");
+    fs::remove_file(payload).unwrap();
+
+    let records = records.lock().unwrap();
+    let dispatch = records.iter()
+        .find(|r| r.title.starts_with(">>> POST") && r.title.contains("/dispatch"))
+        .unwrap();
+    let body: serde_json::Value = serde_json::from_str(dispatch.details.lines().last().unwrap()).unwrap();
+
+    assert_eq!(body["Meta"]["api_key"], REDACTED);
+    assert_eq!(body["Meta"]["env"], "s3cr3t");
+    assert_eq!(body["Payload"], REDACTED);
+}
@@ -0,0 +1,68 @@
+// each test binary uses a part of the fixtures
+#![allow(dead_code)]
+
+use std::fs;
+use std::path::PathBuf;
+use noumead::nomad::fake::FakeNomad;
+
+/// Jobs and variables shared by the tests, added to a fake Nomad
+pub trait Fixtures {
+    /// Add the parameterized job `busybox` with the required `word` meta and the optional `bar` meta,
+    /// whose task output two lines, along with the `web` service
+    fn with_busybox(self) -> Self;
+
+    /// Add the parameterized job `encode` whose metas are described by the meta conventions.
+    /// The `bucket` meta is required and has a default
+    fn with_encode(self) -> Self;
+
+    /// Add variables to the `default` and `staging` namespaces
+    fn with_variables(self) -> Self;
+}
+
+impl Fixtures for FakeNomad {
+    fn with_busybox(self) -> Self {
+        self.with_parameterized_job("busybox", &["word"], &["bar"], &[("test", "foo\nfoo lala\n")])
+            .with_job("web", "running")
+    }
+
+    fn with_encode(self) -> Self {
+        self.with_parameterized_job("encode", &["input", "quality", "bucket"], &["codec"], &[("ffmpeg", "done")])
+            .with_job_meta("encode", "noumead.input.description", "S3 key of the video to encode")
+            .with_job_meta("encode", "noumead.input.pattern", "s3://.+\\.mp4")
+            .with_job_meta("encode", "noumead.quality.choices", "480p, 720p, 1080p")
+            .with_job_meta("encode", "noumead.quality.default", "720p")
+            .with_job_meta("encode", "noumead.bucket.default", "videos")
+            .with_job_meta("encode", "noumead.codec.choices", "h264,h265")
+    }
+
+    fn with_variables(self) -> Self {
+        self.with_variable("default", "deploy/api", &[("key", "s3cr3t")])
+            .with_variable("default", "deploy/db", &[("user", "admin"), ("password", "hunter2")])
+            .with_variable("staging", "deploy/api", &[("key", "staging")])
+    }
+}
+
+/// Return a path in the temp directory of the running test binary, the directory is created if needed
+///
+/// # Arguments
+///
+/// * `name` - &str
+pub fn temp_path(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("noumead-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+
+    dir.join(name)
+}
+
+/// Write a file in the temp directory of the running test binary
+///
+/// # Arguments
+///
+/// * `name` - &str
+/// * `content` - C
+pub fn temp_file<C: AsRef<[u8]>>(name: &str, content: C) -> PathBuf {
+    let path = temp_path(name);
+    fs::write(&path, content).unwrap();
+
+    path
+}
@@ -0,0 +1,228 @@
+use std::convert::Infallible;
+use std::net::TcpListener;
+use std::sync::Arc;
+use hyper::{Body, Method, Request, Response, Server, StatusCode};
+use hyper::service::{make_service_fn, service_fn};
+use reqwest::Url;
+use serde::Serialize;
+use serde_json::Value;
+use tokio::time::Duration;
+use noumead::{Client, Error};
+use noumead::nomad::api::NomadApi;
+use noumead::nomad::dispatch::DispatchPayload;
+use noumead::nomad::stream::StdKind;
+use noumead::nomad::variable::Variable;
+use noumead::rest::RestOptions;
+
+pub mod fixture;
+
+// Constant
+const INDEX_HEADER: &str = "X-Nomad-Index";
+const JOB_PREFIX: &str = "/v1/job/";
+const ALLOCATION_PREFIX: &str = "/v1/allocation/";
+const LOGS_PREFIX: &str = "/v1/client/fs/logs/";
+const VAR_PREFIX: &str = "/v1/var/";
+const ACL_POLICY_PREFIX: &str = "/v1/acl/policy/";
+const ACL_ROLE_PREFIX: &str = "/v1/acl/role/";
+
+/// Serve the provided Nomad API on a local HTTP server which expose the same routes as Nomad.
+/// The returned Client is connected to the server
+///
+/// # Arguments
+///
+/// * `api` - Arc<dyn NomadApi>
+#[allow(dead_code)]
+pub fn serve(api: Arc<dyn NomadApi>) -> Client {
+    serve_with(api, RestOptions::default())
+}
+
+/// Serve the provided Nomad API on a local HTTP server. The returned Client is connected to the server
+/// with the provided options
+///
+/// # Arguments
+///
+/// * `api` - Arc<dyn NomadApi>
+/// * `options` - RestOptions
+#[allow(dead_code)]
+pub fn serve_with(api: Arc<dyn NomadApi>, options: RestOptions) -> Client {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    let make_svc = make_service_fn(move |_| {
+        let api = api.clone();
+        async move {
+            Ok::<_, Infallible>(service_fn(move |req| route(api.clone(), req)))
+        }
+    });
+
+    let server = Server::from_tcp(listener).unwrap().serve(make_svc);
+    tokio::spawn(server);
+
+    Client::new(Some(format!("http://{addr}")), None, options).unwrap()
+}
+
+/// Serve the provided Nomad API on a unix socket, as an agent whose address is a socket.
+/// The returned Client is connected to the socket
+///
+/// # Arguments
+///
+/// * `api` - Arc<dyn NomadApi>
+/// * `path` - &Path
+#[cfg(unix)]
+#[allow(dead_code)]
+pub fn serve_unix(api: Arc<dyn NomadApi>, path: &std::path::Path) -> Client {
+    use hyperlocal::UnixServerExt;
+
+    let make_svc = make_service_fn(move |_| {
+        let api = api.clone();
+        async move {
+            Ok::<_, Infallible>(service_fn(move |req| route(api.clone(), req)))
+        }
+    });
+
+    let server = Server::bind_unix(path).unwrap().serve(make_svc);
+    tokio::spawn(server);
+
+    Client::new(Some(format!("unix://{}", path.display())), None, RestOptions::default()).unwrap()
+}
+
+/// Route a request to the Nomad API
+///
+/// # Arguments
+///
+/// * `api` - Arc<dyn NomadApi>
+/// * `req` - Request<Body>
+async fn route(api: Arc<dyn NomadApi>, req: Request<Body>) -> Result<Response<Body>, Infallible> {
+    let url = Url::parse(&format!("http://stub{}", req.uri())).unwrap();
+    let query = |key: &str| url.query_pairs()
+        .find(|(k, _)| k == key)
+        .map(|(_, v)| v.to_string())
+        .unwrap_or_default();
+
+    let index = query("index").parse::<u64>().unwrap_or_default();
+    let namespace = query("namespace");
+    let wait = Duration::from_millis(0);
+    let path = url.path().to_string();
+    let method = req.method().clone();
+
+    let res = match (method, path.as_str()) {
+        (Method::GET, "/v1/jobs") if url.query_pairs().any(|(k, _)| k == "prefix") => {
+            reply(api.dispatched_jobs(&query("prefix"), index, wait).await)
+        },
+        (Method::GET, "/v1/jobs") => reply(api.jobs().await.map(|j| (j, 0))),
+        (Method::POST, p) if p.starts_with(JOB_PREFIX) && p.ends_with("/dispatch") => {
+            let id = &p[JOB_PREFIX.len()..p.len() - "/dispatch".len()];
+            let bytes = hyper::body::to_bytes(req.into_body()).await.unwrap();
+            let payload: DispatchPayload = serde_json::from_slice(&bytes).unwrap();
+
+            reply(api.dispatch(&namespace, id, &payload).await.map(|r| (r, 0)))
+        },
+        (Method::GET, p) if p.starts_with(JOB_PREFIX) && p.ends_with("/allocations") => {
+            let id = &p[JOB_PREFIX.len()..p.len() - "/allocations".len()];
+            reply(api.allocations(&namespace, id, index, wait).await)
+        },
+        (Method::GET, p) if p.starts_with(JOB_PREFIX) && p.ends_with("/summary") => {
+            let id = &p[JOB_PREFIX.len()..p.len() - "/summary".len()];
+            reply(api.job_summary(&namespace, id).await.map(|s| (s, 0)))
+        },
+        (Method::GET, p) if p.starts_with(JOB_PREFIX) => {
+            let id = &p[JOB_PREFIX.len()..];
+            reply(job(api.as_ref(), &namespace, id, index).await)
+        },
+        (Method::DELETE, p) if p.starts_with(JOB_PREFIX) => {
+            let purge = query("purge") == "true";
+            reply(api.deregister(&namespace, &p[JOB_PREFIX.len()..], purge).await.map(|_| (Value::Null, 0)))
+        },
+        (Method::GET, "/v1/acl/token/self") => reply(api.token_self().await.map(|t| (t, 0))),
+        (Method::GET, p) if p.starts_with(ACL_POLICY_PREFIX) => reply(api.acl_policy(&p[ACL_POLICY_PREFIX.len()..]).await.map(|p| (p, 0))),
+        (Method::GET, p) if p.starts_with(ACL_ROLE_PREFIX) => reply(api.acl_role(&p[ACL_ROLE_PREFIX.len()..]).await.map(|r| (r, 0))),
+        (Method::POST, "/v1/acl/oidc/auth-url") => {
+            let bytes = hyper::body::to_bytes(req.into_body()).await.unwrap();
+            reply(api.oidc_auth_url(&serde_json::from_slice(&bytes).unwrap()).await.map(|u| (u, 0)))
+        },
+        (Method::POST, "/v1/acl/oidc/complete-auth") => {
+            let bytes = hyper::body::to_bytes(req.into_body()).await.unwrap();
+            reply(api.oidc_complete_auth(&serde_json::from_slice(&bytes).unwrap()).await.map(|t| (t, 0)))
+        },
+        (Method::POST, "/v1/acl/login") => {
+            let bytes = hyper::body::to_bytes(req.into_body()).await.unwrap();
+            reply(api.jwt_login(&serde_json::from_slice(&bytes).unwrap()).await.map(|t| (t, 0)))
+        },
+        (Method::GET, "/v1/namespaces") => reply(api.namespaces().await.map(|n| (n, 0))),
+        (Method::GET, "/v1/vars") => reply(api.variables(&query("namespace"), &query("prefix")).await.map(|v| (v, 0))),
+        (Method::GET, p) if p.starts_with(VAR_PREFIX) => {
+            reply(api.variable(&query("namespace"), &p[VAR_PREFIX.len()..]).await.map(|v| (v, 0)))
+        },
+        (Method::PUT, p) if p.starts_with(VAR_PREFIX) => {
+            let bytes = hyper::body::to_bytes(req.into_body()).await.unwrap();
+            let variable: Variable = serde_json::from_slice(&bytes).unwrap();
+            let cas = query("cas").parse::<u64>().ok();
+
+            reply(api.put_variable(&variable, cas).await.map(|v| (v, 0)))
+        },
+        (Method::DELETE, p) if p.starts_with(VAR_PREFIX) => {
+            let cas = query("cas").parse::<u64>().ok();
+            reply(api.delete_variable(&query("namespace"), &p[VAR_PREFIX.len()..], cas).await.map(|_| (Value::Null, 0)))
+        },
+        (Method::GET, p) if p.starts_with(ALLOCATION_PREFIX) => {
+            reply(api.allocation(&p[ALLOCATION_PREFIX.len()..], index, wait).await)
+        },
+        (Method::GET, p) if p.starts_with(LOGS_PREFIX) => {
+            let kind = match query("type").as_str() {
+                "stderr" => StdKind::Stderr,
+                _ => StdKind::Stdout
+            };
+            let offset = query("offset").parse::<i64>().unwrap_or_default();
+
+            reply(api.logs(&p[LOGS_PREFIX.len()..], &query("task"), &kind, offset).await.map(|l| (l, 0)))
+        },
+        _ => Response::builder()
+            .status(StatusCode::NOT_FOUND)
+            .body(Body::from("Invalid URL"))
+            .unwrap()
+    };
+
+    Ok(res)
+}
+
+/// Merge the status and the spec of a job as the job endpoint of Nomad returns both
+///
+/// # Arguments
+///
+/// * `api` - &dyn NomadApi
+/// * `namespace` - &str
+/// * `id` - &str
+/// * `index` - u64
+async fn job(api: &dyn NomadApi, namespace: &str, id: &str, index: u64) -> Result<(Value, u64), Error> {
+    let (status, last_index) = api.job_status(namespace, id, index, Duration::from_millis(0)).await?;
+    let spec = api.spec(namespace, id).await?;
+
+    let mut value = serde_json::to_value(status).unwrap();
+    if let (Value::Object(job), Value::Object(spec)) = (&mut value, serde_json::to_value(spec).unwrap()) {
+        job.extend(spec);
+    }
+
+    Ok((value, last_index))
+}
+
+/// Convert the result of the Nomad API into an HTTP response
+///
+/// # Arguments
+///
+/// * `res` - Result<(T, u64), Error>
+fn reply<T: Serialize>(res: Result<(T, u64), Error>) -> Response<Body> {
+    match res {
+        Ok((body, index)) => Response::builder()
+            .header(INDEX_HEADER, index.to_string())
+            .body(Body::from(serde_json::to_string(&body).unwrap()))
+            .unwrap(),
+        Err(Error::Api { status, body, .. }) => Response::builder()
+            .status(status)
+            .body(Body::from(body))
+            .unwrap(),
+        Err(err) => Response::builder()
+            .status(StatusCode::INTERNAL_SERVER_ERROR)
+            .body(Body::from(err.to_string()))
+            .unwrap()
+    }
+}
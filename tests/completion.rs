@@ -0,0 +1,84 @@
+mod common;
+
+use std::sync::Arc;
+use chrono::Duration;
+use noumead::completion::{self, Candidate, CompletionCache, CompletionKind};
+use noumead::nomad::fake::FakeNomad;
+use common::fixture::{self, Fixtures};
+
+fn jobs() -> Arc<FakeNomad> {
+    let fake = FakeNomad::new()
+        .with_parameterized_job("busybox", &["word"], &[], &[("test", "done"), ("sidecar", "")])
+        .with_encode()
+        .with_variables();
+
+    Arc::new(fake)
+}
+
+fn values(candidates: &[Candidate]) -> Vec<&str> {
+    candidates.iter().map(|c| c.value.as_str()).collect()
+}
+
+#[tokio::test]
+async fn expect_to_fetch_candidates() {
+    let client = common::serve(jobs());
+
+    let jobs = completion::fetch(&client, CompletionKind::Jobs, None).await.unwrap();
+    assert_eq!(values(&jobs), vec!["busybox", "encode"]);
+    assert_eq!(jobs[0].help.as_deref(), Some("default"));
+
+    let namespaces = completion::fetch(&client, CompletionKind::Namespaces, None).await.unwrap();
+    assert_eq!(values(&namespaces), vec!["default", "staging"]);
+
+    let tasks = completion::fetch(&client, CompletionKind::Tasks, None).await.unwrap();
+    assert_eq!(values(&tasks), vec!["ffmpeg", "sidecar", "test"]);
+}
+
+#[tokio::test]
+async fn expect_candidates_to_be_cached_per_address() {
+    let dir = fixture::temp_path("completion");
+    let _ = std::fs::remove_dir_all(&dir);
+
+    let client = common::serve(jobs());
+    let other = common::serve(Arc::new(FakeNomad::new().with_parameterized_job("report", &[], &[], &[("report", "done")])));
+
+    let cache = CompletionCache::new(dir.clone(), Duration::seconds(60));
+    let jobs = cache.candidates(&client, "http://prod:4646", CompletionKind::Jobs, None).await.unwrap();
+    assert_eq!(jobs.len(), 2);
+
+    // the candidates of the address are read from the cache
+    let cached = cache.candidates(&other, "http://prod:4646", CompletionKind::Jobs, None).await.unwrap();
+    assert_eq!(cached, jobs);
+
+    let staging = cache.candidates(&other, "http://staging:4646", CompletionKind::Jobs, None).await.unwrap();
+    assert_eq!(values(&staging), vec!["report"]);
+
+    // expired candidates are fetched again
+    let expired = CompletionCache::new(dir, Duration::zero());
+    let fetched = expired.candidates(&other, "http://prod:4646", CompletionKind::Jobs, None).await.unwrap();
+    assert_eq!(values(&fetched), vec!["report"]);
+}
+
+#[tokio::test]
+async fn expect_tasks_to_be_limited_to_the_typed_job() {
+    let client = common::serve(jobs());
+
+    let tasks = completion::fetch(&client, CompletionKind::Tasks, Some("busybox")).await.unwrap();
+    assert_eq!(values(&tasks), vec!["sidecar", "test"]);
+
+    let tasks = completion::fetch(&client, CompletionKind::Tasks, Some("unknown")).await.unwrap();
+    assert!(tasks.is_empty());
+}
+
+#[test]
+fn expect_to_read_the_typed_values() {
+    let words: Vec<String> = ["noumead", "--context=prod", "dispatch", "-j", "busybox", "--job", "encode", "--task", ""]
+        .iter()
+        .map(|w| w.to_string())
+        .collect();
+
+    assert_eq!(completion::typed_value(&words, &["--job", "-j"]).as_deref(), Some("encode"));
+    assert_eq!(completion::typed_value(&words, &["--context"]).as_deref(), Some("prod"));
+    assert_eq!(completion::typed_value(&words, &["--task"]), None);
+    assert_eq!(completion::typed_value(&words, &["--nomad-url", "-n"]), None);
+}
@@ -0,0 +1,135 @@
+mod common;
+
+use noumead::config::{self, Config, ConnectionArgs, TokenSources};
+use noumead::error::Error;
+use common::fixture::temp_file;
+
+#[test]
+fn expect_context_to_be_selected_from_the_config() {
+    let path = temp_file("config.toml", r#"
+context = "staging"
+
+[contexts.staging]
+address = "http://staging:4646"
+
+[contexts.prod]
+address = "https://prod:4646"
+token_command = "pass show nomad/prod"
+"#);
+
+    let config = Config::load(&path).unwrap();
+
+    let default = config.context(None).unwrap().unwrap();
+    assert_eq!(default.address.as_deref(), Some("http://staging:4646"));
+
+    let prod = config.context(Some("prod")).unwrap().unwrap();
+    assert_eq!(prod.token_command.as_deref(), Some("pass show nomad/prod"));
+
+    assert!(matches!(config.context(Some("dev")), Err(Error::Config(_))));
+
+    // no config and no context selected is not an error
+    let empty = Config::load(&path.with_file_name("missing.toml")).unwrap();
+    assert!(empty.context(None).unwrap().is_none());
+}
+
+#[test]
+fn expect_token_to_be_read_from_a_file() {
+    let path = temp_file("token", "s3cr3t\n");
+    assert_eq!(config::token_from_file(&path).unwrap(), "s3cr3t");
+
+    let empty = temp_file("empty-token", "\n");
+    assert!(matches!(config::token_from_file(&empty), Err(Error::Token(_))));
+}
+
+#[test]
+fn expect_token_to_be_read_from_a_command() {
+    assert_eq!(config::token_from_command("echo s3cr3t").unwrap(), "s3cr3t");
+
+    assert!(matches!(config::token_from_command("exit 3"), Err(Error::Token(_))));
+}
+
+fn contexts() -> Config {
+    let path = temp_file("contexts.toml", r#"
+context = "staging"
+
+[contexts.staging]
+address = "http://staging:4646"
+token_command = "echo staging-token"
+
+[contexts.prod]
+address = "https://prod:4646"
+token_command = "echo prod-token"
+
+[contexts.prod.headers]
+X-Gateway-Key = "gateway"
+"#);
+
+    Config::load(&path).unwrap()
+}
+
+fn env(var: &str) -> Option<String> {
+    match var {
+        config::NOMAD_ADDR_ENV => Some("http://env:4646".to_string()),
+        config::NOMAD_TOKEN_ENV => Some("env-token".to_string()),
+        _ => None
+    }
+}
+
+#[test]
+fn expect_an_explicit_context_to_take_precedence_over_the_environment() {
+    let config = contexts();
+
+    // the environment takes precedence over the default context
+    let default = config.connection(ConnectionArgs::default(), env, TokenSources::All).unwrap();
+    assert_eq!(default.context.as_deref(), Some("staging"));
+    assert_eq!(default.address.as_deref(), Some("http://env:4646"));
+    assert_eq!(default.token.as_deref(), Some("env-token"));
+
+    // the environment is ignored when the context is given
+    let args = ConnectionArgs { context: Some("prod".to_string()), ..Default::default() };
+    let prod = config.connection(args, env, TokenSources::All).unwrap();
+    assert_eq!(prod.address.as_deref(), Some("https://prod:4646"));
+    assert_eq!(prod.token.as_deref(), Some("prod-token"));
+    assert_eq!(prod.headers, vec![("X-Gateway-Key".to_string(), "gateway".to_string())]);
+
+    // the flags take precedence over everything
+    let args = ConnectionArgs {
+        context: Some("prod".to_string()),
+        address: Some("http://flag:4646".to_string()),
+        token: Some("flag-token".to_string()),
+        headers: vec![("x-gateway-key".to_string(), "flag".to_string())],
+        ..Default::default()
+    };
+    let flags = config.connection(args, env, TokenSources::All).unwrap();
+    assert_eq!(flags.address.as_deref(), Some("http://flag:4646"));
+    assert_eq!(flags.token.as_deref(), Some("flag-token"));
+    assert_eq!(flags.headers, vec![("x-gateway-key".to_string(), "flag".to_string())]);
+
+    let missing = ConnectionArgs { context: Some("dev".to_string()), ..Default::default() };
+    assert!(matches!(config.connection(missing, env, TokenSources::All), Err(Error::Config(_))));
+}
+
+#[test]
+fn expect_the_token_not_to_be_read_when_it_is_not_needed() {
+    let failing = temp_file("failing.toml", r#"
+[contexts.prod]
+address = "https://prod:4646"
+token_command = "exit 3"
+"#);
+    let failing = Config::load(&failing).unwrap();
+
+    // a failing token command does not prevent the login from storing a new token
+    let args = ConnectionArgs { context: Some("prod".to_string()), ..Default::default() };
+    let login = failing.connection(args.clone(), env, TokenSources::None).unwrap();
+    assert_eq!(login.address.as_deref(), Some("https://prod:4646"));
+    assert_eq!(login.token, None);
+    assert!(matches!(failing.connection(args, env, TokenSources::All), Err(Error::Token(_))));
+
+    // the completion never runs the token command
+    let args = ConnectionArgs { context: Some("prod".to_string()), ..Default::default() };
+    let completion = failing.connection(args, env, TokenSources::Cached).unwrap();
+    assert_eq!(completion.token, None);
+
+    let args = ConnectionArgs { token: Some("flag-token".to_string()), ..Default::default() };
+    assert_eq!(failing.connection(args, env, TokenSources::Cached).unwrap().token.as_deref(), Some("flag-token"));
+}
@@ -0,0 +1,236 @@
+mod common;
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use futures::StreamExt;
+use noumead::Error;
+use noumead::nomad::dispatch::{self, DispatchPayload};
+use noumead::nomad::fake::FakeNomad;
+use noumead::nomad::job;
+use noumead::nomad::stream::StdKind;
+use common::fixture::Fixtures;
+
+#[tokio::test]
+async fn expect_to_dispatch_parameterized_job() {
+    let fake = Arc::new(FakeNomad::new().with_busybox());
+    let client = common::serve(fake.clone());
+
+    let jobs = client.parameterized_jobs().await.unwrap();
+    assert_eq!(jobs.len(), 1);
+
+    let job = &jobs[0];
+    let spec = client.spec(job).await.unwrap();
+    assert_eq!(spec.parameterized.meta_required, Some(vec!["word".to_string()]));
+    assert_eq!(spec.parameterized.meta_optional, Some(vec!["bar".to_string()]));
+
+    let metas = HashMap::from([("word".to_string(), "foo".to_string())]);
+    let res = client.dispatch(job, metas).await.unwrap();
+    assert!(res.dispatch_id.starts_with("busybox/dispatch-"));
+
+    let dispatched = fake.dispatched();
+    assert_eq!(dispatched.len(), 1);
+    assert_eq!(dispatched[0].0, res.dispatch_id);
+    assert_eq!(dispatched[0].1.metas.get("word"), Some(&"foo".to_string()));
+}
+
+#[tokio::test]
+async fn expect_dispatch_to_return_nomad_error() {
+    let client = common::serve(Arc::new(FakeNomad::new().with_busybox()));
+
+    let jobs = client.parameterized_jobs().await.unwrap();
+    let err = client.dispatch(&jobs[0], HashMap::new()).await.unwrap_err();
+
+    match err {
+        Error::Api { status, method, endpoint, body } => {
+            assert_eq!(status, 400);
+            assert_eq!(method, "POST");
+            assert_eq!(endpoint, "/v1/job/busybox/dispatch?namespace=default");
+            assert!(body.contains("word"));
+        },
+        err => panic!("unexpected error {err}")
+    }
+}
+
+#[tokio::test]
+async fn expect_to_follow_dispatched_job() {
+    let client = common::serve(Arc::new(FakeNomad::new().with_busybox()));
+
+    let jobs = client.parameterized_jobs().await.unwrap();
+    let metas = HashMap::from([("word".to_string(), "foo".to_string())]);
+    let res = client.dispatch(&jobs[0], metas).await.unwrap();
+
+    let alloc = client.allocation("default", &res.dispatch_id).await.unwrap();
+    assert_eq!(alloc.get_tasks_name(), vec!["test"]);
+
+    let events: Vec<_> = client.follow_logs(&alloc, "test")
+        .map(Result::unwrap)
+        .collect()
+        .await;
+
+    let lines: Vec<&str> = events.iter().map(|e| e.line.as_str()).collect();
+    assert_eq!(lines, vec!["foo", "foo lala"]);
+    assert!(events.iter().all(|e| e.stream == StdKind::Stdout && e.task == "test" && e.alloc == alloc.alloc_id));
+
+    let event = serde_json::to_value(&events[0]).unwrap();
+    let mut keys: Vec<&String> = event.as_object().unwrap().keys().collect();
+    keys.sort();
+    assert_eq!(keys, vec!["alloc", "line", "stream", "task", "ts"]);
+    assert_eq!(event["stream"], "stdout");
+
+    let status = client.wait_for_dead_job("default", &res.dispatch_id).await.unwrap();
+    assert_eq!(status.status, "dead");
+}
+
+#[tokio::test]
+async fn expect_to_use_fake_without_http() {
+    let client = noumead::Client::with_api(Arc::new(FakeNomad::new().with_busybox()));
+
+    let jobs = client.parameterized_jobs().await.unwrap();
+    let metas = HashMap::from([("word".to_string(), "foo".to_string())]);
+    let res = client.dispatch(&jobs[0], metas).await.unwrap();
+
+    let alloc = client.allocation("default", &res.dispatch_id).await.unwrap();
+    let count = client.follow_logs(&alloc, "test").count().await;
+
+    assert_eq!(count, 2);
+}
+
+#[tokio::test]
+async fn expect_idempotent_dispatch_to_return_existing_job() {
+    let fake = Arc::new(FakeNomad::new().with_busybox());
+    let client = common::serve(fake.clone());
+    let jobs = client.parameterized_jobs().await.unwrap();
+
+    let metas = HashMap::from([("word".to_string(), "foo".to_string())]);
+    let token = dispatch::idempotency_token(&jobs[0].id, &metas, None);
+    assert_eq!(token, dispatch::idempotency_token(&jobs[0].id, &metas.clone(), None));
+    assert_ne!(token, dispatch::idempotency_token(&jobs[0].id, &metas, Some("payload".as_bytes())));
+
+    let payload = DispatchPayload::new(metas, None).unwrap().with_idempotency_token(Some(token));
+    let res = client.send_dispatch(&jobs[0], &payload).await.unwrap();
+
+    match client.send_dispatch(&jobs[0], &payload).await {
+        Err(Error::AlreadyDispatched { job }) => assert_eq!(job, res.dispatch_id),
+        res => panic!("unexpected result {res:?}")
+    }
+
+    assert_eq!(fake.dispatched().len(), 1);
+    assert_eq!(fake.dispatched()[0].1.idempotency_token, payload.idempotency_token);
+}
+
+#[tokio::test]
+async fn expect_new_dispatch_without_evaluation_not_to_be_reported_as_existing() {
+    let fake = Arc::new(FakeNomad::new()
+        .with_parameterized_job("report", &["word"], &[], &[("test", "foo\n")])
+        .periodic());
+    let client = common::serve(fake.clone());
+    let jobs = client.parameterized_jobs().await.unwrap();
+
+    let metas = HashMap::from([("word".to_string(), "foo".to_string())]);
+    let payload = DispatchPayload::new(metas, None).unwrap().with_idempotency_token(Some("token".to_string()));
+    let res = client.send_dispatch(&jobs[0], &payload).await.unwrap();
+    assert!(res.eval_id.is_empty());
+
+    match client.send_dispatch(&jobs[0], &payload).await {
+        Err(Error::AlreadyDispatched { job }) => assert_eq!(job, res.dispatch_id),
+        res => panic!("unexpected result {res:?}")
+    }
+
+    // another token dispatches a new job
+    let other = payload.with_idempotency_token(Some("other".to_string()));
+    assert_ne!(client.send_dispatch(&jobs[0], &other).await.unwrap().dispatch_id, res.dispatch_id);
+}
+
+#[test]
+fn expect_payload_to_be_decoded() {
+    let metas = HashMap::from([("word".to_string(), "foo".to_string())]);
+
+    let payload = DispatchPayload::new(metas.clone(), None).unwrap();
+    assert_eq!(payload.decoded_payload(), Some("{\"word\":\"foo\"}".to_string()));
+
+    let payload = DispatchPayload::new(metas, Some("hello".to_string())).unwrap();
+    assert_eq!(payload.decoded_payload(), Some("hello".to_string()));
+}
+
+#[tokio::test]
+async fn expect_jobs_to_be_looked_up_by_id_rather_than_by_name() {
+    let fake = FakeNomad::new()
+        .with_parameterized_job("nightly-report", &["date"], &[], &[("main", "report\n")])
+        .named("Nightly report");
+    let client = common::serve(Arc::new(fake));
+
+    let jobs = client.parameterized_jobs().await.unwrap();
+    assert_eq!((jobs[0].id.as_str(), jobs[0].name.as_str()), ("nightly-report", "Nightly report"));
+
+    let spec = client.spec(&jobs[0]).await.unwrap();
+    assert_eq!(spec.id, "nightly-report");
+
+    let (required, _) = jobs[0].get_job_meta(client.api()).await.unwrap();
+    assert_eq!(required, Some(vec!["date".to_string()]));
+
+    let metas = HashMap::from([("date".to_string(), "2023-01-31".to_string())]);
+    let res = client.dispatch(&jobs[0], metas).await.unwrap();
+    assert!(res.dispatch_id.starts_with("nightly-report/dispatch-"), "{}", res.dispatch_id);
+}
+
+#[tokio::test]
+async fn expect_jobs_sharing_an_id_to_be_told_apart_by_namespace() {
+    let fake = FakeNomad::new()
+        .with_parameterized_job("report", &["date"], &[], &[("main", "default report\n")])
+        .with_parameterized_job("report", &[], &["team"], &[("main", "billing report\n")])
+        .in_namespace("billing");
+    let client = common::serve(Arc::new(fake));
+
+    let jobs = client.parameterized_jobs().await.unwrap();
+    let billing = jobs.iter().find(|j| j.namespace == "billing").unwrap();
+
+    let spec = client.spec(billing).await.unwrap();
+    assert_eq!(spec.namespace, "billing");
+    assert_eq!(spec.parameterized.meta_required, None);
+
+    // the meta required by the job of the default namespace is not required in billing
+    let metas = HashMap::from([("team".to_string(), "ops".to_string())]);
+    let res = client.dispatch(billing, metas).await.unwrap();
+
+    let alloc = client.allocation("billing", &res.dispatch_id).await.unwrap();
+    let lines: Vec<String> = client.follow_logs(&alloc, "main")
+        .map(|e| e.unwrap().line)
+        .collect()
+        .await;
+    assert_eq!(lines, vec!["billing report"]);
+
+    let err = client.allocation("default", &res.dispatch_id).await.unwrap_err();
+    assert!(matches!(err, Error::Api { status: 404, .. }));
+
+    let (status, _) = client.dispatch_status(Some("report"), None, 0, tokio::time::Duration::ZERO).await.unwrap();
+    assert_eq!(status.len(), 1);
+    assert_eq!(status[0].namespace, "billing");
+
+    let default = client.inspect("default", "report").await.unwrap();
+    assert_eq!(default.parameterized.meta_required, Some(vec!["date".to_string()]));
+
+    // stopping the job of billing leaves the job of the default namespace running
+    client.stop(std::slice::from_ref(billing)).await.into_iter().for_each(Result::unwrap);
+    let jobs = client.jobs().await.unwrap();
+    let status = |ns: &str| jobs.iter().find(|j| j.id == "report" && j.namespace == ns).unwrap().status.clone();
+    assert_eq!(status("billing"), "dead");
+    assert_eq!(status("default"), "running");
+}
+
+#[tokio::test]
+async fn expect_namespace_to_be_required_when_jobs_share_an_id() {
+    let fake = FakeNomad::new()
+        .with_parameterized_job("report", &[], &[], &[("main", "")])
+        .with_parameterized_job("report", &[], &[], &[("main", "")])
+        .in_namespace("billing")
+        .with_parameterized_job("busybox", &[], &[], &[("main", "")]);
+    let jobs = noumead::Client::with_api(Arc::new(fake)).parameterized_jobs().await.unwrap();
+
+    let err = job::find_job(&jobs, None, "report").unwrap_err();
+    assert!(err.to_string().contains("default, billing"));
+
+    let report = job::find_job(&jobs, Some("billing"), "report").unwrap();
+    assert_eq!(report.namespace, "billing");
+    assert_eq!(job::find_job(&jobs, None, "busybox").unwrap().namespace, "default");
+    assert!(job::find_job(&jobs, Some("billing"), "busybox").is_err());
+}
@@ -0,0 +1,76 @@
+mod common;
+
+use std::sync::Arc;
+use noumead::Error;
+use noumead::nomad::fake::FakeNomad;
+use noumead::nomad::spec::Spec;
+use common::fixture::Fixtures;
+
+#[tokio::test]
+async fn expect_to_inspect_parameterized_job() {
+    let client = common::serve(Arc::new(FakeNomad::new().with_busybox()));
+
+    let spec = client.inspect("default", "busybox").await.unwrap();
+    assert_eq!(spec.id, "busybox");
+    assert_eq!(spec.datacenters, vec!["dc1"]);
+    assert_eq!(spec.parameterized.payload, "optional");
+    assert_eq!(spec.parameterized.meta_required, Some(vec!["word".to_string()]));
+    assert_eq!(spec.parameterized.meta_optional, Some(vec!["bar".to_string()]));
+
+    let task = &spec.task_groups[0].tasks[0];
+    assert_eq!(task.name, "test");
+    assert_eq!(task.driver, "docker");
+    assert_eq!(task.image(), Some("busybox:latest"));
+    assert_eq!(task.dispatch_payload.as_ref().map(|p| p.file.as_str()), Some("local/payload.json"));
+}
+
+#[tokio::test]
+async fn expect_inspect_of_missing_job_to_fail() {
+    let client = common::serve(Arc::new(FakeNomad::new()));
+
+    let err = client.inspect("default", "unknown").await.unwrap_err();
+    assert!(matches!(err, Error::Api { status: 404, .. }));
+}
+
+#[test]
+fn expect_to_deserialize_nomad_job() {
+    let job = r#"{
+        "ID": "encode",
+        "Name": "encode",
+        "Namespace": "media",
+        "Type": "batch",
+        "Datacenters": ["eu-west-1a", "eu-west-1b"],
+        "Meta": {"quality": "720p"},
+        "ParameterizedJob": {"Payload": "required", "MetaRequired": ["input"], "MetaOptional": null},
+        "TaskGroups": [{
+            "Name": "encoder",
+            "Count": 1,
+            "Meta": null,
+            "Tasks": [{
+                "Name": "ffmpeg",
+                "Driver": "docker",
+                "Config": {"image": "jrottenberg/ffmpeg:6", "args": ["-i", "${NOMAD_META_input}"]},
+                "DispatchPayload": {"File": "input/settings.json"},
+                "Meta": {"threads": "4"}
+            }, {
+                "Name": "upload",
+                "Driver": "exec",
+                "Config": {"command": "upload.sh"},
+                "DispatchPayload": null,
+                "Meta": null
+            }]
+        }]
+    }"#;
+
+    let spec: Spec = serde_json::from_str(job).unwrap();
+    assert_eq!(spec.namespace, "media");
+    assert_eq!(spec.metas.get("quality"), Some(&"720p".to_string()));
+    assert_eq!(spec.parameterized.payload, "required");
+    assert_eq!(spec.parameterized.meta_optional, None);
+
+    let tasks = &spec.task_groups[0].tasks;
+    assert_eq!(tasks[0].image(), Some("jrottenberg/ffmpeg:6"));
+    assert_eq!(tasks[0].metas.get("threads"), Some(&"4".to_string()));
+    assert_eq!(tasks[1].image(), None);
+    assert!(tasks[1].dispatch_payload.is_none());
+}
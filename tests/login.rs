@@ -0,0 +1,82 @@
+mod common;
+
+use std::sync::Arc;
+use noumead::Error;
+use noumead::login::CallbackListener;
+use noumead::nomad::fake::FakeNomad;
+use reqwest::Url;
+use tokio::net::TcpStream;
+
+const OIDC_METHOD: &str = "okta";
+const OIDC_CODE: &str = "code-123";
+const JWT_METHOD: &str = "gitlab";
+const JWT: &str = "eyJhbGciOiJSUzI1NiJ9.eyJzdWIiOiJjaSJ9.c2ln";
+
+/// Simulate the browser following the redirection of the OIDC provider to the callback listener
+fn redirect(auth_url: &str, code: &str) {
+    let url = Url::parse(auth_url).unwrap();
+    let param = |key: &str| url.query_pairs().find(|(k, _)| k == key).unwrap().1.to_string();
+
+    let callback = Url::parse_with_params(&param("redirect_uri"), [("code", code), ("state", &param("state"))]).unwrap();
+    tokio::spawn(async move {
+        let res = reqwest::get(callback).await.unwrap();
+        assert!(res.status().is_success());
+    });
+}
+
+#[tokio::test]
+async fn expect_oidc_login_to_exchange_the_code_for_a_token() {
+    let client = common::serve(Arc::new(FakeNomad::default().with_auth_method(OIDC_METHOD, OIDC_CODE)));
+    let listener = CallbackListener::bind(0).await.unwrap();
+    assert!(listener.redirect_uri().ends_with("/oidc/callback"));
+
+    let token = client.oidc_login(OIDC_METHOD, listener, |url| redirect(url, OIDC_CODE)).await.unwrap();
+    assert_eq!(token.secret_id, "secret-okta");
+    assert!(!token.is_expired(chrono::Utc::now()));
+
+    // the code is checked by Nomad
+    let listener = CallbackListener::bind(0).await.unwrap();
+    let res = client.oidc_login(OIDC_METHOD, listener, |url| redirect(url, "forged")).await;
+    assert!(matches!(res, Err(Error::Api { status: 403, .. })));
+}
+
+#[tokio::test]
+async fn expect_jwt_login_to_exchange_the_jwt_for_a_token() {
+    let client = common::serve(Arc::new(FakeNomad::default().with_auth_method(JWT_METHOD, JWT)));
+
+    let token = client.jwt_login(JWT_METHOD, &format!("{JWT}\n")).await.unwrap();
+    assert_eq!(token.secret_id, "secret-gitlab");
+
+    let res = client.jwt_login("unknown", JWT).await;
+    assert!(matches!(res, Err(Error::Api { status: 400, .. })));
+}
+
+#[tokio::test]
+async fn expect_callback_to_report_the_error_of_the_provider() {
+    let listener = CallbackListener::bind(0).await.unwrap();
+    let base = Url::parse(listener.redirect_uri()).unwrap();
+    let wait = tokio::spawn(listener.wait());
+
+    // requests which are not the redirection are ignored
+    let favicon = reqwest::get(base.join("/favicon.ico").unwrap()).await.unwrap();
+    assert_eq!(favicon.status(), 404);
+
+    let denied = reqwest::get(format!("{base}?error=access_denied")).await.unwrap();
+    assert_eq!(denied.status(), 400);
+
+    assert!(matches!(wait.await.unwrap(), Err(Error::Login(msg)) if msg == "access_denied"));
+}
+
+#[tokio::test]
+async fn expect_idle_connections_not_to_hold_the_callback() {
+    let client = common::serve(Arc::new(FakeNomad::default().with_auth_method(OIDC_METHOD, OIDC_CODE)));
+    let listener = CallbackListener::bind(0).await.unwrap();
+    let port = Url::parse(listener.redirect_uri()).unwrap().port().unwrap();
+
+    // the browser may open connections ahead without sending any request
+    let _idle = TcpStream::connect(("127.0.0.1", port)).await.unwrap();
+    let login = client.oidc_login(OIDC_METHOD, listener, |url| redirect(url, OIDC_CODE));
+
+    let token = tokio::time::timeout(std::time::Duration::from_secs(5), login).await.unwrap().unwrap();
+    assert_eq!(token.secret_id, "secret-okta");
+}
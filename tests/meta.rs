@@ -0,0 +1,226 @@
+mod common;
+
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use std::time::Duration;
+use noumead::Error;
+use noumead::nomad::dispatch::DispatchPayload;
+use noumead::nomad::fake::FakeNomad;
+use noumead::nomad::meta::{self, format_duration, parse_duration, MetaPrompt, SecretKeys};
+use noumead::nomad::spec;
+use common::fixture::Fixtures;
+
+fn metas(items: &[(&str, &str)]) -> HashMap<String, String> {
+    items.iter()
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect()
+}
+
+#[tokio::test]
+async fn expect_to_parse_meta_conventions() {
+    let client = common::serve(Arc::new(FakeNomad::new().with_encode()));
+    let spec = client.inspect("default", "encode").await.unwrap();
+
+    let fields = spec.meta_fields().unwrap();
+    let names: Vec<&str> = fields.iter().map(|f| f.name.as_str()).collect();
+    assert_eq!(names, vec!["input", "quality", "bucket", "codec"]);
+
+    assert!(fields[0].required);
+    assert_eq!(fields[0].description.as_deref(), Some("S3 key of the video to encode"));
+    assert_eq!(fields[1].default.as_deref(), Some("720p"));
+    assert_eq!(fields[1].choices, vec!["480p", "720p", "1080p"]);
+    assert_eq!(fields[2].default.as_deref(), Some("videos"));
+    assert!(!fields[3].required);
+}
+
+#[tokio::test]
+async fn expect_to_resolve_metas_with_defaults() {
+    let client = common::serve(Arc::new(FakeNomad::new().with_encode()));
+    let spec = client.inspect("default", "encode").await.unwrap();
+
+    let resolved = spec.resolve_metas(metas(&[("input", "s3://videos/cat.mp4"), ("codec", "")])).unwrap();
+    assert_eq!(resolved, metas(&[("input", "s3://videos/cat.mp4"), ("quality", "720p"), ("bucket", "videos")]));
+}
+
+#[tokio::test]
+async fn expect_invalid_metas_to_be_rejected() {
+    let client = common::serve(Arc::new(FakeNomad::new().with_encode()));
+    let spec = client.inspect("default", "encode").await.unwrap();
+
+    let cases = [
+        (metas(&[("input", "videos/cat.mp4")]), "input"),
+        (metas(&[("input", "s3://videos/cat.mp4"), ("quality", "4k")]), "quality"),
+        (metas(&[("input", "s3://videos/cat.mp4"), ("codec", "vp9")]), "codec"),
+        (metas(&[("input", "s3://videos/cat.mp4"), ("bitrate", "1M")]), "bitrate"),
+        (metas(&[]), "input")
+    ];
+
+    for (metas, expected) in cases {
+        match spec.resolve_metas(metas) {
+            Err(Error::InvalidMeta { key, .. }) => assert_eq!(key, expected),
+            res => panic!("unexpected result {res:?} for {expected}")
+        }
+    }
+}
+
+#[tokio::test]
+async fn expect_invalid_pattern_to_be_reported() {
+    let fake = FakeNomad::new()
+        .with_parameterized_job("busybox", &["word"], &[], &[("test", "foo")])
+        .with_job_meta("busybox", "noumead.word.pattern", "[a-z");
+    let client = common::serve(Arc::new(fake));
+
+    let spec = client.inspect("default", "busybox").await.unwrap();
+    assert!(matches!(spec.meta_fields(), Err(Error::InvalidMeta { .. })));
+}
+
+#[tokio::test]
+async fn expect_typed_metas_to_be_normalized() {
+    let fake = FakeNomad::new()
+        .with_parameterized_job("report", &["count", "ratio", "dry", "timeout", "day", "filters"], &[], &[("run", "ok")])
+        .with_job_meta("report", "noumead.count.type", "int")
+        .with_job_meta("report", "noumead.ratio.type", "float")
+        .with_job_meta("report", "noumead.dry.type", "bool")
+        .with_job_meta("report", "noumead.timeout.type", "duration")
+        .with_job_meta("report", "noumead.day.type", "date")
+        .with_job_meta("report", "noumead.filters.type", "json");
+    let client = common::serve(Arc::new(fake));
+    let spec = client.inspect("default", "report").await.unwrap();
+
+    let resolved = spec.resolve_metas(metas(&[
+        ("count", " 10 "),
+        ("ratio", "0.50"),
+        ("dry", "Yes"),
+        ("timeout", "90m"),
+        ("day", "2023-01-31"),
+        ("filters", "{ \"env\": [\"prod\"] }")
+    ])).unwrap();
+
+    assert_eq!(resolved, metas(&[
+        ("count", "10"),
+        ("ratio", "0.5"),
+        ("dry", "true"),
+        ("timeout", "1h30m0s"),
+        ("day", "2023-01-31"),
+        ("filters", "{\"env\":[\"prod\"]}")
+    ]));
+
+    let invalid = [("count", "1O"), ("ratio", "half"), ("dry", "maybe"), ("timeout", "10"), ("day", "31/01/2023"), ("filters", "{env}")];
+    for (key, value) in invalid {
+        let mut values = resolved.clone();
+        values.insert(key.to_string(), value.to_string());
+
+        match spec.resolve_metas(values) {
+            Err(Error::InvalidMeta { key: k, .. }) => assert_eq!(k, key),
+            res => panic!("unexpected result {res:?} for {key}")
+        }
+    }
+}
+
+#[tokio::test]
+async fn expect_typed_metas_to_have_typed_prompts() {
+    let fake = FakeNomad::new()
+        .with_parameterized_job("report", &["count", "ratio", "dry", "day", "filters", "name", "level", "key"], &[], &[("run", "ok")])
+        .with_job_meta("report", "noumead.count.type", "int")
+        .with_job_meta("report", "noumead.ratio.type", "float")
+        .with_job_meta("report", "noumead.dry.type", "bool")
+        .with_job_meta("report", "noumead.day.type", "date")
+        .with_job_meta("report", "noumead.filters.type", "json")
+        .with_job_meta("report", "noumead.level.type", "int")
+        .with_job_meta("report", "noumead.level.choices", "1,2,3")
+        .with_job_meta("report", "noumead.key.type", "int")
+        .with_job_meta("report", "noumead.key.secret", "true");
+    let client = common::serve(Arc::new(fake));
+    let spec = client.inspect("default", "report").await.unwrap();
+
+    let prompts: Vec<(String, MetaPrompt)> = spec.meta_fields().unwrap()
+        .into_iter()
+        .map(|field| (field.name.clone(), field.prompt()))
+        .collect();
+
+    // the choices and the secrets take precedence over the type
+    assert_eq!(prompts, vec![
+        ("count".to_string(), MetaPrompt::Int),
+        ("ratio".to_string(), MetaPrompt::Float),
+        ("dry".to_string(), MetaPrompt::Confirm),
+        ("day".to_string(), MetaPrompt::DateSelect),
+        ("filters".to_string(), MetaPrompt::Editor),
+        ("name".to_string(), MetaPrompt::Text),
+        ("level".to_string(), MetaPrompt::Select),
+        ("key".to_string(), MetaPrompt::Password)
+    ]);
+}
+
+#[tokio::test]
+async fn expect_unknown_type_to_be_reported() {
+    let fake = FakeNomad::new()
+        .with_parameterized_job("busybox", &["word"], &[], &[("test", "foo")])
+        .with_job_meta("busybox", "noumead.word.type", "uuid");
+    let client = common::serve(Arc::new(fake));
+
+    let spec = client.inspect("default", "busybox").await.unwrap();
+    assert!(matches!(spec.meta_fields(), Err(Error::InvalidMeta { .. })));
+}
+
+#[test]
+fn expect_durations_to_use_the_go_syntax() {
+    let cases = [("0", "0s"), ("1.5s", "1.5s"), ("300ms", "300ms"), ("2h", "2h0m0s"), ("1h30m15s", "1h30m15s"), ("90s", "1m30s")];
+
+    for (input, expected) in cases {
+        let duration = parse_duration(input).unwrap();
+        assert_eq!(format_duration(duration), expected);
+    }
+
+    assert!(parse_duration("").is_none());
+    assert!(parse_duration("10").is_none());
+    assert!(parse_duration("1d").is_none());
+}
+
+#[tokio::test]
+async fn expect_secret_metas_to_be_redacted() {
+    let fake = FakeNomad::new()
+        .with_parameterized_job("deploy", &["env", "api_key"], &[], &[("deploy", "done")])
+        .with_job_meta("deploy", "noumead.api_key.secret", "true");
+    let client = common::serve(Arc::new(fake));
+
+    let spec = client.inspect("default", "deploy").await.unwrap();
+    let secrets = spec::secret_keys(&spec.metas);
+    assert_eq!(secrets, HashSet::from(["api_key".to_string()]));
+
+    let fields = spec.meta_fields().unwrap();
+    assert!(!fields[0].secret);
+    assert!(fields[1].secret);
+
+    let values = metas(&[("env", "prod"), ("api_key", "s3cr3t")]);
+    assert_eq!(meta::redact_metas(&values, &secrets), metas(&[("env", "prod"), ("api_key", meta::REDACTED)]));
+
+    let payload = DispatchPayload::new(values.clone(), None).unwrap().redacted(&secrets, |_| None).unwrap();
+    assert!(!payload.decoded_payload().unwrap().contains("s3cr3t"));
+    assert_eq!(payload.metas.get("api_key").map(String::as_str), Some(meta::REDACTED));
+
+    // a secret escaped in the json payload is redacted as well
+    let escaped = metas(&[("env", "prod"), ("api_key", "s3\"cr\\3t-é")]);
+    let payload = DispatchPayload::new(escaped, None).unwrap().redacted(&secrets, |_| None).unwrap();
+    let decoded = payload.decoded_payload().unwrap();
+    assert!(!decoded.contains("cr") && decoded.contains(meta::REDACTED), "{decoded}");
+
+    // a payload rendered from a template is rendered again from the redacted metas
+    let template = |metas: &HashMap<String, String>| Some(format!("key: {}", metas["api_key"]));
+    let payload = DispatchPayload::new(values.clone(), template(&values)).unwrap().redacted(&secrets, template).unwrap();
+    assert_eq!(payload.decoded_payload().unwrap(), format!("key: {}", meta::REDACTED));
+
+    // the dispatched jobs inherit the convention of their parent
+    let jobs = client.parameterized_jobs().await.unwrap();
+    client.dispatch(&jobs[0], values).await.unwrap();
+
+    let status = client.dispatch_status(Some("deploy"), None, 0, Duration::from_millis(10)).await.unwrap().0;
+    let dispatched = &status[0].dispatched[0];
+    assert_eq!(dispatched.metas.get("env").map(String::as_str), Some("prod"));
+    assert_eq!(dispatched.metas.get("api_key").map(String::as_str), Some(meta::REDACTED));
+
+    // the clones of the secret keys share the keys
+    let keys = SecretKeys::default();
+    keys.clone().extend(&secrets);
+    assert!(keys.contains("api_key"));
+    assert!(!keys.contains("env"));
+}
@@ -0,0 +1,202 @@
+mod common;
+
+use std::convert::Infallible;
+use std::net::TcpListener;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use hyper::{Body, HeaderMap, Request, Response, Server};
+use hyper::service::{make_service_fn, service_fn};
+use noumead::Error;
+use noumead::nomad::api::{self, NomadApi};
+use noumead::nomad::fake::FakeNomad;
+use noumead::nomad::stream::StdKind;
+use noumead::rest::{DebugHook, DebugRecord, RestHandler, RestOptions};
+
+type Recorded = Arc<Mutex<Vec<(String, HeaderMap)>>>;
+
+/// Start a server which records the uri and the headers of the requests, then replies after the delay with
+/// empty logs or an empty list. The uri is absolute when the server is used as a proxy
+///
+/// # Arguments
+///
+/// * `delay` - Duration
+fn record_after(delay: Duration) -> (String, Recorded) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let requests = Arc::new(Mutex::new(Vec::new()));
+
+    let recorded = requests.clone();
+    let make_svc = make_service_fn(move |_| {
+        let recorded = recorded.clone();
+        async move {
+            Ok::<_, Infallible>(service_fn(move |req: Request<Body>| {
+                recorded.lock().unwrap().push((req.uri().to_string(), req.headers().clone()));
+                let body = match req.uri().path().contains("/fs/logs/") {
+                    true => "{}",
+                    false => "[]"
+                };
+
+                async move {
+                    tokio::time::sleep(delay).await;
+                    Ok::<_, Infallible>(Response::new(Body::from(body)))
+                }
+            }))
+        }
+    });
+
+    tokio::spawn(Server::from_tcp(listener).unwrap().serve(make_svc));
+
+    (format!("http://{addr}"), requests)
+}
+
+fn record() -> (String, Recorded) {
+    record_after(Duration::ZERO)
+}
+
+fn uris(recorded: &Recorded) -> Vec<String> {
+    recorded.lock().unwrap().iter().map(|(uri, _)| uri.clone()).collect()
+}
+
+#[tokio::test]
+async fn expect_endpoints_to_be_joined_to_the_path_prefix() {
+    let (addr, recorded) = record();
+
+    for base_url in [addr.clone(), format!("{addr}/"), format!("{addr}/nomad"), format!("{addr}/nomad/")] {
+        let handler = RestHandler::new(Some(base_url), None, RestOptions::default()).unwrap();
+        handler.jobs().await.unwrap();
+    }
+
+    assert_eq!(uris(&recorded), vec![
+        "/v1/jobs?meta=true&namespace=*",
+        "/v1/jobs?meta=true&namespace=*",
+        "/nomad/v1/jobs?meta=true&namespace=*",
+        "/nomad/v1/jobs?meta=true&namespace=*"
+    ]);
+}
+
+#[test]
+fn expect_dispatch_endpoint_to_be_relative_to_the_address() {
+    assert_eq!(api::dispatch_endpoint("default", "busybox"), "v1/job/busybox/dispatch?namespace=default");
+}
+
+#[tokio::test]
+async fn expect_invalid_addresses_to_be_refused() {
+    for address in ["localhost:4646", "ftp://nomad:4646", "not an url"] {
+        let res = RestHandler::new(Some(address.to_string()), None, RestOptions::default());
+        assert!(matches!(res, Err(Error::NomadReqErr(_))), "{address}");
+    }
+}
+
+#[tokio::test]
+async fn expect_requests_to_go_through_the_proxy() {
+    let (proxy, proxied) = record();
+    let options = RestOptions { proxy: Some(proxy), ..Default::default() };
+
+    let handler = RestHandler::new(Some("http://nomad.internal:4646".to_string()), None, options.clone()).unwrap();
+    handler.jobs().await.unwrap();
+    assert_eq!(uris(&proxied), vec!["http://nomad.internal:4646/v1/jobs?meta=true&namespace=*"]);
+
+    // the hosts of NO_PROXY are reached directly
+    std::env::set_var("NO_PROXY", "127.0.0.1");
+    let (addr, direct) = record();
+    let handler = RestHandler::new(Some(addr), None, options).unwrap();
+    handler.jobs().await.unwrap();
+
+    assert_eq!(proxied.lock().unwrap().len(), 1);
+    assert_eq!(direct.lock().unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn expect_custom_headers_to_be_sent() {
+    let (addr, recorded) = record();
+    let options = RestOptions {
+        headers: vec![("X-Gateway-Key".to_string(), "s3cr3t".to_string()), ("X-Team".to_string(), "data".to_string())],
+        ..Default::default()
+    };
+
+    let handler = RestHandler::new(Some(addr), Some("token".to_string()), options).unwrap();
+    handler.jobs().await.unwrap();
+
+    let headers = recorded.lock().unwrap()[0].1.clone();
+    assert_eq!(headers["x-gateway-key"], "s3cr3t");
+    assert_eq!(headers["x-team"], "data");
+    assert_eq!(headers["x-nomad-token"], "token");
+
+    let invalid = RestOptions { headers: vec![("X Gateway".to_string(), "key".to_string())], ..Default::default() };
+    let res = RestHandler::new(Some("http://127.0.0.1:4646".to_string()), None, invalid);
+    assert!(matches!(res, Err(Error::NomadReqErr(_))));
+}
+
+#[tokio::test]
+async fn expect_requests_and_responses_to_be_given_to_the_debug_hook() {
+    let (addr, _) = record();
+    let records: Arc<Mutex<Vec<DebugRecord>>> = Arc::new(Mutex::new(Vec::new()));
+    let collected = records.clone();
+    let options = RestOptions {
+        debug: Some(DebugHook::new(move |record| collected.lock().unwrap().push(record))),
+        ..Default::default()
+    };
+
+    let handler = RestHandler::new(Some(addr.clone()), Some("s3cr3t-token".to_string()), options).unwrap();
+    handler.jobs().await.unwrap();
+
+    let records = records.lock().unwrap();
+    assert_eq!(records.len(), 2);
+    assert_eq!(records[0].title, format!(">>> GET {addr}/v1/jobs?meta=true&namespace=*"));
+    assert!(!records[0].details.contains("s3cr3t-token"), "{}", records[0].details);
+    assert!(records[1].title.starts_with("<<< 200"), "{}", records[1].title);
+    assert!(records[1].details.ends_with("[]"), "{}", records[1].details);
+}
+
+#[tokio::test]
+async fn expect_stuck_requests_to_time_out_without_retry() {
+    let (addr, _) = record_after(Duration::from_secs(5));
+    let options = RestOptions { request_timeout: Some(Duration::from_millis(200)), ..Default::default() };
+    let handler = RestHandler::new(Some(addr), None, options).unwrap();
+
+    let start = Instant::now();
+    let res = handler.jobs().await;
+
+    assert!(matches!(res, Err(Error::Timeout(_))));
+    assert!(start.elapsed() < Duration::from_secs(2));
+}
+
+#[tokio::test]
+async fn expect_blocking_queries_and_logs_to_have_their_own_timeout() {
+    let (addr, _) = record_after(Duration::from_millis(400));
+    let options = RestOptions {
+        request_timeout: Some(Duration::from_millis(200)),
+        log_poll_timeout: Some(Duration::from_secs(2)),
+        ..Default::default()
+    };
+    let handler = RestHandler::new(Some(addr), None, options).unwrap();
+
+    // the wait of the blocking query is added to the request timeout
+    handler.dispatched_jobs("report", 1, Duration::from_millis(500)).await.unwrap();
+    // each poll of the logs is bounded by its own timeout
+    handler.logs("alloc", "main", &StdKind::Stdout, 0).await.unwrap();
+
+    assert!(matches!(handler.jobs().await, Err(Error::Timeout(_))));
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn expect_requests_to_go_through_the_unix_socket() {
+    let path = common::fixture::temp_path("rest.sock");
+    let _ = std::fs::remove_file(&path);
+
+    let api = Arc::new(FakeNomad::new().with_parameterized_job("report", &["date"], &[], &[("main", "done")]));
+    let client = common::serve_unix(api.clone(), &path);
+
+    // the socket exposes the same api as the http address
+    let jobs = client.parameterized_jobs().await.unwrap();
+    let http_jobs = common::serve(api).parameterized_jobs().await.unwrap();
+    assert_eq!(jobs.len(), 1);
+    assert_eq!(jobs[0].id, http_jobs[0].id);
+
+    // errors of Nomad are returned through the socket as well
+    let res = client.inspect("default", "unknown").await;
+    assert!(matches!(res, Err(Error::Api { status: 404, .. })));
+
+    std::fs::remove_file(&path).unwrap();
+}
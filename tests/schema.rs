@@ -0,0 +1,116 @@
+mod common;
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use noumead::nomad::fake::FakeNomad;
+use noumead::nomad::meta::MetaType;
+use noumead::schema::{Drift, Schema};
+use common::fixture::{self, Fixtures};
+
+const TOML_SCHEMA: &str = r#"
+[jobs.encode]
+payload = '{"input": "{{input}}", "quality": "{{quality}}"}'
+
+[jobs.encode.metas.input]
+description = "S3 key of the video to encode"
+pattern = "s3://.+"
+
+[jobs.encode.metas.quality]
+type = "int"
+default = "720"
+choices = ["480", "720", "1080"]
+
+[jobs.encode.metas.preset]
+description = "Removed from the job"
+"#;
+
+const YAML_SCHEMA: &str = r#"
+jobs:
+  encode:
+    metas:
+      input:
+        description: S3 key of the video to encode
+        secret: true
+"#;
+
+#[test]
+fn expect_to_load_toml_and_yaml_schemas() {
+    let toml = Schema::load(&fixture::temp_file("schema.toml", TOML_SCHEMA)).unwrap();
+    let encode = toml.job("encode").unwrap();
+    assert!(encode.payload.is_some());
+    assert_eq!(encode.metas["quality"].kind, Some(MetaType::Int));
+    assert_eq!(encode.metas["quality"].choices, vec!["480", "720", "1080"]);
+
+    let yaml = Schema::load(&fixture::temp_file("schema.yaml", YAML_SCHEMA)).unwrap();
+    let encode = yaml.job("encode").unwrap();
+    assert!(encode.payload.is_none());
+    assert!(encode.metas["input"].secret);
+
+    assert!(Schema::load(&fixture::temp_file("schema.json", "{}")).is_err());
+}
+
+#[tokio::test]
+async fn expect_schema_to_override_job_metas() {
+    let client = common::serve(Arc::new(FakeNomad::new().with_encode()));
+    let schema = Schema::load(&fixture::temp_file("override.toml", TOML_SCHEMA)).unwrap();
+
+    let spec = client.inspect("default", "encode").await.unwrap();
+    let spec = schema.job("encode").unwrap().apply(spec);
+
+    let fields = spec.meta_fields().unwrap();
+    assert_eq!(fields[0].description.as_deref(), Some("S3 key of the video to encode"));
+    assert_eq!(fields[1].kind, MetaType::Int);
+    assert_eq!(fields[1].default.as_deref(), Some("720"));
+    assert!(fields[0].validate("videos/cat.mp4").is_err());
+}
+
+#[tokio::test]
+async fn expect_schema_to_be_keyed_by_job_id() {
+    let fake = FakeNomad::new().with_encode().named("Encode videos");
+    let client = common::serve(Arc::new(fake));
+    let schema = Schema::load(&fixture::temp_file("keyed.toml", TOML_SCHEMA)).unwrap();
+
+    let jobs = client.parameterized_jobs().await.unwrap();
+    assert!(schema.job(&jobs[0].name).is_none());
+
+    let spec = client.spec(&jobs[0]).await.unwrap();
+    let spec = schema.job(&jobs[0].id).unwrap().apply(spec);
+    assert_eq!(spec.meta_fields().unwrap()[1].kind, MetaType::Int);
+}
+
+#[tokio::test]
+async fn expect_to_dispatch_rendered_payload() {
+    let fake = Arc::new(FakeNomad::new().with_encode());
+    let client = common::serve(fake.clone());
+    let schema = Schema::load(&fixture::temp_file("payload.toml", TOML_SCHEMA)).unwrap();
+    let job_schema = schema.job("encode").unwrap();
+
+    let metas = HashMap::from([
+        ("input".to_string(), "s3://videos/cat.mp4".to_string()),
+        ("quality".to_string(), "1080".to_string()),
+        ("bucket".to_string(), "videos".to_string())
+    ]);
+
+    let payload = job_schema.render_payload(&metas).unwrap();
+    assert_eq!(payload, r#"{"input": "s3://videos/cat.mp4", "quality": "1080"}"#);
+
+    let jobs = client.parameterized_jobs().await.unwrap();
+    client.dispatch_with_payload(&jobs[0], metas, payload.clone()).await.unwrap();
+
+    let dispatched = fake.dispatched();
+    assert_eq!(dispatched[0].1.payload, base64::encode(payload));
+}
+
+#[tokio::test]
+async fn expect_check_to_report_drifts() {
+    let client = common::serve(Arc::new(FakeNomad::new().with_encode()));
+    let schema = Schema::load(&fixture::temp_file("drift.toml", TOML_SCHEMA)).unwrap();
+
+    let spec = client.inspect("default", "encode").await.unwrap();
+    let drifts = schema.job("encode").unwrap().check("encode", &spec);
+
+    assert_eq!(drifts, vec![
+        Drift::MissingKey { job: "encode".to_string(), key: "bucket".to_string() },
+        Drift::RemovedKey { job: "encode".to_string(), key: "preset".to_string() }
+    ]);
+}
@@ -0,0 +1,127 @@
+mod common;
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use futures::StreamExt;
+use tokio::time::Duration;
+use noumead::Error;
+use noumead::nomad::fake::FakeNomad;
+use noumead::nomad::meta;
+use noumead::nomad::summary::DispatchState;
+use noumead::schema::Schema;
+
+#[tokio::test]
+async fn expect_to_get_status_of_dispatched_jobs() {
+    let fake = FakeNomad::new()
+        .with_parameterized_job("busybox", &["word"], &[], &[("test", "foo\n")])
+        .with_parameterized_job("alpine", &[], &[], &[("test", "bar\n")]);
+    let client = common::serve(Arc::new(fake));
+
+    let jobs = client.parameterized_jobs().await.unwrap();
+    let busybox = jobs.iter().find(|j| j.id == "busybox").unwrap();
+
+    let first = client.dispatch(busybox, HashMap::from([("word".to_string(), "foo".to_string())])).await.unwrap();
+    let second = client.dispatch(busybox, HashMap::from([("word".to_string(), "bar".to_string())])).await.unwrap();
+
+    // follow the first job until it finish
+    let alloc = client.allocation("default", &first.dispatch_id).await.unwrap();
+    let _: Vec<_> = client.follow_logs(&alloc, "test").collect().await;
+
+    let (status, index) = client.dispatch_status(Some("busybox"), None, 0, Duration::ZERO).await.unwrap();
+    assert!(index > 0);
+    assert_eq!(status.len(), 1);
+
+    let parent = &status[0];
+    assert_eq!(parent.job, "busybox");
+    assert_eq!(parent.children.dead, 1);
+    assert_eq!(parent.children.running, 1);
+
+    // the most recent dispatch comes first
+    let ids: Vec<&str> = parent.dispatched.iter().map(|d| d.id.as_str()).collect();
+    assert_eq!(ids, vec![second.dispatch_id.as_str(), first.dispatch_id.as_str()]);
+
+    let (running, complete) = (&parent.dispatched[0], &parent.dispatched[1]);
+    assert_eq!(running.state, DispatchState::Running);
+    assert_eq!(running.metas.get("word"), Some(&"bar".to_string()));
+    assert!(running.finished_at.is_none());
+
+    assert_eq!(complete.state, DispatchState::Complete);
+    assert_eq!(complete.metas.get("word"), Some(&"foo".to_string()));
+    assert!(complete.finished_at.unwrap() >= complete.submitted_at.unwrap());
+}
+
+#[tokio::test]
+async fn expect_to_redispatch_only_the_parameterized_metas() {
+    let fake = Arc::new(FakeNomad::new()
+        .with_parameterized_job("busybox", &["word"], &["bar"], &[("test", "foo\n")])
+        .with_job_meta("busybox", "owner", "ops"));
+    let client = common::serve(fake.clone());
+
+    let jobs = client.parameterized_jobs().await.unwrap();
+    client.dispatch(&jobs[0], HashMap::from([("word".to_string(), "foo".to_string())])).await.unwrap();
+
+    // the dispatched job inherit the meta of its parent, which can't be dispatched again
+    let (status, _) = client.dispatch_status(Some("busybox"), None, 0, Duration::ZERO).await.unwrap();
+    let child = &status[0].dispatched[0];
+    assert_eq!(child.metas.get("owner"), Some(&"ops".to_string()));
+    let err = client.dispatch(&jobs[0], child.metas.clone()).await.unwrap_err();
+    assert!(matches!(err, Error::Api { status: 400, ref body, .. } if body.contains("unpermitted")));
+
+    let spec = client.spec(&jobs[0]).await.unwrap();
+    let metas = spec.dispatch_metas(&child.metas).unwrap();
+    assert_eq!(metas, HashMap::from([("word".to_string(), "foo".to_string())]));
+
+    client.dispatch(&jobs[0], metas.clone()).await.unwrap();
+    assert_eq!(fake.dispatched()[1].1.metas, metas);
+}
+
+#[tokio::test]
+async fn expect_secrets_of_the_schema_to_be_redacted_and_not_redispatched() {
+    let fake = FakeNomad::new()
+        .with_parameterized_job("deploy", &["env", "api_key"], &[], &[("main", "deployed\n")]);
+    let client = common::serve(Arc::new(fake));
+    let schema: Schema = toml::from_str("[jobs.deploy.metas.api_key]\nsecret = true\n").unwrap();
+
+    let jobs = client.parameterized_jobs().await.unwrap();
+    let metas = HashMap::from([("env".to_string(), "prod".to_string()), ("api_key".to_string(), "s3cr3t".to_string())]);
+    client.dispatch(&jobs[0], metas).await.unwrap();
+
+    // the job does not follow the convention, only the schema knows that the meta is a secret
+    let (status, _) = client.dispatch_status(Some("deploy"), None, 0, Duration::ZERO).await.unwrap();
+    assert_eq!(status[0].dispatched[0].metas.get("api_key"), Some(&"s3cr3t".to_string()));
+
+    let (status, _) = client.dispatch_status(Some("deploy"), Some(&schema), 0, Duration::ZERO).await.unwrap();
+    let child = &status[0].dispatched[0];
+    assert_eq!(child.metas.get("api_key"), Some(&meta::REDACTED.to_string()));
+    assert_eq!(child.metas.get("env"), Some(&"prod".to_string()));
+
+    let spec = schema.job("deploy").unwrap().apply(client.spec(&jobs[0]).await.unwrap());
+    let err = spec.dispatch_metas(&child.metas).unwrap_err();
+    assert!(matches!(err, Error::InvalidMeta { ref key, .. } if key == "api_key"));
+
+    // a redacted value is never dispatched, even when the spec does not mark the meta as secret
+    let spec = client.spec(&jobs[0]).await.unwrap();
+    assert!(spec.dispatch_metas(&child.metas).is_err());
+}
+
+#[tokio::test]
+async fn expect_allocations_of_dead_jobs_to_be_listed_once() {
+    let fake = Arc::new(FakeNomad::new().with_parameterized_job("busybox", &[], &[], &[("test", "foo\n")]));
+    let client = common::serve(fake.clone());
+
+    let jobs = client.parameterized_jobs().await.unwrap();
+    let first = client.dispatch(&jobs[0], HashMap::new()).await.unwrap();
+    client.dispatch(&jobs[0], HashMap::new()).await.unwrap();
+
+    let alloc = client.allocation("default", &first.dispatch_id).await.unwrap();
+    let _: Vec<_> = client.follow_logs(&alloc, "test").collect().await;
+    let listed = fake.listed_allocations().len();
+
+    // only the dead job has a finish time, which is read once from its allocations
+    for _ in 0..3 {
+        let (status, _) = client.dispatch_status(Some("busybox"), None, 0, Duration::ZERO).await.unwrap();
+        assert!(status[0].dispatched.iter().any(|d| d.finished_at.is_some()));
+    }
+
+    assert_eq!(fake.listed_allocations()[listed..], [first.dispatch_id]);
+}
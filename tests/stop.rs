@@ -0,0 +1,59 @@
+mod common;
+
+use std::sync::Arc;
+use noumead::Error;
+use noumead::nomad::fake::FakeNomad;
+
+#[tokio::test]
+async fn expect_to_stop_running_jobs() {
+    let fake = FakeNomad::new()
+        .with_parameterized_job("busybox", &["word"], &[], &[("test", "foo")])
+        .with_job("web", "running")
+        .with_job("batch", "dead");
+
+    let client = common::serve(Arc::new(fake));
+
+    let jobs = client.running_jobs().await.unwrap();
+    let ids: Vec<&str> = jobs.iter().map(|j| j.id.as_str()).collect();
+    assert_eq!(ids, vec!["web"]);
+
+    let res = client.stop(&jobs).await;
+    assert!(res.iter().all(Result::is_ok));
+
+    let jobs = client.running_jobs().await.unwrap();
+    assert!(jobs.is_empty());
+}
+
+#[tokio::test]
+async fn expect_stop_of_missing_job_to_fail_with_hint() {
+    let fake = FakeNomad::new().with_job("web", "running");
+    let client = common::serve(Arc::new(fake));
+
+    let mut jobs = client.running_jobs().await.unwrap();
+    jobs[0].id = "unknown".to_string();
+
+    let res = client.stop(&jobs).await;
+    let err = res.into_iter().next().unwrap().unwrap_err();
+
+    assert!(matches!(err, Error::Api { status: 404, .. }));
+    assert!(err.hint().is_some());
+}
+
+#[tokio::test]
+async fn expect_purged_jobs_to_be_removed() {
+    let fake = FakeNomad::new()
+        .with_job("web", "running")
+        .with_job("api", "running");
+
+    let client = common::serve(Arc::new(fake));
+
+    let jobs = client.running_jobs().await.unwrap();
+    let web: Vec<_> = jobs.into_iter().filter(|j| j.id == "web").collect();
+
+    let res = client.purge(&web).await;
+    assert!(res.iter().all(Result::is_ok));
+
+    let jobs = client.jobs().await.unwrap();
+    let ids: Vec<&str> = jobs.iter().map(|j| j.id.as_str()).collect();
+    assert_eq!(ids, vec!["api"]);
+}
@@ -0,0 +1,56 @@
+mod common;
+
+use std::fs;
+use chrono::{Duration, Utc};
+use noumead::nomad::job::Job;
+use noumead::usage::Usage;
+use common::fixture;
+
+fn job(namespace: &str, id: &str) -> Job {
+    Job {
+        id: id.to_string(),
+        name: id.to_string(),
+        namespace: namespace.to_string(),
+        parameterized: true,
+        ..Default::default()
+    }
+}
+
+#[test]
+fn expect_recent_and_frequent_jobs_first() {
+    let now = Utc::now();
+    let jobs = vec![job("default", "alpine"), job("default", "busybox"), job("default", "debian"), job("staging", "busybox")];
+
+    let mut usage = Usage::default();
+    // debian was dispatched often a long time ago, busybox once today
+    for _ in 0..5 {
+        usage.record(&jobs[2], now - Duration::days(120));
+    }
+    usage.record(&jobs[1], now);
+
+    assert_eq!(usage.frecency(&jobs[1], now), 100);
+    assert_eq!(usage.frecency(&jobs[2], now), 50);
+    assert_eq!(usage.frecency(&jobs[3], now), 0);
+
+    // jobs which have never been dispatched keep their order
+    assert_eq!(usage.rank(&jobs, now), vec![1, 2, 0, 3]);
+}
+
+#[test]
+fn expect_usage_to_be_saved_and_loaded() {
+    let path = fixture::temp_path("usage.json");
+    let _ = fs::remove_file(&path);
+
+    assert!(Usage::load(&path).unwrap().jobs.is_empty());
+
+    let mut usage = Usage::default();
+    usage.record(&job("default", "busybox"), Utc::now());
+    usage.record(&job("default", "busybox"), Utc::now());
+    usage.save(&path).unwrap();
+
+    let loaded = Usage::load(&path).unwrap();
+    assert_eq!(loaded.jobs["default/busybox"].count, 2);
+
+    fs::write(&path, "not json").unwrap();
+    assert!(Usage::load(&path).is_err());
+}
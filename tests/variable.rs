@@ -0,0 +1,110 @@
+mod common;
+
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+use noumead::Error;
+use noumead::nomad::fake::FakeNomad;
+use noumead::nomad::variable::{self, Variable};
+use common::fixture::Fixtures;
+
+#[tokio::test]
+async fn expect_to_list_variables_of_namespace() {
+    let client = common::serve(Arc::new(FakeNomad::new().with_variables()));
+
+    let paths: Vec<String> = client.variables("default", "deploy/").await.unwrap()
+        .into_iter()
+        .map(|v| v.path)
+        .collect();
+    assert_eq!(paths, vec!["deploy/api", "deploy/db"]);
+
+    let staging = client.variables("staging", "").await.unwrap();
+    assert_eq!(staging.len(), 1);
+    assert_eq!(staging[0].namespace, "staging");
+}
+
+#[tokio::test]
+async fn expect_to_read_item_of_variable() {
+    let client = common::serve(Arc::new(FakeNomad::new().with_variables()));
+
+    let api = client.variable("default", "deploy/api").await.unwrap();
+    assert_eq!(api.item(None).unwrap(), "s3cr3t");
+    assert_eq!(api.item(Some("key")).unwrap(), "s3cr3t");
+    assert!(!format!("{api:?}").contains("s3cr3t"));
+
+    let db = client.variable("default", "deploy/db").await.unwrap();
+    assert_eq!(db.item(Some("password")).unwrap(), "hunter2");
+    assert!(matches!(db.item(None), Err(Error::Variable(_))));
+    assert!(matches!(db.item(Some("host")), Err(Error::Variable(_))));
+
+    let staging = client.variable("staging", "deploy/api").await.unwrap();
+    assert_eq!(staging.item(None).unwrap(), "staging");
+
+    match client.variable("default", "deploy/cache").await {
+        Err(Error::Api { status, .. }) => assert_eq!(status, 404),
+        res => panic!("unexpected result {res:?}")
+    }
+}
+
+#[tokio::test]
+async fn expect_put_and_delete_to_check_and_set() {
+    let fake = Arc::new(FakeNomad::new().with_variables());
+    let client = common::serve(fake.clone());
+
+    let current = client.variable("default", "deploy/api").await.unwrap();
+    let items = variable::parse_items(r#"{"key": "rotated", "ttl": 60}"#).unwrap();
+    let updated = Variable::new("default", "deploy/api", items);
+
+    let written = client.put_variable(&updated, Some(current.modify_index)).await.unwrap();
+    assert!(written.modify_index > current.modify_index);
+    assert_eq!(written.item(Some("ttl")).unwrap(), "60");
+
+    // the variable has been modified since it has been read
+    match client.put_variable(&updated, Some(current.modify_index)).await {
+        Err(Error::Api { status, body, .. }) => {
+            assert_eq!(status, 409);
+            assert!(!body.contains("rotated"));
+        },
+        res => panic!("unexpected result {res:?}")
+    }
+
+    // 0 means that the variable must not exist
+    assert!(client.put_variable(&Variable::new("default", "deploy/api", BTreeMap::new()), Some(0)).await.is_err());
+    let created = client.put_variable(&Variable::new("staging", "deploy/db", BTreeMap::new()), Some(0)).await.unwrap();
+
+    assert!(client.delete_variable("staging", "deploy/db", Some(current.modify_index)).await.is_err());
+    client.delete_variable("staging", "deploy/db", Some(created.modify_index)).await.unwrap();
+    assert_eq!(client.variables("staging", "deploy/db").await.unwrap().len(), 0);
+
+    assert!(variable::parse_items(r#"{"key": {"nested": true}}"#).is_err());
+    assert!(variable::parse_items("[]").is_err());
+}
+
+#[tokio::test]
+async fn expect_values_read_from_variables_to_be_validated_against_the_meta() {
+    let fake = FakeNomad::new()
+        .with_encode()
+        .with_variable("default", "encode/valid", &[("input", "s3://videos/cat.mp4"), ("quality", "720p")])
+        .with_variable("default", "encode/invalid", &[("input", "s3://videos/cat.avi"), ("quality", "4k")]);
+    let client = common::serve(Arc::new(fake));
+    let spec = client.inspect("default", "encode").await.unwrap();
+
+    let read = |path: &'static str| async {
+        let var = client.variable("default", path).await.unwrap();
+        ["input", "quality"].into_iter()
+            .map(|key| (key.to_string(), var.item(Some(key)).unwrap().clone()))
+            .collect::<HashMap<String, String>>()
+    };
+
+    let valid = read("encode/valid").await;
+    assert!(spec.resolve_metas(valid.clone()).is_ok());
+
+    let invalid = read("encode/invalid").await;
+    for key in ["input", "quality"] {
+        let mut metas = valid.clone();
+        metas.insert(key.to_string(), invalid[key].clone());
+        match spec.resolve_metas(metas) {
+            Err(Error::InvalidMeta { key: invalid_key, .. }) => assert_eq!(invalid_key, key),
+            res => panic!("unexpected result {res:?}")
+        }
+    }
+}